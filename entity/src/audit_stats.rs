@@ -29,6 +29,9 @@ pub struct Model {
     pub success_rate_four_fours_headers: f32,
     pub success_rate_four_fours_bodies: f32,
     pub success_rate_four_fours_receipts: f32,
+    /// The `--run-label` of the audit process, if it had one, whose audits the
+    /// rates were taken from.
+    pub label: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -57,6 +60,7 @@ pub async fn create(
     success_rate_four_fours_headers: f32,
     success_rate_four_fours_bodies: f32,
     success_rate_four_fours_receipts: f32,
+    label: Option<String>,
     conn: &DatabaseConnection,
 ) -> Result<Model> {
     let audit_stats = ActiveModel {
@@ -80,16 +84,26 @@ pub async fn create(
         success_rate_four_fours_headers: Set(success_rate_four_fours_headers),
         success_rate_four_fours_bodies: Set(success_rate_four_fours_bodies),
         success_rate_four_fours_receipts: Set(success_rate_four_fours_receipts),
+        label: Set(label),
     };
     Ok(audit_stats.insert(conn).await?)
 }
 
-/// Get the most recent audit stat series of the last 7 days.
-pub async fn get_recent_stats(conn: &DatabaseConnection) -> Result<Vec<Model>, DbErr> {
+/// Get the most recent audit stat series of the last 7 days, recorded by the audit
+/// process with the given label, or by one without a label.
+pub async fn get_recent_stats(
+    label: Option<&str>,
+    conn: &DatabaseConnection,
+) -> Result<Vec<Model>, DbErr> {
     let one_week_ago = Utc::now() - Duration::days(7);
+    let label = match label {
+        Some(label) => Column::Label.eq(label),
+        None => Column::Label.is_null(),
+    };
 
     Entity::find()
         .filter(Column::Timestamp.gt(one_week_ago))
+        .filter(label)
         .order_by_asc(Column::Timestamp)
         .all(conn)
         .await
@@ -1,11 +1,17 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.10.7
 use crate::utils;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use ethereum_types::H256;
 use ethportal_api::utils::bytes::{hex_encode, hex_encode_compact};
-use ethportal_api::OverlayContentKey;
-use sea_orm::{entity::prelude::*, ActiveValue::NotSet, Set};
+use ethportal_api::{HistoryContentKey, OverlayContentKey};
+use sea_orm::{
+    entity::prelude::*,
+    sea_query::{Expr, OnConflict},
+    ActiveValue::NotSet,
+    Set,
+};
+use std::fmt;
 
 /// Portal network sub-protocol. History, state, transactions etc.
 #[derive(Debug, Clone, Eq, PartialEq, EnumIter, DeriveActiveEnum)]
@@ -36,6 +42,12 @@ pub struct Model {
     #[sea_orm(unique)]
     pub content_id: Vec<u8>,
     pub first_available_at: DateTime<Utc>,
+    /// Audits recorded of the content, kept up to date as audits are stored.
+    ///
+    /// Audits where the request to the node failed are not counted.
+    pub audit_count: i32,
+    /// Those audits that passed.
+    pub audit_success_count: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -60,29 +72,95 @@ impl Related<super::execution_metadata::Entity> for Entity {
 
 impl ActiveModelBehavior for ActiveModel {}
 
+/// A content id that does not match the one derived from its content key.
+///
+/// Points to a bug in whatever stored the content, and audits of it would check
+/// the wrong id, so the content is rejected rather than used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentIdMismatch {
+    pub content_key: String,
+    pub content_id: String,
+    pub expected_content_id: String,
+}
+
+impl fmt::Display for ContentIdMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "content id {} stored for content key {} does not match the derived id {}",
+            self.content_id, self.content_key, self.expected_content_id
+        )
+    }
+}
+
+impl std::error::Error for ContentIdMismatch {}
+
+/// Checks that a content id is the one derived from the content key.
+pub fn verify_content_id<T: OverlayContentKey>(
+    content_key: &T,
+    content_id: &[u8],
+) -> Result<(), ContentIdMismatch> {
+    let expected_content_id = content_key.content_id();
+    if content_id == expected_content_id {
+        return Ok(());
+    }
+    Err(ContentIdMismatch {
+        content_key: hex_encode(content_key.to_bytes()),
+        content_id: hex_encode(content_id),
+        expected_content_id: hex_encode(expected_content_id),
+    })
+}
+
+/// Looks up the content for a key, storing it if it is new.
+///
+/// The insert is skipped if another writer stores the same key first, and the
+/// record it stored is returned instead, so running a backfill twice is safe.
+///
+/// An existing record whose content id does not match the key is returned as a
+/// [`ContentIdMismatch`] error.
 pub async fn get_or_create<T: OverlayContentKey>(
     content_key: &T,
     conn: &DatabaseConnection,
 ) -> Result<Model> {
     // First try to lookup an existing entry.
-    if let Some(content_key_model) = Entity::find()
-        .filter(Column::ContentKey.eq(content_key.to_bytes()))
-        .one(conn)
-        .await?
-    {
+    if let Some(content_key_model) = get(content_key, conn).await? {
+        verify_content_id(content_key, &content_key_model.content_id)?;
         // If there is an existing record, return it
         return Ok(content_key_model);
     }
 
     // If no record exists, create one and return it
-    let content_key = ActiveModel {
+    insert_if_new(content_key, conn).await?;
+    get(content_key, conn)
+        .await?
+        .ok_or_else(|| anyhow!("content key was not stored"))
+}
+
+/// Stores a content key unless it is already stored, returning whether it was new.
+///
+/// When the same key is stored concurrently, only one caller sees it as new.
+pub async fn insert_if_new<T: OverlayContentKey>(
+    content_key: &T,
+    conn: &DatabaseConnection,
+) -> Result<bool> {
+    let new_content = ActiveModel {
         id: NotSet,
         content_id: Set(content_key.content_id().to_vec()),
         content_key: Set(content_key.to_bytes()),
         protocol_id: Set(SubProtocol::History),
         first_available_at: Set(Utc::now()),
+        audit_count: Set(0),
+        audit_success_count: Set(0),
     };
-    Ok(content_key.insert(conn).await?)
+    let inserted = Entity::insert(new_content)
+        .on_conflict(
+            OnConflict::column(Column::ContentKey)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(conn)
+        .await?;
+    Ok(inserted == 1)
 }
 
 pub async fn get<T: OverlayContentKey>(
@@ -95,6 +173,30 @@ pub async fn get<T: OverlayContentKey>(
         .await?)
 }
 
+/// Adds newly stored audits to the audit counts of content.
+///
+/// Run in the transaction storing the audits, so that the counts stay in step with them.
+pub async fn add_audit_counts<C: ConnectionTrait>(
+    id: i32,
+    audits: i32,
+    successes: i32,
+    conn: &C,
+) -> Result<()> {
+    Entity::update_many()
+        .col_expr(
+            Column::AuditCount,
+            Expr::col(Column::AuditCount).add(audits),
+        )
+        .col_expr(
+            Column::AuditSuccessCount,
+            Expr::col(Column::AuditSuccessCount).add(successes),
+        )
+        .filter(Column::Id.eq(id))
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
 /// These are helper functions for glados-web.
 impl Model {
     pub fn id_as_hash(&self) -> H256 {
@@ -121,6 +223,26 @@ impl Model {
         hex_encode_compact(&self.content_key)
     }
 
+    /// The content key in words, such as "Block header 0x…", or "Unknown type" if it
+    /// is not a history network content key.
+    pub fn key_description(&self) -> String {
+        match HistoryContentKey::try_from(self.content_key.clone()) {
+            Ok(HistoryContentKey::BlockHeaderWithProof(key)) => {
+                format!("Block header {}", hex_encode(key.block_hash))
+            }
+            Ok(HistoryContentKey::BlockBody(key)) => {
+                format!("Block body {}", hex_encode(key.block_hash))
+            }
+            Ok(HistoryContentKey::BlockReceipts(key)) => {
+                format!("Receipts {}", hex_encode(key.block_hash))
+            }
+            Ok(HistoryContentKey::EpochAccumulator(key)) => {
+                format!("Epoch accumulator {}", hex_encode(key.epoch_hash))
+            }
+            Err(_) => "Unknown type".to_string(),
+        }
+    }
+
     pub fn available_at_local_time(&self) -> String {
         self.first_available_at
             .with_timezone(&chrono::Local)
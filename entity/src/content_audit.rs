@@ -6,7 +6,13 @@ use chrono::DateTime;
 use chrono::Utc;
 use clap::ValueEnum;
 use ethportal_api::OverlayContentKey;
-use sea_orm::{entity::prelude::*, ActiveValue::NotSet, Set};
+use sea_orm::{
+    entity::prelude::*,
+    ActiveValue::{self, NotSet},
+    Set, TransactionTrait,
+};
+use sea_query::{Expr, Query};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Eq, PartialEq, EnumIter, DeriveActiveEnum)]
 #[sea_orm(rs_type = "i32", db_type = "Integer")]
@@ -15,6 +21,28 @@ pub enum AuditResult {
     Success = 1,
 }
 
+/// Why an audit passed or failed.
+///
+/// Absent for audits recorded before outcomes were classified.
+#[derive(Debug, Clone, Eq, Hash, PartialEq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+pub enum AuditOutcome {
+    /// The node returned valid content.
+    Success = 0,
+    /// The node responded but did not have the content.
+    NotFound = 1,
+    /// The node returned content that failed validation.
+    InvalidContent = 2,
+    /// The node could not be reached or its response could not be read.
+    TransportError = 3,
+    /// The node did not respond within the request timeout.
+    Timeout = 4,
+    /// The node responded with a JSON-RPC error.
+    RpcError = 5,
+    /// The node returned well formed content whose proof did not verify.
+    InvalidProof = 6,
+}
+
 #[derive(Debug, Clone, Eq, Hash, PartialEq, EnumIter, DeriveActiveEnum, ValueEnum)]
 #[clap(rename_all = "snake_case")]
 #[sea_orm(rs_type = "i32", db_type = "Integer")]
@@ -30,6 +58,7 @@ pub enum SelectionStrategy {
     /// 1. Key was audited previously
     /// 2. Latest audit for the key failed (data absent)
     /// 3. Keys sorted by date audited (keys with oldest failed audit first)
+    #[value(name = "recheck_failures", aliases = ["recheck-failures", "failed"])]
     Failed = 2,
     /// Content that is:
     /// 1. Not yet audited.
@@ -39,6 +68,49 @@ pub enum SelectionStrategy {
     SpecificContentKey = 4,
     /// Perform audits of random fourfours data.
     FourFours = 5,
+    /// Content of each block in a range given on the command line.
+    BlockRange = 6,
+    /// The header of a block near the chain tip, enabled with `--follow-head`.
+    #[value(skip)]
+    FollowHead = 7,
+    /// Content that is:
+    /// 1. Not yet audited, or whose latest audit failed or passed longer ago than `--skip-if-passed-within`.
+    /// 2. Not audited within the recheck cooldown.
+    /// 3. Sorted by date entered into glados database (newest first).
+    Unconfirmed = 8,
+    /// Pre-merge content that is:
+    /// 1. Among the first or last `--epoch-boundary-window` blocks of an epoch.
+    /// 2. Not audited within the staleness threshold.
+    /// 3. Sorted by distance from the epoch boundary (nearest first).
+    EpochBoundary = 9,
+}
+
+impl AuditOutcome {
+    pub fn as_text(&self) -> String {
+        match self {
+            AuditOutcome::Success => "success".to_string(),
+            AuditOutcome::NotFound => "not found".to_string(),
+            AuditOutcome::InvalidContent => "invalid content".to_string(),
+            AuditOutcome::TransportError => "transport error".to_string(),
+            AuditOutcome::Timeout => "timeout".to_string(),
+            AuditOutcome::RpcError => "rpc error".to_string(),
+            AuditOutcome::InvalidProof => "invalid proof".to_string(),
+        }
+    }
+
+    /// Outcomes where the request to the node failed, which say nothing about
+    /// whether the content is available.
+    pub fn request_failures() -> [AuditOutcome; 3] {
+        [
+            AuditOutcome::TransportError,
+            AuditOutcome::Timeout,
+            AuditOutcome::RpcError,
+        ]
+    }
+
+    pub fn is_request_failure(&self) -> bool {
+        AuditOutcome::request_failures().contains(self)
+    }
 }
 
 impl AuditResult {
@@ -62,6 +134,23 @@ pub struct Model {
     pub strategy_used: Option<SelectionStrategy>,
     pub result: AuditResult,
     pub trace: String,
+    /// Time taken for the Portal node to respond, absent for early audits.
+    pub duration_ms: Option<i32>,
+    pub outcome: Option<AuditOutcome>,
+    /// Node that returned the content, when the Portal node reports it.
+    pub served_by: Option<i32>,
+    /// Why the request to the Portal node failed, including any JSON-RPC error code and message.
+    pub error: Option<String>,
+    /// The `--run-label` of the audit process that made the audit, if it had one.
+    pub label: Option<String>,
+    /// Responses returning closer peers rather than the content, for failed lookups
+    /// traced by the Portal node.
+    pub closer_peer_responses: Option<i32>,
+    /// Bytes of content returned by the Portal node, whether or not it was valid.
+    pub content_length: Option<i32>,
+    /// Whether the returned content was shown to be the content of the requested content
+    /// id, rather than only well formed. Absent if no content was returned.
+    pub content_id_verified: Option<bool>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -90,6 +179,14 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     Node,
+    #[sea_orm(
+        belongs_to = "super::node::Entity",
+        from = "Column::ServedBy",
+        to = "super::node::Column::Id",
+        on_update = "Cascade",
+        on_delete = "SetNull"
+    )]
+    ServedBy,
 }
 
 impl Related<super::content::Entity> for Entity {
@@ -106,23 +203,85 @@ impl Related<super::client_info::Entity> for Entity {
 
 impl ActiveModelBehavior for ActiveModel {}
 
+#[allow(clippy::too_many_arguments)]
 pub async fn create(
     content_key_model_id: i32,
     client_info_id: i32,
     node_id: i32,
-    query_successful: bool,
+    outcome: AuditOutcome,
     strategy_used: SelectionStrategy,
     trace_string: String,
+    duration_ms: Option<i32>,
+    served_by: Option<i32>,
+    error: Option<String>,
+    label: Option<String>,
     conn: &DatabaseConnection,
 ) -> Result<Model> {
-    // If no record exists, create one and return it
-    let audit_result = if query_successful {
+    let content_audit = new_audit(
+        content_key_model_id,
+        client_info_id,
+        node_id,
+        outcome,
+        strategy_used,
+        trace_string,
+        duration_ms,
+        served_by,
+        error,
+        label,
+    );
+    store(content_audit, conn).await
+}
+
+/// Inserts an audit built by [`new_audit`], counting it in the audit counts of its
+/// content in the same transaction.
+pub async fn store(audit: ActiveModel, conn: &DatabaseConnection) -> Result<Model> {
+    let content_key_model_id = *audit.content_key.as_ref();
+    let (audits, successes) = audit_counts(&audit);
+    let txn = conn.begin().await?;
+    let model = audit.insert(&txn).await?;
+    content::add_audit_counts(content_key_model_id, audits, successes, &txn).await?;
+    txn.commit().await?;
+    Ok(model)
+}
+
+/// The audits and successes that an audit adds to the audit counts of its content.
+///
+/// Audits where the request to the node failed say nothing about the content, so
+/// they are not counted, as in the audit stats.
+fn audit_counts(audit: &ActiveModel) -> (i32, i32) {
+    let request_failed = match &audit.outcome {
+        ActiveValue::Set(outcome) | ActiveValue::Unchanged(outcome) => outcome
+            .as_ref()
+            .is_some_and(AuditOutcome::is_request_failure),
+        ActiveValue::NotSet => false,
+    };
+    if request_failed {
+        return (0, 0);
+    }
+    (1, i32::from(*audit.result.as_ref() == AuditResult::Success))
+}
+
+/// An audit created now, to be inserted along with others by [`create_many`].
+#[allow(clippy::too_many_arguments)]
+pub fn new_audit(
+    content_key_model_id: i32,
+    client_info_id: i32,
+    node_id: i32,
+    outcome: AuditOutcome,
+    strategy_used: SelectionStrategy,
+    trace_string: String,
+    duration_ms: Option<i32>,
+    served_by: Option<i32>,
+    error: Option<String>,
+    label: Option<String>,
+) -> ActiveModel {
+    let audit_result = if outcome == AuditOutcome::Success {
         AuditResult::Success
     } else {
         AuditResult::Failure
     };
 
-    let content_audit = ActiveModel {
+    ActiveModel {
         id: NotSet,
         content_key: Set(content_key_model_id),
         client_info: Set(Some(client_info_id)),
@@ -131,8 +290,43 @@ pub async fn create(
         result: Set(audit_result),
         strategy_used: Set(Some(strategy_used)),
         trace: Set(trace_string),
-    };
-    Ok(content_audit.insert(conn).await?)
+        duration_ms: Set(duration_ms),
+        outcome: Set(Some(outcome)),
+        served_by: Set(served_by),
+        error: Set(error),
+        label: Set(label),
+        closer_peer_responses: Set(None),
+        content_length: Set(None),
+        content_id_verified: Set(None),
+    }
+}
+
+/// Inserts audits in a single statement, returning the number inserted.
+///
+/// The audit counts of their content are updated in the same transaction.
+pub async fn create_many(audits: Vec<ActiveModel>, conn: &DatabaseConnection) -> Result<u64> {
+    let count = audits.len() as u64;
+    if count == 0 {
+        return Ok(0);
+    }
+    // Audits and successes of each content key, in the order of the keys' ids so
+    // that concurrent batches update them in the same order.
+    let mut counts: BTreeMap<i32, (i32, i32)> = BTreeMap::new();
+    for audit in &audits {
+        let (audits, successes) = audit_counts(audit);
+        let (audited, passed) = counts.entry(*audit.content_key.as_ref()).or_default();
+        *audited += audits;
+        *passed += successes;
+    }
+    let txn = conn.begin().await?;
+    Entity::insert_many(audits)
+        .exec_without_returning(&txn)
+        .await?;
+    for (content_key_model_id, (audited, passed)) in counts {
+        content::add_audit_counts(content_key_model_id, audited, passed, &txn).await?;
+    }
+    txn.commit().await?;
+    Ok(count)
 }
 
 pub async fn get_audits<T: OverlayContentKey>(
@@ -148,6 +342,23 @@ pub async fn get_audits<T: OverlayContentKey>(
         .await?)
 }
 
+/// Deletes audits created before `cutoff`, returning the number deleted.
+///
+/// The latest audit of each content key is kept, however old.
+pub async fn delete_older_than(cutoff: DateTime<Utc>, conn: &DatabaseConnection) -> Result<u64> {
+    let latest_audits = Query::select()
+        .expr(Expr::max(Expr::col(Column::Id)))
+        .from(Entity)
+        .group_by_col(Column::ContentKey)
+        .to_owned();
+    let result = Entity::delete_many()
+        .filter(Column::CreatedAt.lt(cutoff))
+        .filter(Column::Id.not_in_subquery(latest_audits))
+        .exec(conn)
+        .await?;
+    Ok(result.rows_affected)
+}
+
 impl SelectionStrategy {
     /// This performs the function of Display, which is not able to be implemented
     /// for this enum.
@@ -162,6 +373,10 @@ impl SelectionStrategy {
             SelectionStrategy::FourFours => "FourFours".to_string(),
             SelectionStrategy::SelectOldestUnaudited => "Select Oldest Unaudited".to_string(),
             SelectionStrategy::SpecificContentKey => "Specific Content Key".to_string(),
+            SelectionStrategy::BlockRange => "Block Range".to_string(),
+            SelectionStrategy::FollowHead => "Follow Head".to_string(),
+            SelectionStrategy::Unconfirmed => "Unconfirmed".to_string(),
+            SelectionStrategy::EpochBoundary => "Epoch Boundary".to_string(),
         }
     }
 }
@@ -176,6 +391,23 @@ impl Model {
     pub fn created_at_humanized(&self) -> String {
         utils::time_ago(self.created_at, Utc::now())
     }
+    /// A convenience method for displaying the outcome.
+    ///
+    /// Early database entries do not have a recorded outcome.
+    pub fn outcome_as_text(&self) -> String {
+        match &self.outcome {
+            Some(o) => o.as_text(),
+            None => "No outcome recorded".to_string(),
+        }
+    }
+    /// A convenience method for displaying whether the content matched its content id.
+    pub fn content_id_verified_as_text(&self) -> String {
+        match self.content_id_verified {
+            Some(true) => "Yes".to_string(),
+            Some(false) => "No".to_string(),
+            None => "Not checked".to_string(),
+        }
+    }
     /// A convenience method for displaying the strategy.
     ///
     /// A few early databse entries do not have a recorded strategy.
@@ -186,3 +418,62 @@ impl Model {
         }
     }
 }
+
+/// Whether content could be retrieved, judged from its audit history.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Availability {
+    /// The content has not been audited.
+    Unknown,
+    /// The latest audit passed.
+    Available,
+    /// The latest audits failed, after the content was last retrieved at this time.
+    MissingSince(DateTime<Utc>),
+    /// Every audit failed.
+    NeverAvailable,
+}
+
+impl Availability {
+    /// Finds when content dropped off the network from the audits of one content key.
+    ///
+    /// The audits may be in any order. The time of the latest success before the current
+    /// run of failures is when the content was last known to be available.
+    pub fn from_audits(audits: &[Model]) -> Self {
+        let Some(latest) = audits.iter().max_by_key(|audit| audit.created_at) else {
+            return Availability::Unknown;
+        };
+        if latest.is_success() {
+            return Availability::Available;
+        }
+        match audits
+            .iter()
+            .filter(|audit| audit.is_success())
+            .map(|audit| audit.created_at)
+            .max()
+        {
+            Some(last_success) => Availability::MissingSince(last_success),
+            None => Availability::NeverAvailable,
+        }
+    }
+
+    /// The time the content was last retrieved, if it is missing now.
+    pub fn missing_since(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Availability::MissingSince(time) => Some(*time),
+            _ => None,
+        }
+    }
+
+    pub fn as_text(&self) -> String {
+        match self {
+            Availability::Unknown => "Not audited yet".to_string(),
+            Availability::Available => "Available".to_string(),
+            Availability::MissingSince(time) => {
+                format!(
+                    "Missing, last available {}",
+                    utils::time_ago(*time, Utc::now())
+                )
+            }
+            Availability::NeverAvailable => "Never available".to_string(),
+        }
+    }
+}
@@ -5,6 +5,7 @@ pub mod prelude;
 pub mod audit_stats;
 pub mod census;
 pub mod census_node;
+pub mod census_result;
 pub mod client_info;
 pub mod content;
 pub mod content_audit;
@@ -12,5 +13,6 @@ pub mod execution_metadata;
 pub mod key_value;
 pub mod node;
 pub mod record;
+pub mod routing_table_snapshot;
 pub mod test;
 pub mod utils;
@@ -1,8 +1,11 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.10.7
-use anyhow::Result;
+use crate::utils;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
 use ethereum_types::U256;
+use ethportal_api::types::enr::Enr;
 use ethportal_api::types::node_id::NodeId;
-use ethportal_api::utils::bytes::hex_encode;
+use ethportal_api::utils::bytes::{hex_encode, hex_encode_compact};
 
 use sea_orm::{
     entity::prelude::*, ActiveValue::NotSet, DatabaseBackend, FromQueryResult, QueryOrder,
@@ -17,6 +20,20 @@ pub struct Model {
     pub id: i32,
     pub node_id: Vec<u8>,
     pub node_id_high: i64,
+    /// Client software advertised by the node, if known.
+    pub client_version: Option<String>,
+    /// When the node last answered a census ping.
+    pub last_seen: Option<DateTime<Utc>>,
+    /// Whether the node answered its latest census ping.
+    pub alive: bool,
+    /// IP address advertised in the node's ENR, for nodes glados queries directly.
+    pub ip: Option<String>,
+    /// UDP port advertised in the node's ENR, for nodes glados queries directly.
+    pub port: Option<i32>,
+    /// When the node's info was last read from the node itself.
+    pub info_updated_at: Option<DateTime<Utc>>,
+    /// Latest ENR seen for the node, base64 encoded.
+    pub enr: Option<String>,
 }
 
 impl Model {
@@ -24,11 +41,57 @@ impl Model {
         hex_encode(&self.node_id)
     }
 
+    pub fn node_id_as_hex_short(&self) -> String {
+        hex_encode_compact(&self.node_id)
+    }
+
+    pub fn client_name(&self) -> &'static str {
+        client_name_from_version(self.client_version.as_deref())
+    }
+
+    pub fn last_seen_humanized(&self) -> String {
+        match self.last_seen {
+            Some(last_seen) => utils::time_ago(last_seen, Utc::now()),
+            None => "never".to_string(),
+        }
+    }
+
+    /// Decodes the node's ENR, `None` if it is absent or malformed.
+    pub fn decoded_enr(&self) -> Option<Enr> {
+        self.enr.as_deref()?.parse().ok()
+    }
+
+    /// Address advertised in the node's ENR, decoding it once, with the stored address
+    /// used for whatever the ENR can't give.
+    pub fn enr_address(&self) -> EnrAddress {
+        let enr = self.decoded_enr();
+        let ip = enr
+            .as_ref()
+            .and_then(|enr| {
+                enr.ip4()
+                    .map(|ip| ip.to_string())
+                    .or_else(|| enr.ip6().map(|ip| ip.to_string()))
+            })
+            .or_else(|| self.ip.clone());
+        let udp_port = enr
+            .as_ref()
+            .and_then(|enr| enr.udp4().or_else(|| enr.udp6()))
+            .or_else(|| self.port.and_then(|port| u16::try_from(port).ok()));
+        EnrAddress { ip, udp_port }
+    }
+
     pub fn get_node_id(&self) -> NodeId {
         NodeId(self.node_id.to_owned().try_into().expect("failed"))
     }
 }
 
+/// Address a node advertises, as shown in the node list.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EnrAddress {
+    pub ip: Option<String>,
+    pub udp_port: Option<u16>,
+}
+
 #[derive(FromQueryResult)]
 pub struct ModelWithDistance {
     pub id: i32,
@@ -49,6 +112,8 @@ pub enum Relation {
     Record,
     #[sea_orm(has_one = "super::client_info::Entity")]
     ClientInfo,
+    #[sea_orm(has_many = "super::census_result::Entity")]
+    CensusResult,
 }
 
 impl Related<super::record::Entity> for Entity {
@@ -63,6 +128,12 @@ impl Related<super::client_info::Entity> for Entity {
     }
 }
 
+impl Related<super::census_result::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CensusResult.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
@@ -128,7 +199,102 @@ pub async fn get_or_create(node_id: NodeId, conn: &DatabaseConnection) -> Result
         id: NotSet,
         node_id: Set(node_id.0.into()),
         node_id_high: Set(node_id_high),
+        client_version: NotSet,
+        last_seen: NotSet,
+        alive: Set(false),
+        ip: NotSet,
+        port: NotSet,
+        info_updated_at: NotSet,
+        enr: NotSet,
     };
 
     Ok(node_id_model.insert(conn).await?)
 }
+
+/// Records the outcome of pinging a node during a census.
+///
+/// A node that answers is marked alive and seen now. One that doesn't is marked
+/// not alive, keeping the time it was last seen.
+pub async fn record_ping(node_id: i32, alive: bool, conn: &DatabaseConnection) -> Result<()> {
+    let update = Entity::update_many().col_expr(Column::Alive, Expr::value(alive));
+    let update = match alive {
+        true => update.col_expr(Column::LastSeen, Expr::value(Utc::now())),
+        false => update,
+    };
+    update.filter(Column::Id.eq(node_id)).exec(conn).await?;
+    Ok(())
+}
+
+/// Stores the info a node that glados queries directly reports about itself.
+///
+/// The ENR is kept as a record of the node, and its address and client version
+/// are copied onto the node.
+pub async fn record_local_info(
+    enr: &Enr,
+    client_version: &str,
+    conn: &DatabaseConnection,
+) -> Result<Model> {
+    let record = super::record::get_or_create(enr, conn).await?;
+    let (ip, port) = match (enr.ip4(), enr.udp4()) {
+        (Some(ip), port) => (Some(ip.to_string()), port),
+        (None, _) => (enr.ip6().map(|ip| ip.to_string()), enr.udp6()),
+    };
+    let node = Entity::find_by_id(record.node_id)
+        .one(conn)
+        .await?
+        .ok_or_else(|| anyhow!("Node of record {} is missing", record.id))?;
+    let mut node: ActiveModel = node.into();
+    node.client_version = Set(Some(client_version.to_string()));
+    node.ip = Set(ip);
+    node.port = Set(port.map(i32::from));
+    node.info_updated_at = Set(Some(Utc::now()));
+    Ok(node.update(conn).await?)
+}
+
+/// Records the client software advertised by a node.
+pub async fn set_client_version(
+    node_id: i32,
+    client_version: &str,
+    conn: &DatabaseConnection,
+) -> Result<()> {
+    Entity::update_many()
+        .col_expr(Column::ClientVersion, Expr::value(client_version))
+        .filter(Column::Id.eq(node_id))
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
+/// Maps a client version string to the name of the client implementation.
+///
+/// Accepts both the ENR form (`"t 0.1.0"`) and the `web3_clientVersion` form (`"trin/v0.1.0"`).
+pub fn client_name_from_version(client_version: Option<&str>) -> &'static str {
+    let prefix = client_version
+        .and_then(|version| version.split([' ', '/']).next())
+        .map(str::to_lowercase);
+    match prefix.as_deref() {
+        Some("t") | Some("trin") => "trin",
+        Some("f") | Some("fluffy") => "fluffy",
+        Some("u") | Some("ultralight") => "ultralight",
+        Some("") | None => "unknown",
+        Some(_) => "other",
+    }
+}
+
+#[derive(FromQueryResult, Debug)]
+pub struct ClientVersionCount {
+    pub client_version: Option<String>,
+    pub count: i64,
+}
+
+/// Returns the number of nodes advertising each distinct client version.
+pub async fn count_by_client_version(conn: &DatabaseConnection) -> Result<Vec<ClientVersionCount>> {
+    Ok(Entity::find()
+        .select_only()
+        .column(Column::ClientVersion)
+        .column_as(Expr::count(Expr::col(Column::Id)), "count")
+        .group_by(Column::ClientVersion)
+        .into_model::<ClientVersionCount>()
+        .all(conn)
+        .await?)
+}
@@ -2,6 +2,7 @@
 use anyhow::Result;
 use ethportal_api::types::enr::Enr;
 use sea_orm::{entity::prelude::*, ActiveValue::NotSet, Set};
+use sea_query::{Expr, Query};
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
 #[sea_orm(table_name = "record")]
@@ -41,6 +42,7 @@ impl Related<super::node::Entity> for Entity {
 
 impl ActiveModelBehavior for ActiveModel {}
 
+/// Stores an ENR as a record of its node, which keeps a copy of the latest one seen.
 pub async fn get_or_create(enr: &Enr, conn: &DatabaseConnection) -> Result<Model> {
     let node_id = super::node::get_or_create(enr.node_id().into(), conn).await?;
 
@@ -63,6 +65,23 @@ pub async fn get_or_create(enr: &Enr, conn: &DatabaseConnection) -> Result<Model
         sequence_number: Set(enr.seq().try_into().unwrap()),
     };
     let enr_model = enr_model_unsaved.insert(conn).await?;
+    // Records can arrive out of order, so the node keeps the one with the highest
+    // sequence number.
+    super::node::Entity::update_many()
+        .col_expr(super::node::Column::Enr, Expr::value(enr_model.raw.clone()))
+        .filter(super::node::Column::Id.eq(node_id.id))
+        .filter(
+            super::node::Column::Id.not_in_subquery(
+                Query::select()
+                    .column(Column::NodeId)
+                    .from(Entity)
+                    .and_where(Column::NodeId.eq(node_id.id))
+                    .and_where(Column::SequenceNumber.gt(enr_model.sequence_number))
+                    .to_owned(),
+            ),
+        )
+        .exec(conn)
+        .await?;
 
     for (enr_key, enr_value) in enr.iter() {
         super::key_value::get_or_create(enr_model.id, enr_key, &enr_value.to_vec(), conn).await?;
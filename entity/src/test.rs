@@ -10,7 +10,7 @@ use ethportal_api::{BlockHeaderKey, HistoryContentKey, OverlayContentKey};
 use sea_orm::entity::prelude::*;
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, Database, DbConn, DbErr, EntityTrait, NotSet, PaginatorTrait,
-    QueryFilter, Set,
+    QueryFilter, QueryOrder, Set,
 };
 
 use ethportal_api::utils::bytes::hex_encode;
@@ -18,7 +18,9 @@ use migration::{Migrator, MigratorTrait};
 
 use crate::content::SubProtocol;
 use crate::content_audit::SelectionStrategy;
-use crate::{client_info, content, content_audit, node, record};
+use crate::{
+    census_result, client_info, content, content_audit, node, record, routing_table_snapshot,
+};
 
 #[allow(dead_code)]
 async fn setup_database() -> Result<DbConn, DbErr> {
@@ -50,11 +52,25 @@ async fn test_node_crud() -> Result<(), DbErr> {
         id: NotSet,
         node_id: Set(node_id_a.clone()),
         node_id_high: Set(0),
+        client_version: NotSet,
+        last_seen: NotSet,
+        alive: Set(false),
+        ip: NotSet,
+        port: NotSet,
+        info_updated_at: NotSet,
+        enr: NotSet,
     };
     let node_b = node::ActiveModel {
         id: NotSet,
         node_id: Set(node_id_b.clone()),
         node_id_high: Set(0),
+        client_version: NotSet,
+        last_seen: NotSet,
+        alive: Set(false),
+        ip: NotSet,
+        port: NotSet,
+        info_updated_at: NotSet,
+        enr: NotSet,
     };
 
     assert_eq!(node::Entity::find().count(&conn).await?, 0);
@@ -158,6 +174,22 @@ async fn test_content_key_as_hex() -> Result<(), DbErr> {
     Ok(())
 }
 
+/// Tests that content keys are described by their type and block, and undecodable
+/// keys by an unknown type.
+#[tokio::test]
+async fn test_content_key_description() -> Result<(), DbErr> {
+    let conn = setup_database().await?;
+    let key = sample_history_key();
+    let mut content_model = content::get_or_create(&key, &conn).await.unwrap();
+    assert_eq!(
+        content_model.key_description(),
+        "Block header 0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+    );
+    content_model.content_key = vec![0xff, 0x01];
+    assert_eq!(content_model.key_description(), "Unknown type");
+    Ok(())
+}
+
 /// Tests that the get_or_create() function correctly handles the
 /// presence or absence of a key in the database.
 #[tokio::test]
@@ -185,6 +217,81 @@ async fn test_content_get_or_create() -> Result<(), DbErr> {
     Ok(())
 }
 
+/// Tests that a content key can only be stored once, and that storing it twice at
+/// the same time returns the same record.
+#[tokio::test]
+async fn test_content_key_stored_once() -> Result<(), DbErr> {
+    let conn = setup_database().await?;
+    let key = sample_history_key();
+
+    let (content_a, content_b) = tokio::join!(
+        content::get_or_create(&key, &conn),
+        content::get_or_create(&key, &conn)
+    );
+    assert_eq!(content_a.unwrap().id, content_b.unwrap().id);
+    assert_eq!(content::Entity::find().count(&conn).await?, 1);
+
+    let duplicate = content::ActiveModel {
+        id: NotSet,
+        content_id: Set(key.content_id().to_vec()),
+        content_key: Set(key.to_bytes()),
+        protocol_id: Set(SubProtocol::State),
+        first_available_at: Set(Utc::now()),
+        audit_count: Set(0),
+        audit_success_count: Set(0),
+    }
+    .insert(&conn)
+    .await;
+    assert!(duplicate.is_err());
+    assert_eq!(content::Entity::find().count(&conn).await?, 1);
+    Ok(())
+}
+
+/// Tests that only one of two concurrent inserts of a content key sees it as new.
+#[tokio::test]
+async fn test_content_insert_if_new() -> Result<(), DbErr> {
+    let conn = setup_database().await?;
+    let key = sample_history_key();
+
+    let (inserted_a, inserted_b) = tokio::join!(
+        content::insert_if_new(&key, &conn),
+        content::insert_if_new(&key, &conn)
+    );
+    let mut inserted = [inserted_a.unwrap(), inserted_b.unwrap()];
+    inserted.sort();
+    assert_eq!(inserted, [false, true]);
+    assert!(!content::insert_if_new(&key, &conn).await.unwrap());
+    assert_eq!(content::Entity::find().count(&conn).await?, 1);
+    Ok(())
+}
+
+/// Tests that stored content is rejected when its content id does not match its key.
+#[tokio::test]
+async fn test_content_get_or_create_rejects_mismatched_id() -> Result<(), DbErr> {
+    let conn = setup_database().await?;
+    let key = sample_history_key();
+
+    content::ActiveModel {
+        id: NotSet,
+        content_id: Set(vec![0; 32]),
+        content_key: Set(key.to_bytes()),
+        protocol_id: Set(SubProtocol::History),
+        first_available_at: Set(Utc::now()),
+        audit_count: Set(0),
+        audit_success_count: Set(0),
+    }
+    .insert(&conn)
+    .await?;
+
+    let err = content::get_or_create(&key, &conn).await.unwrap_err();
+    let mismatch = err.downcast_ref::<content::ContentIdMismatch>().unwrap();
+    assert_eq!(mismatch.content_id, hex_encode([0u8; 32]));
+    assert_eq!(mismatch.expected_content_id, hex_encode(key.content_id()));
+
+    assert!(content::verify_content_id(&key, &key.content_id()).is_ok());
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_audit_crud() -> Result<(), DbErr> {
     let conn = setup_database().await?;
@@ -196,6 +303,8 @@ async fn test_audit_crud() -> Result<(), DbErr> {
         content_key: Set(key.to_bytes()),
         protocol_id: Set(SubProtocol::History),
         first_available_at: Set(Utc::now()),
+        audit_count: Set(0),
+        audit_success_count: Set(0),
     };
 
     let content_model = content_key_active_model.insert(&conn).await?;
@@ -229,6 +338,14 @@ async fn test_audit_crud() -> Result<(), DbErr> {
         trace: Set("".to_owned()),
         client_info: Set(Some(client_info_model.id)),
         node: Set(Some(node.id)),
+        duration_ms: Set(None),
+        outcome: Set(Some(content_audit::AuditOutcome::Success)),
+        served_by: Set(None),
+        error: Set(None),
+        label: Set(None),
+        closer_peer_responses: Set(None),
+        content_length: Set(None),
+        content_id_verified: Set(None),
     };
 
     let content_audit_model = content_audit_active_model.insert(&conn).await?;
@@ -243,6 +360,10 @@ async fn test_audit_crud() -> Result<(), DbErr> {
         searched_content_audit_model.result,
         content_audit::AuditResult::Success
     );
+    assert_eq!(
+        searched_content_audit_model.outcome,
+        Some(content_audit::AuditOutcome::Success)
+    );
     assert_eq!(
         searched_content_audit_model.strategy_used,
         Some(content_audit::SelectionStrategy::Random)
@@ -251,6 +372,365 @@ async fn test_audit_crud() -> Result<(), DbErr> {
     Ok(())
 }
 
+/// Tests that audits inserted together are all stored, and that no audits is a no-op.
+#[tokio::test]
+async fn test_audit_create_many() -> Result<(), DbErr> {
+    let conn = setup_database().await?;
+    let content_model = content::get_or_create(&sample_history_key(), &conn)
+        .await
+        .unwrap();
+    let client_info = client_info::ActiveModel {
+        id: NotSet,
+        version_info: Set("trin v0.1.0".to_owned()),
+    }
+    .insert(&conn)
+    .await?;
+    let node = node::get_or_create(NodeId::random(), &conn).await.unwrap();
+
+    let audits = [
+        content_audit::AuditOutcome::Success,
+        content_audit::AuditOutcome::NotFound,
+    ]
+    .into_iter()
+    .map(|outcome| {
+        content_audit::new_audit(
+            content_model.id,
+            client_info.id,
+            node.id,
+            outcome,
+            SelectionStrategy::Latest,
+            "".to_owned(),
+            Some(10),
+            None,
+            None,
+            None,
+        )
+    })
+    .collect();
+    assert_eq!(content_audit::create_many(audits, &conn).await.unwrap(), 2);
+    assert_eq!(content_audit::create_many(vec![], &conn).await.unwrap(), 0);
+
+    let stored: Vec<Option<content_audit::AuditOutcome>> = content_audit::Entity::find()
+        .order_by_asc(content_audit::Column::Id)
+        .all(&conn)
+        .await?
+        .into_iter()
+        .map(|audit| audit.outcome)
+        .collect();
+    assert_eq!(
+        stored,
+        vec![
+            Some(content_audit::AuditOutcome::Success),
+            Some(content_audit::AuditOutcome::NotFound)
+        ]
+    );
+    Ok(())
+}
+
+/// Tests that storing audits one at a time or in a batch keeps the content's audit counts.
+#[tokio::test]
+async fn test_audit_counts() -> Result<(), DbErr> {
+    let conn = setup_database().await?;
+    let content_model = content::get_or_create(&sample_history_key(), &conn)
+        .await
+        .unwrap();
+    assert_eq!(
+        (content_model.audit_count, content_model.audit_success_count),
+        (0, 0)
+    );
+    let client_info = client_info::ActiveModel {
+        id: NotSet,
+        version_info: Set("trin v0.1.0".to_owned()),
+    }
+    .insert(&conn)
+    .await?;
+    let node = node::get_or_create(NodeId::random(), &conn).await.unwrap();
+    let audit = |outcome| {
+        content_audit::new_audit(
+            content_model.id,
+            client_info.id,
+            node.id,
+            outcome,
+            SelectionStrategy::Latest,
+            "".to_owned(),
+            None,
+            None,
+            None,
+            None,
+        )
+    };
+
+    content_audit::store(audit(content_audit::AuditOutcome::Success), &conn)
+        .await
+        .unwrap();
+    content_audit::create_many(
+        vec![
+            audit(content_audit::AuditOutcome::NotFound),
+            audit(content_audit::AuditOutcome::Success),
+            audit(content_audit::AuditOutcome::Timeout),
+        ],
+        &conn,
+    )
+    .await
+    .unwrap();
+
+    let counted = content::Entity::find_by_id(content_model.id)
+        .one(&conn)
+        .await?
+        .unwrap();
+    // The timeout is not counted.
+    assert_eq!((counted.audit_count, counted.audit_success_count), (3, 2));
+    Ok(())
+}
+
+/// Tests that pruning deletes old audits but keeps the latest audit of each content key.
+#[tokio::test]
+async fn test_delete_older_than_keeps_latest_audit() -> Result<(), DbErr> {
+    let conn = setup_database().await?;
+    let now = Utc::now();
+
+    // Audits of each key, oldest first, in days before now.
+    let audit_ages: [(u8, &[i64]); 3] = [(1, &[60, 50, 40]), (2, &[50, 1]), (3, &[90])];
+    let mut latest_audit_ids = vec![];
+    for (block_hash_byte, ages) in audit_ages {
+        let content_key = HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+            block_hash: [block_hash_byte; 32],
+        });
+        let content_model = content::get_or_create(&content_key, &conn).await.unwrap();
+        for days_ago in ages {
+            let audit = content_audit::ActiveModel {
+                id: NotSet,
+                content_key: Set(content_model.id),
+                created_at: Set(now - chrono::Duration::days(*days_ago)),
+                strategy_used: Set(Some(SelectionStrategy::Random)),
+                result: Set(content_audit::AuditResult::Failure),
+                trace: Set("".to_owned()),
+                client_info: Set(None),
+                node: Set(None),
+                duration_ms: Set(None),
+                outcome: Set(Some(content_audit::AuditOutcome::NotFound)),
+                served_by: Set(None),
+                error: Set(None),
+                label: Set(None),
+                closer_peer_responses: Set(None),
+                content_length: Set(None),
+                content_id_verified: Set(None),
+            }
+            .insert(&conn)
+            .await?;
+            if Some(days_ago) == ages.last() {
+                latest_audit_ids.push(audit.id);
+            }
+        }
+    }
+
+    let deleted = content_audit::delete_older_than(now - chrono::Duration::days(30), &conn)
+        .await
+        .unwrap();
+    assert_eq!(deleted, 3);
+
+    let remaining: Vec<i32> = content_audit::Entity::find()
+        .order_by_asc(content_audit::Column::Id)
+        .all(&conn)
+        .await?
+        .into_iter()
+        .map(|audit| audit.id)
+        .collect();
+    assert_eq!(remaining, latest_audit_ids);
+
+    Ok(())
+}
+
+/// Tests that a node keeps the time it was last seen after it stops answering pings.
+#[tokio::test]
+async fn test_node_record_ping() -> Result<(), DbErr> {
+    let conn = setup_database().await?;
+
+    let node = node::get_or_create(NodeId::random(), &conn).await.unwrap();
+    assert!(!node.alive);
+    assert_eq!(node.last_seen, None);
+
+    node::record_ping(node.id, true, &conn).await.unwrap();
+    let seen = node::Entity::find_by_id(node.id).one(&conn).await?.unwrap();
+    assert!(seen.alive);
+    assert!(seen.last_seen.is_some());
+
+    node::record_ping(node.id, false, &conn).await.unwrap();
+    let unreachable = node::Entity::find_by_id(node.id).one(&conn).await?.unwrap();
+    assert!(!unreachable.alive);
+    assert_eq!(unreachable.last_seen, seen.last_seen);
+
+    Ok(())
+}
+
+/// Tests that a node's own info is stored along with its ENR.
+#[tokio::test]
+async fn test_node_record_local_info() -> Result<(), DbErr> {
+    let conn = setup_database().await?;
+    let (_, enr) = ethportal_api::generate_random_remote_enr();
+
+    let node = node::record_local_info(&enr, "trin v0.1.0", &conn)
+        .await
+        .unwrap();
+    assert_eq!(node.node_id, enr.node_id().raw().to_vec());
+    assert_eq!(node.client_version.as_deref(), Some("trin v0.1.0"));
+    assert_eq!(node.ip, enr.ip4().map(|ip| ip.to_string()));
+    assert_eq!(node.port, enr.udp4().map(i32::from));
+    assert!(node.info_updated_at.is_some());
+    assert_eq!(node.enr, Some(enr.to_base64()));
+    assert_eq!(node.decoded_enr(), Some(enr.clone()));
+    assert_eq!(
+        node.enr_address(),
+        node::EnrAddress {
+            ip: node.ip.clone(),
+            udp_port: enr.udp4(),
+        }
+    );
+    assert_eq!(node.find_related(record::Entity).count(&conn).await?, 1);
+
+    // Refreshing updates the same node.
+    let refreshed = node::record_local_info(&enr, "trin v0.1.1", &conn)
+        .await
+        .unwrap();
+    assert_eq!(refreshed.id, node.id);
+    assert_eq!(refreshed.client_version.as_deref(), Some("trin v0.1.1"));
+    assert_eq!(node::Entity::find().count(&conn).await?, 1);
+
+    Ok(())
+}
+
+/// Tests that a node keeps its newest ENR when an older record is stored after it.
+#[tokio::test]
+async fn test_record_keeps_newest_enr() -> Result<(), DbErr> {
+    let conn = setup_database().await?;
+    let (key, old_enr) = ethportal_api::generate_random_remote_enr();
+    let mut new_enr = old_enr.clone();
+    new_enr.set_udp4(9010, &key).unwrap();
+    assert!(new_enr.seq() > old_enr.seq());
+
+    record::get_or_create(&new_enr, &conn).await.unwrap();
+    record::get_or_create(&old_enr, &conn).await.unwrap();
+    let node = node::Entity::find().one(&conn).await?.unwrap();
+    assert_eq!(node.enr, Some(new_enr.to_base64()));
+    assert_eq!(node.find_related(record::Entity).count(&conn).await?, 2);
+
+    Ok(())
+}
+
+/// Tests that the stored address is shown when a node's ENR can't be decoded.
+#[tokio::test]
+async fn test_node_malformed_enr() -> Result<(), DbErr> {
+    let conn = setup_database().await?;
+    let node = node::get_or_create(NodeId::random(), &conn).await.unwrap();
+    assert_eq!(node.decoded_enr(), None);
+    assert_eq!(node.enr_address(), node::EnrAddress::default());
+
+    let mut node: node::ActiveModel = node.into();
+    node.enr = Set(Some("enr:not-an-enr".to_string()));
+    node.ip = Set(Some("192.168.0.1".to_string()));
+    node.port = Set(Some(9009));
+    let node = node.update(&conn).await?;
+    assert_eq!(node.decoded_enr(), None);
+    assert_eq!(
+        node.enr_address(),
+        node::EnrAddress {
+            ip: Some("192.168.0.1".to_string()),
+            udp_port: Some(9009),
+        }
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_census_result_crud() -> Result<(), DbErr> {
+    let conn = setup_database().await?;
+
+    let node = node::get_or_create(NodeId::random(), &conn).await.unwrap();
+
+    census_result::create(node.id, true, Some(25), &conn)
+        .await
+        .unwrap();
+    census_result::create(node.id, false, None, &conn)
+        .await
+        .unwrap();
+
+    let results = node
+        .find_related(census_result::Entity)
+        .order_by_asc(census_result::Column::Id)
+        .all(&conn)
+        .await?;
+    assert_eq!(results.len(), 2);
+    assert!(results[0].ping_successful);
+    assert_eq!(results[0].rtt_ms, Some(25));
+    assert!(!results[1].ping_successful);
+    assert_eq!(results[1].rtt_ms, None);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_routing_table_snapshot_crud() -> Result<(), DbErr> {
+    let conn = setup_database().await?;
+
+    let bucket_counts = std::collections::BTreeMap::from([(254, 3), (255, 5), (256, 8)]);
+    let snapshot = routing_table_snapshot::create(vec![0x11; 32], &bucket_counts, 10, &conn)
+        .await
+        .unwrap();
+
+    let stored = routing_table_snapshot::Entity::find_by_id(snapshot.id)
+        .one(&conn)
+        .await?
+        .unwrap();
+    assert_eq!(stored.local_node_id, vec![0x11; 32]);
+    assert_eq!(stored.total_peers, 16);
+    assert_eq!(stored.connected_peers, 10);
+    assert_eq!(stored.bucket_counts().unwrap(), bucket_counts);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_node_client_version() -> Result<(), DbErr> {
+    let conn = setup_database().await?;
+
+    let trin_node = node::get_or_create(NodeId::random(), &conn).await.unwrap();
+    let fluffy_node = node::get_or_create(NodeId::random(), &conn).await.unwrap();
+    let unknown_node = node::get_or_create(NodeId::random(), &conn).await.unwrap();
+    assert_eq!(unknown_node.client_name(), "unknown");
+
+    node::set_client_version(trin_node.id, "t 0.1.0", &conn)
+        .await
+        .unwrap();
+    node::set_client_version(fluffy_node.id, "f", &conn)
+        .await
+        .unwrap();
+
+    let trin_node = node::Entity::find_by_id(trin_node.id)
+        .one(&conn)
+        .await?
+        .unwrap();
+    assert_eq!(trin_node.client_version.as_deref(), Some("t 0.1.0"));
+    assert_eq!(trin_node.client_name(), "trin");
+
+    let counts = node::count_by_client_version(&conn).await.unwrap();
+    assert_eq!(counts.len(), 3);
+    assert!(counts.iter().all(|count| count.count == 1));
+
+    assert_eq!(
+        node::client_name_from_version(Some("trin/v0.1.1-alpha")),
+        "trin"
+    );
+    assert_eq!(
+        node::client_name_from_version(Some("u 0.0.1")),
+        "ultralight"
+    );
+    assert_eq!(node::client_name_from_version(Some("x 1.0")), "other");
+    assert_eq!(node::client_name_from_version(None), "unknown");
+
+    Ok(())
+}
+
 /// Tests that the content table unique constraints prevent duplicate entries.
 /// No two keys should have the same protocol_id, content_key and content_id combination.
 #[tokio::test]
@@ -269,6 +749,8 @@ async fn test_content_table_unique_constraints() {
         content_key: Set(key_a.clone()),
         protocol_id: Set(protocol_a.clone()),
         first_available_at: Set(Utc::now()),
+        audit_count: Set(0),
+        audit_success_count: Set(0),
     };
     action_a.clone().insert(&conn).await.unwrap();
     assert_eq!(content::Entity::find().count(&conn).await.unwrap(), 1);
@@ -290,6 +772,8 @@ async fn test_content_table_unique_constraints() {
         content_key: Set(key_a.clone()),
         protocol_id: Set(protocol_b),
         first_available_at: Set(Utc::now()),
+        audit_count: Set(0),
+        audit_success_count: Set(0),
     };
     action_b.clone().insert(&conn).await.unwrap();
     assert_eq!(content::Entity::find().count(&conn).await.unwrap(), 2);
@@ -310,6 +794,8 @@ async fn test_content_table_unique_constraints() {
         content_key: Set(key_a),
         protocol_id: Set(protocol_a.clone()),
         first_available_at: Set(Utc::now()),
+        audit_count: Set(0),
+        audit_success_count: Set(0),
     };
     assert!(action_c
         .clone()
@@ -336,6 +822,8 @@ async fn test_content_table_unique_constraints() {
         content_key: Set(key_b),
         protocol_id: Set(protocol_a),
         first_available_at: Set(Utc::now()),
+        audit_count: Set(0),
+        audit_success_count: Set(0),
     };
     assert!(action_d
         .insert(&conn)
@@ -434,3 +922,77 @@ async fn test_query_closest() {
     let order_from_c = [nodes_near_c[0].id, nodes_near_c[1].id, nodes_near_c[2].id];
     assert_eq!(order_from_c, expected_from_c);
 }
+
+/// An audit made `minutes_ago` minutes before `now`, for availability tests.
+#[cfg(test)]
+fn audit_at(now: DateTime<Utc>, minutes_ago: i64, passed: bool) -> content_audit::Model {
+    content_audit::Model {
+        id: minutes_ago as i32,
+        content_key: 1,
+        client_info: None,
+        node: None,
+        created_at: now - chrono::Duration::minutes(minutes_ago),
+        strategy_used: Some(SelectionStrategy::Random),
+        result: match passed {
+            true => content_audit::AuditResult::Success,
+            false => content_audit::AuditResult::Failure,
+        },
+        trace: "".to_owned(),
+        duration_ms: None,
+        outcome: None,
+        served_by: None,
+        error: None,
+        label: None,
+        closer_peer_responses: None,
+        content_length: None,
+        content_id_verified: None,
+    }
+}
+
+#[test]
+fn test_availability_from_audits() {
+    use content_audit::Availability;
+    let now = Utc::now();
+    let minutes_ago = |minutes: i64| now - chrono::Duration::minutes(minutes);
+
+    assert_eq!(Availability::from_audits(&[]), Availability::Unknown);
+
+    // Latest audit passed, regardless of earlier failures.
+    let audits = [audit_at(now, 30, false), audit_at(now, 10, true)];
+    assert_eq!(Availability::from_audits(&audits), Availability::Available);
+
+    // Passed, then failed twice: missing since the pass.
+    let audits = [
+        audit_at(now, 30, true),
+        audit_at(now, 20, false),
+        audit_at(now, 10, false),
+    ];
+    assert_eq!(
+        Availability::from_audits(&audits),
+        Availability::MissingSince(minutes_ago(30))
+    );
+
+    // Only the latest success before the current failures counts, in any order.
+    let audits = [
+        audit_at(now, 10, false),
+        audit_at(now, 50, true),
+        audit_at(now, 20, true),
+        audit_at(now, 40, false),
+    ];
+    assert_eq!(
+        Availability::from_audits(&audits),
+        Availability::MissingSince(minutes_ago(20))
+    );
+    assert_eq!(
+        Availability::from_audits(&audits).missing_since(),
+        Some(minutes_ago(20))
+    );
+
+    // Never passed.
+    let audits = [audit_at(now, 20, false), audit_at(now, 10, false)];
+    assert_eq!(
+        Availability::from_audits(&audits),
+        Availability::NeverAvailable
+    );
+    assert_eq!(Availability::from_audits(&audits).missing_since(), None);
+}
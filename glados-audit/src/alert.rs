@@ -0,0 +1,224 @@
+use serde::Serialize;
+use std::{collections::VecDeque, fmt::Display, str::FromStr};
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+use url::Url;
+
+/// Fraction of audits that must pass for the audited network to count as healthy.
+#[derive(Clone, Copy, Debug)]
+pub struct PassRateThreshold(f64);
+
+impl PassRateThreshold {
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+}
+
+impl FromStr for PassRateThreshold {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let threshold: f64 = s.parse().map_err(|_| format!("'{s}' is not a number"))?;
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(format!("{threshold} is not between 0 and 1"));
+        }
+        Ok(PassRateThreshold(threshold))
+    }
+}
+
+impl Display for PassRateThreshold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Parsing rejects NaN, so thresholds can be compared as their bits.
+impl PartialEq for PassRateThreshold {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for PassRateThreshold {}
+
+/// Whether the rolling pass rate is at or above the alert threshold.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Health {
+    Healthy,
+    Degraded,
+}
+
+/// Body POSTed to the webhook when the health changes.
+///
+/// The message is sent as both `text` and `content`, which Slack and Discord
+/// display respectively, so either can be pointed at directly.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AlertPayload {
+    pub status: Health,
+    pub pass_rate: f64,
+    pub threshold: f64,
+    pub window: usize,
+    pub text: String,
+    pub content: String,
+}
+
+/// Tracks the pass rate over the latest audits and notifies a webhook when it
+/// crosses the threshold in either direction.
+///
+/// The pass rate is kept as a running count over a fixed size window, so each
+/// audit updates it without reading earlier audits from the database.
+#[derive(Debug)]
+pub struct PassRateAlert {
+    webhook_url: Url,
+    threshold: PassRateThreshold,
+    window_size: usize,
+    window: Mutex<AlertWindow>,
+    client: reqwest::Client,
+}
+
+#[derive(Debug)]
+struct AlertWindow {
+    results: VecDeque<bool>,
+    passed: usize,
+    health: Health,
+}
+
+impl PassRateAlert {
+    pub fn new(webhook_url: Url, threshold: PassRateThreshold, window_size: usize) -> Self {
+        PassRateAlert {
+            webhook_url,
+            threshold,
+            window_size,
+            window: Mutex::new(AlertWindow {
+                results: VecDeque::with_capacity(window_size),
+                passed: 0,
+                health: Health::Healthy,
+            }),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Adds an audit result to the window.
+    ///
+    /// Returns a payload if the health changed. Nothing is returned until the
+    /// window is full, so a few early failures don't raise an alert.
+    pub async fn record(&self, passed: bool) -> Option<AlertPayload> {
+        let mut window = self.window.lock().await;
+        if window.results.len() == self.window_size {
+            if let Some(true) = window.results.pop_front() {
+                window.passed -= 1;
+            }
+        }
+        window.results.push_back(passed);
+        if passed {
+            window.passed += 1;
+        }
+        if window.results.len() < self.window_size {
+            return None;
+        }
+
+        let pass_rate = window.passed as f64 / self.window_size as f64;
+        let health = match pass_rate < self.threshold.value() {
+            true => Health::Degraded,
+            false => Health::Healthy,
+        };
+        if health == window.health {
+            return None;
+        }
+        window.health = health;
+
+        let text = match health {
+            Health::Degraded => format!(
+                "Glados audit pass rate dropped to {:.1}% over the last {} audits (threshold {:.1}%)",
+                pass_rate * 100.0,
+                self.window_size,
+                self.threshold.value() * 100.0
+            ),
+            Health::Healthy => format!(
+                "Glados audit pass rate recovered to {:.1}% over the last {} audits (threshold {:.1}%)",
+                pass_rate * 100.0,
+                self.window_size,
+                self.threshold.value() * 100.0
+            ),
+        };
+        Some(AlertPayload {
+            status: health,
+            pass_rate,
+            threshold: self.threshold.value(),
+            window: self.window_size,
+            content: text.clone(),
+            text,
+        })
+    }
+
+    /// Records an audit result and, if the health changed, POSTs the alert to the webhook.
+    ///
+    /// The request is sent from its own task so the audit worker does not wait for it.
+    pub async fn record_and_notify(&self, passed: bool) {
+        let Some(payload) = self.record(passed).await else {
+            return;
+        };
+        warn!(
+            alert.status=?payload.status,
+            alert.pass_rate = payload.pass_rate,
+            "Audit pass rate crossed the alert threshold."
+        );
+        let request = self
+            .client
+            .post(self.webhook_url.clone())
+            .json(&payload)
+            .send();
+        tokio::spawn(async move {
+            match request
+                .await
+                .and_then(|response| response.error_for_status())
+            {
+                Ok(_) => info!(alert.status=?payload.status, "Sent alert to webhook."),
+                Err(err) => error!(err=?err, "Could not send alert to webhook."),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(threshold: &str, window_size: usize) -> PassRateAlert {
+        PassRateAlert::new(
+            Url::parse("http://127.0.0.1:9/webhook").unwrap(),
+            threshold.parse().unwrap(),
+            window_size,
+        )
+    }
+
+    /// Tests that alerts fire only when the health changes, once the window is full.
+    #[tokio::test]
+    async fn test_alert_transitions() {
+        let alert = alert("0.5", 4);
+        // The window is not full yet.
+        for _ in 0..3 {
+            assert_eq!(alert.record(false).await, None);
+        }
+        let degraded = alert.record(false).await.unwrap();
+        assert_eq!(degraded.status, Health::Degraded);
+        assert_eq!(degraded.pass_rate, 0.0);
+        // Still degraded, so nothing more is sent.
+        assert_eq!(alert.record(true).await, None);
+        let healthy = alert.record(true).await.unwrap();
+        assert_eq!(healthy.status, Health::Healthy);
+        assert_eq!(healthy.pass_rate, 0.5);
+        assert_eq!(alert.record(true).await, None);
+    }
+
+    /// Tests that thresholds outside of 0 to 1 are rejected.
+    #[test]
+    fn test_threshold_range() {
+        assert!("0.9".parse::<PassRateThreshold>().is_ok());
+        assert!("1".parse::<PassRateThreshold>().is_ok());
+        assert!("1.5".parse::<PassRateThreshold>().is_err());
+        assert!("-0.1".parse::<PassRateThreshold>().is_err());
+        assert!("NaN".parse::<PassRateThreshold>().is_err());
+    }
+}
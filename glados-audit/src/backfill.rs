@@ -0,0 +1,117 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use entity::content;
+use ethportal_api::utils::bytes::hex_decode;
+use ethportal_api::HistoryContentKey;
+use sea_orm::DatabaseConnection;
+use tracing::{info, warn};
+
+/// Inserts the content keys listed in a file so that they become available for auditing.
+///
+/// The file holds one hex encoded content key per line, optionally followed by the
+/// content id the source derived for it. Blank lines and lines starting with `#` are
+/// skipped. Keys that cannot be decoded, that are given with a content id that does not
+/// match them, or that are already stored with one, are logged and skipped.
+pub async fn backfill_from_file(path: &Path, conn: &DatabaseConnection) -> Result<()> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Could not read content keys from {}: {e}", path.display()))?;
+
+    let mut inserted = 0;
+    let mut skipped = 0;
+    for (index, line) in contents.lines().enumerate() {
+        let content_key = match parse_content_key_line(line) {
+            Ok(Some(content_key)) => content_key,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!(line = index + 1, err = %e, "Skipping invalid content key");
+                skipped += 1;
+                continue;
+            }
+        };
+        if let Err(e) = content::get_or_create(&content_key, conn).await {
+            match e.downcast_ref::<content::ContentIdMismatch>() {
+                Some(mismatch) => {
+                    warn!(line = index + 1, err = %mismatch, "Skipping content key stored with the wrong content id");
+                    skipped += 1;
+                    continue;
+                }
+                None => return Err(e),
+            }
+        }
+        inserted += 1;
+    }
+    info!(
+        path = %path.display(),
+        inserted,
+        skipped,
+        "Finished backfilling content keys"
+    );
+    Ok(())
+}
+
+/// Parses a single line of a content key file, returning `None` for blank and comment lines.
+///
+/// A content id after the key, separated by whitespace, must be the one derived from the key.
+fn parse_content_key_line(line: &str) -> Result<Option<HistoryContentKey>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let mut fields = line.split_whitespace();
+    let raw = hex_decode(fields.next().unwrap_or_default())?;
+    let content_key =
+        HistoryContentKey::try_from(raw).map_err(|e| anyhow!("Invalid content key: {e}"))?;
+    if let Some(content_id) = fields.next() {
+        content::verify_content_id(&content_key, &hex_decode(content_id)?)?;
+    }
+    if fields.next().is_some() {
+        return Err(anyhow!("Expected a content key and at most a content id"));
+    }
+    Ok(Some(content_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethportal_api::{utils::bytes::hex_encode, OverlayContentKey};
+
+    #[test]
+    fn test_parse_content_key_line() {
+        const BLOCK_HEADER_KEY: &str =
+            "0x00720704f3aa11c53cf344ea069db95cecb81ad7453c8f276b2a1062979611f09c";
+
+        let content_key = parse_content_key_line(&format!("  {BLOCK_HEADER_KEY}\t"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(content_key.to_hex(), BLOCK_HEADER_KEY);
+
+        assert!(parse_content_key_line("").unwrap().is_none());
+        assert!(parse_content_key_line("# header keys").unwrap().is_none());
+        assert!(parse_content_key_line("0xzz").is_err());
+        assert!(parse_content_key_line("0x0a").is_err());
+    }
+
+    /// Tests that a content id given with a key must be the one derived from it.
+    #[test]
+    fn test_parse_content_key_line_with_content_id() {
+        const BLOCK_HEADER_KEY: &str =
+            "0x00720704f3aa11c53cf344ea069db95cecb81ad7453c8f276b2a1062979611f09c";
+        let content_key = parse_content_key_line(BLOCK_HEADER_KEY).unwrap().unwrap();
+        let content_id = hex_encode(content_key.content_id());
+
+        let line = format!("{BLOCK_HEADER_KEY} {content_id}");
+        assert_eq!(
+            parse_content_key_line(&line).unwrap(),
+            Some(content_key.clone())
+        );
+
+        let line = format!("{BLOCK_HEADER_KEY} {}", hex_encode([0; 32]));
+        let err = parse_content_key_line(&line).unwrap_err();
+        let mismatch = err.downcast_ref::<content::ContentIdMismatch>().unwrap();
+        assert_eq!(mismatch.expected_content_id, content_id);
+
+        let line = format!("{BLOCK_HEADER_KEY} {content_id} extra");
+        assert!(parse_content_key_line(&line).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! Audits of the history content for a fixed range of blocks.
+//!
+//! Block hashes are resolved through the Ethereum execution JSON-RPC given by
+//! `--provider-url`. The header, body and receipts keys of each block are stored
+//! and then audited once, leaving out the keys of other shards if `--shard-index`
+//! is set. Blocks whose hash can't be resolved are skipped and reported at the end
+//! of the run.
+
+use std::{
+    ops::RangeInclusive,
+    sync::{atomic::AtomicUsize, atomic::Ordering, Arc},
+};
+
+use anyhow::{anyhow, Result};
+use entity::content_audit::SelectionStrategy;
+use glados_core::db::store_block_keys;
+use sea_orm::DatabaseConnection;
+use tokio::{
+    sync::mpsc,
+    time::{sleep, Duration},
+};
+use tracing::{debug, info, warn};
+use web3::{
+    transports::Http,
+    types::{BlockId, BlockNumber, H256},
+    Web3,
+};
+
+use crate::{
+    perform_content_audits,
+    selection::{add_to_queue, retain_content_type, retain_in_shard},
+    AuditConfig, AuditTask,
+};
+
+/// Number of attempts made to resolve a block hash before the block is skipped.
+const BLOCK_HASH_ATTEMPTS: u8 = 3;
+/// Wait between attempts to resolve a block hash.
+const BLOCK_HASH_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Stores and audits the content of every block in `blocks`, returning once all audits finish.
+pub async fn run_block_range_audit(
+    conn: DatabaseConnection,
+    config: AuditConfig,
+    blocks: RangeInclusive<i32>,
+) -> Result<()> {
+    let w3 = Web3::new(Http::new(&config.provider_url)?);
+    let content_type = config.content_type.clone();
+    let metrics = config.metrics.clone();
+    let shard = config.shard;
+
+    let (tx, rx) = mpsc::channel::<AuditTask>(100);
+    let audits_performed = Arc::new(AtomicUsize::new(0));
+    let audits = tokio::spawn(perform_content_audits(
+        config,
+        rx,
+        conn.clone(),
+        audits_performed.clone(),
+    ));
+
+    let mut unresolved: Vec<i32> = vec![];
+    for block_number in blocks.clone() {
+        let block_hash = match fetch_block_hash(&w3, block_number).await {
+            Ok(block_hash) => block_hash,
+            Err(err) => {
+                warn!(block.number = block_number, err=?err, "Could not resolve block hash, skipping block.");
+                unresolved.push(block_number);
+                continue;
+            }
+        };
+        let items = retain_content_type(
+            &content_type,
+            retain_in_shard(
+                &shard,
+                store_block_keys(block_number, block_hash.as_fixed_bytes(), &conn).await,
+            ),
+        );
+        debug!(
+            block.number = block_number,
+            item_count = items.len(),
+            "Adding block content keys to the audit queue."
+        );
+        add_to_queue(tx.clone(), SelectionStrategy::BlockRange, items, &metrics).await;
+    }
+
+    // Closing the channel lets the audit workers stop once the queue is empty.
+    drop(tx);
+    audits.await?;
+
+    info!(
+        blocks.start = blocks.start(),
+        blocks.end = blocks.end(),
+        blocks.unresolved = unresolved.len(),
+        audits.performed = audits_performed.load(Ordering::Relaxed),
+        "Finished auditing block range."
+    );
+    if !unresolved.is_empty() {
+        warn!(blocks=?unresolved, "Some blocks could not be resolved and were not audited.");
+    }
+    Ok(())
+}
+
+/// Gets the hash of a block from the execution provider, retrying a few times.
+async fn fetch_block_hash(w3: &Web3<Http>, block_number: i32) -> Result<H256> {
+    let mut attempt = 1;
+    loop {
+        let result = w3
+            .eth()
+            .block(BlockId::Number(BlockNumber::Number(block_number.into())))
+            .await
+            .map_err(|e| anyhow!("Failed to retrieve block: {e}"))
+            .and_then(|block| block.ok_or_else(|| anyhow!("No block found")))
+            .and_then(|block| block.hash.ok_or_else(|| anyhow!("Block has no hash")));
+        match result {
+            Ok(block_hash) => return Ok(block_hash),
+            Err(err) if attempt < BLOCK_HASH_ATTEMPTS => {
+                debug!(block.number = block_number, attempt, err=?err, "Retrying block hash lookup.");
+                attempt += 1;
+                sleep(BLOCK_HASH_RETRY_DELAY).await;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
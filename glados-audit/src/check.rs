@@ -0,0 +1,103 @@
+use std::{path::Path, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use entity::{content, content_audit::SelectionStrategy};
+use ethportal_api::utils::bytes::{hex_decode, hex_encode};
+use ethportal_api::{HistoryContentKey, OverlayContentKey};
+use glados_core::jsonrpc::{PortalClient, DEFAULT_REQUEST_TIMEOUT};
+use sea_orm::DatabaseConnection;
+
+use crate::{
+    proof::read_pre_merge_accumulator, record_audit, request_audit, AuditConfig, AuditResponse,
+    AuditTask,
+};
+
+/// The config of the audits run from the command line, which check header proofs
+/// against the accumulator at `pre_merge_accumulator` when one is given, as the audit
+/// process does with `--pre-merge-accumulator`.
+pub(crate) fn command_audit_config(pre_merge_accumulator: Option<&Path>) -> Result<AuditConfig> {
+    Ok(AuditConfig {
+        pre_merge_accumulator: match pre_merge_accumulator {
+            Some(path) => Some(Arc::new(read_pre_merge_accumulator(path)?)),
+            None => None,
+        },
+        ..Default::default()
+    })
+}
+
+/// Audits a single content key right away and prints everything learned about it.
+///
+/// The request is not retried, so that the error from the node is shown as is.
+/// When a database connection is given, the content key is stored if needed and
+/// the audit is recorded like any other.
+pub async fn run_check(
+    content_key: &str,
+    portal_client: &str,
+    pre_merge_accumulator: Option<&Path>,
+    conn: Option<&DatabaseConnection>,
+) -> Result<()> {
+    let raw = hex_decode(content_key)?;
+    let content_key =
+        HistoryContentKey::try_from(raw).map_err(|e| anyhow!("Invalid content key: {e}"))?;
+    let mut client = PortalClient::from(portal_client.to_string(), DEFAULT_REQUEST_TIMEOUT).await?;
+    client.api.max_retries = 0;
+
+    let task = AuditTask {
+        strategy: SelectionStrategy::SpecificContentKey,
+        content_key: content_key.clone(),
+        selected_at: Utc::now(),
+    };
+    let config = command_audit_config(pre_merge_accumulator)?;
+    let response = request_audit(&task, &client, &config).await;
+
+    println!("content key:    {}", hex_encode(content_key.to_bytes()));
+    println!("content id:     {}", hex_encode(content_key.content_id()));
+    println!(
+        "client:         {} ({})",
+        client.client_info, client.api.client_url
+    );
+    print_response(&response);
+
+    let Some(conn) = conn else {
+        return Ok(());
+    };
+    let stored = content::get_or_create(&content_key, conn).await?;
+    println!(
+        "stored id:      {} (matches content key)",
+        hex_encode(&stored.content_id)
+    );
+    match record_audit(task, &client, response, None, conn, &config).await {
+        Ok(()) => {
+            println!("persisted:      yes");
+            Ok(())
+        }
+        Err(e) => {
+            println!("persisted:      no");
+            Err(e)
+        }
+    }
+}
+
+fn print_response(response: &AuditResponse) {
+    println!("outcome:        {}", response.outcome.as_text());
+    match response.duration_ms {
+        Some(duration_ms) => println!("duration:       {duration_ms} ms"),
+        None => println!("duration:       -"),
+    }
+    match response.content_length {
+        Some(content_length) => println!("content length: {content_length} bytes"),
+        None => println!("content length: -"),
+    }
+    match response.content_id_verified {
+        Some(true) => println!("id verified:    yes"),
+        Some(false) => println!("id verified:    no"),
+        None => println!("id verified:    -"),
+    }
+    if let Some(error) = &response.error {
+        println!("error:          {error}");
+    }
+    if !response.trace.is_empty() {
+        println!("trace:          {}", response.trace);
+    }
+}
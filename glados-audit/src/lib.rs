@@ -1,35 +1,86 @@
-use anyhow::Result;
-use cli::Args;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use cli::{Args, ContentType, DEFAULT_DB_URL};
+use ethportal_api::types::execution::header::Header;
+use ethportal_api::types::node_id::NodeId;
 use ethportal_api::utils::bytes::{hex_decode, hex_encode};
-use ethportal_api::{HistoryContentKey, OverlayContentKey};
-use sea_orm::DatabaseConnection;
+use ethportal_api::{BlockHeaderKey, HistoryContentKey, OverlayContentKey};
+use sea_orm::{DatabaseConnection, Set};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    future::Future,
+    ops::RangeInclusive,
     sync::{
-        atomic::{AtomicU8, Ordering},
+        atomic::{AtomicUsize, Ordering},
         Arc,
     },
     thread::available_parallelism,
 };
 use tokio::{
-    sync::mpsc::{self, Receiver},
-    time::{sleep, Duration},
+    sync::{
+        mpsc::{self, error::TryRecvError, Receiver},
+        Mutex,
+    },
+    time::{sleep, timeout, Duration, Instant},
 };
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info, info_span, warn, Instrument};
 
 use entity::{
     client_info, content,
-    content_audit::{self, SelectionStrategy},
+    content_audit::{self, AuditOutcome, SelectionStrategy},
     execution_metadata, node,
 };
-use glados_core::jsonrpc::PortalClient;
+use glados_core::{
+    jsonrpc::{Content, JsonRpcError, PortalClient, PortalClientError, DEFAULT_REQUEST_TIMEOUT},
+    metrics::AuditMetrics,
+    trigger::AuditTrigger,
+};
 
-use crate::{selection::start_audit_selection_task, validation::content_is_valid};
+use crate::{
+    alert::PassRateAlert,
+    backfill::backfill_from_file,
+    check::run_check,
+    local_node::periodically_refresh_local_nodes,
+    manifest::run_verify_manifest,
+    proof::{read_pre_merge_accumulator, PreMergeAccumulator},
+    radius::ContentRadius,
+    rate_limit::RateLimiter,
+    report::write_report,
+    retention::periodically_prune_audits,
+    selection::{content_selector, selects_from_database, start_audit_selection_task},
+    shard::Shard,
+    stats::periodically_record_stats,
+    validation::{
+        content_id_matches, content_is_valid, proof_is_valid, verified_header,
+        verified_header_with_proof,
+    },
+    writer::{periodically_flush_audits, AuditWriter},
+};
 
+pub mod alert;
+pub mod backfill;
+pub mod block_range;
+pub mod check;
 pub mod cli;
+pub mod local_node;
+pub mod manifest;
+pub mod proof;
+pub mod radius;
+pub mod rate_limit;
+pub mod report;
+pub mod retention;
 pub(crate) mod selection;
+pub mod shard;
 pub mod stats;
-pub(crate) mod validation;
+pub mod validation;
+pub mod writer;
+
+/// Wait before the first retry of connecting to a Portal node at startup, doubled for each retry.
+const INITIAL_CONNECT_BACKOFF: Duration = Duration::from_secs(1);
+/// Longest wait between retries of connecting to a Portal node at startup.
+const MAX_CONNECT_BACKOFF: Duration = Duration::from_secs(10);
+/// Period between log messages reporting how many audit workers are busy.
+const WORKER_REPORT_PERIOD: Duration = Duration::from_secs(60);
 
 /// Configuration created from CLI arguments.
 #[derive(Clone, Debug)]
@@ -42,16 +93,76 @@ pub struct AuditConfig {
     pub strategies: Vec<SelectionStrategy>,
     /// Weight for each strategy.
     pub weights: HashMap<SelectionStrategy, u8>,
-    /// Number requests to a Portal node active at the same time.
+    /// Number of audit workers, each with one request to a Portal node active at a time.
     pub concurrency: u8,
     /// Portal Clients
     pub portal_clients: Vec<PortalClient>,
     /// Number of seconds between recording the current audit performance in audit_stats table.
     pub stats_recording_period: u64,
+    /// Type of content to audit.
+    pub content_type: ContentType,
+    /// Time allowed for each audit to fetch and check its content, if set.
+    pub content_timeout: Option<Duration>,
+    /// Number of seconds between database queries for content to audit.
+    pub audit_period_seconds: u64,
+    /// Seconds between selections of block headers, in a task of their own, if set.
+    pub header_period: Option<u64>,
+    /// Seconds between selections of block bodies, in a task of their own, if set.
+    pub body_period: Option<u64>,
+    /// Seconds between selections of receipts, in a task of their own, if set.
+    pub receipts_period: Option<u64>,
+    /// Maximum number of content keys each strategy selects per period.
+    pub batch_size: u64,
+    /// Content audited more recently than this is not re-selected by the random strategy.
+    pub staleness_threshold_seconds: u64,
+    /// Content whose latest audit failed is rechecked after this many seconds.
+    pub recheck_cooldown_seconds: u64,
+    /// Content whose latest audit passed within this many seconds is not selected by
+    /// the unconfirmed strategy.
+    pub skip_if_passed_within: u64,
+    /// The epoch boundary strategy selects pre-merge content among the first and last
+    /// this many blocks of each epoch.
+    pub epoch_boundary_window: u64,
+    /// Audits older than this many days are deleted, if set.
+    pub retention_days: Option<u32>,
+    /// Audit each task against every Portal client, rather than one of them.
+    pub compare_clients: bool,
+    /// Log audits without recording them or their stats in the database.
+    pub dry_run: bool,
+    /// Stored with each audit, if set.
+    pub run_label: Option<String>,
+    /// Notifies a webhook when the audit pass rate crosses a threshold, if set.
+    pub alert: Option<Arc<PassRateAlert>>,
+    /// Limits how often audits start, across all workers, if set.
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Buffers audit results to be written in batches, if set. Otherwise each
+    /// result is written as soon as its audit finishes.
+    pub audit_writer: Option<Arc<AuditWriter>>,
+    /// Blocks to audit once, instead of running the selection strategies.
+    pub block_range: Option<RangeInclusive<i32>>,
+    /// Header proofs are checked against this accumulator, if set.
+    pub pre_merge_accumulator: Option<Arc<PreMergeAccumulator>>,
+    /// Only content within this radius of the first Portal client's node id is
+    /// selected, if set.
+    pub radius: Option<ContentRadius>,
+    /// Only content in this shard is selected, if set.
+    pub shard: Option<Shard>,
+    /// Runs a selection cycle of every selection task on demand.
+    pub trigger: AuditTrigger,
+    /// Live state of the audit process.
+    pub metrics: Arc<AuditMetrics>,
 }
 
 impl AuditConfig {
     pub async fn from_args(args: Args) -> Result<AuditConfig> {
+        let Some(database_url) = args.database_url else {
+            return Err(anyhow::anyhow!(
+                "No database URL given, pass --database-url or set DATABASE_URL."
+            ));
+        };
+        if args.dry_run {
+            warn!("DRY RUN: audits are performed and logged, but nothing is recorded in the database.");
+        }
         let parallelism = available_parallelism()?.get() as u8;
         if args.concurrency > parallelism {
             warn!(
@@ -67,7 +178,13 @@ impl AuditConfig {
             )
         }
 
-        let strategies = match args.strategy {
+        if let Some(flag) = args.ignored_period_flag() {
+            return Err(anyhow::anyhow!(
+                "{flag} has no effect, as that content type is left out by --content-type."
+            ));
+        }
+
+        let mut strategies = match args.strategy {
             Some(s) => s,
             None => {
                 vec![
@@ -79,6 +196,9 @@ impl AuditConfig {
                 ]
             }
         };
+        if args.follow_head {
+            strategies.push(SelectionStrategy::FollowHead);
+        }
         let mut weights: HashMap<SelectionStrategy, u8> = HashMap::new();
         for strat in &strategies {
             let weight = match strat {
@@ -87,7 +207,11 @@ impl AuditConfig {
                 SelectionStrategy::Failed => args.failed_strategy_weight,
                 SelectionStrategy::SelectOldestUnaudited => args.oldest_strategy_weight,
                 SelectionStrategy::FourFours => args.four_fours_strategy_weight,
+                SelectionStrategy::FollowHead => args.follow_head_strategy_weight,
+                SelectionStrategy::Unconfirmed => args.unconfirmed_strategy_weight,
+                SelectionStrategy::EpochBoundary => args.epoch_boundary_strategy_weight,
                 SelectionStrategy::SpecificContentKey => 0,
+                SelectionStrategy::BlockRange => 0,
             };
             weights.insert(strat.clone(), weight);
         }
@@ -96,28 +220,224 @@ impl AuditConfig {
                 "No provider URL provided, required when `four_fours` strategy is enabled."
             ));
         }
+        if args.provider_url.is_empty() && args.follow_head {
+            return Err(anyhow::anyhow!(
+                "No provider URL provided, required to look up the chain head for `--follow-head`."
+            ));
+        }
+        let block_range = match (args.start_block, args.end_block) {
+            (Some(start), Some(end)) => {
+                if start > end {
+                    return Err(anyhow::anyhow!(
+                        "The start block must not be greater than the end block."
+                    ));
+                }
+                if args.provider_url.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "No provider URL provided, required to look up block hashes for a block range."
+                    ));
+                }
+                Some(start..=end)
+            }
+            _ => None,
+        };
+        let pre_merge_accumulator = match &args.pre_merge_accumulator {
+            Some(path) => Some(Arc::new(read_pre_merge_accumulator(path)?)),
+            None => None,
+        };
+        let request_timeout = Duration::from_millis(args.request_timeout_ms);
+        let connect_timeout = Duration::from_secs(args.startup_connect_timeout);
         let mut portal_clients: Vec<PortalClient> = vec![];
         for client_url in args.portal_client {
-            let client = PortalClient::from(client_url).await?;
+            let mut client =
+                connect_with_retry(client_url, request_timeout, connect_timeout).await?;
+            client.api.max_retries = args.max_retries;
             info!("Found a portal client with type: {:?}", client.client_info);
             portal_clients.push(client);
         }
+        let radius = match (args.radius, portal_clients.first()) {
+            (Some(radius), Some(client)) => {
+                let node_id = client.enr.node_id().raw();
+                info!(
+                    node.id = hex_encode(node_id),
+                    "Only selecting content within the given radius of the node."
+                );
+                Some(ContentRadius::new(node_id, radius))
+            }
+            (Some(_), None) => {
+                return Err(anyhow::anyhow!(
+                    "--radius needs a Portal client to measure distance from."
+                ))
+            }
+            (None, _) => None,
+        };
+        let shard = match (args.shard_index, args.shard_count) {
+            (Some(index), Some(count)) => {
+                info!(
+                    shard.index = index,
+                    shard.count = count,
+                    "Only selecting content in the given shard."
+                );
+                Some(Shard::new(index, count).map_err(|e| anyhow::anyhow!(e))?)
+            }
+            _ => None,
+        };
         Ok(AuditConfig {
-            database_url: args.database_url,
+            database_url,
             provider_url: args.provider_url,
             strategies,
             weights,
             concurrency: args.concurrency,
             portal_clients,
             stats_recording_period: args.stats_recording_period,
+            content_type: args.content_type,
+            content_timeout: args.content_timeout_ms.map(Duration::from_millis),
+            audit_period_seconds: args.audit_period_seconds,
+            header_period: args.header_period,
+            body_period: args.body_period,
+            receipts_period: args.receipts_period,
+            batch_size: args.batch_size,
+            staleness_threshold_seconds: args.staleness_threshold_seconds,
+            recheck_cooldown_seconds: args.recheck_cooldown_seconds,
+            skip_if_passed_within: args.skip_if_passed_within,
+            epoch_boundary_window: args.epoch_boundary_window,
+            retention_days: args.retention_days,
+            compare_clients: args.compare_clients,
+            dry_run: args.dry_run,
+            run_label: args.run_label.clone(),
+            alert: args.alert_webhook_url.map(|webhook_url| {
+                Arc::new(PassRateAlert::new(
+                    webhook_url,
+                    args.alert_threshold,
+                    args.alert_window as usize,
+                ))
+            }),
+            rate_limiter: args
+                .max_audits_per_second
+                .map(|per_second| Arc::new(RateLimiter::new(per_second))),
+            audit_writer: args.audit_write_batch_size.map(|batch_size| {
+                Arc::new(AuditWriter::new(
+                    batch_size as usize,
+                    Duration::from_millis(args.audit_flush_interval_ms),
+                ))
+            }),
+            block_range,
+            pre_merge_accumulator,
+            radius,
+            shard,
+            trigger: AuditTrigger::default(),
+            metrics: Arc::new(AuditMetrics::default()),
         })
     }
+
+    /// The configs of the selection tasks to run for a strategy.
+    ///
+    /// When a period is set for any content type, strategies that select content from
+    /// the database run a task for each type, with that type's period. Otherwise a strategy
+    /// runs a single task.
+    pub fn selection_configs(&self, strategy: &SelectionStrategy) -> Vec<AuditConfig> {
+        let periods = [
+            (ContentType::Header, self.header_period),
+            (ContentType::Body, self.body_period),
+            (ContentType::Receipts, self.receipts_period),
+        ];
+        if !selects_from_database(strategy) || periods.iter().all(|(_, period)| period.is_none()) {
+            return vec![self.clone()];
+        }
+        periods
+            .into_iter()
+            .filter(|(content_type, _)| {
+                self.content_type == ContentType::All || self.content_type == *content_type
+            })
+            .map(|(content_type, period)| AuditConfig {
+                content_type,
+                audit_period_seconds: period.unwrap_or(self.audit_period_seconds),
+                ..self.clone()
+            })
+            .collect()
+    }
+}
+
+/// Connects to a Portal node, retrying with backoff until `connect_timeout` has passed.
+///
+/// The node may still be starting when glados-audit starts, for example when its
+/// IPC socket has not been created yet.
+async fn connect_with_retry(
+    client_url: String,
+    request_timeout: Duration,
+    connect_timeout: Duration,
+) -> Result<PortalClient> {
+    let deadline = Instant::now() + connect_timeout;
+    let mut backoff = INITIAL_CONNECT_BACKOFF;
+    loop {
+        match PortalClient::from(client_url.clone(), request_timeout).await {
+            Ok(client) => return Ok(client),
+            Err(err) if Instant::now() + backoff < deadline => {
+                warn!(
+                    client.url = client_url,
+                    backoff_ms = backoff.as_millis() as u64,
+                    err=?err,
+                    "Could not connect to Portal node, retrying."
+                );
+                sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_CONNECT_BACKOFF);
+            }
+            Err(err) => {
+                return Err(anyhow::anyhow!(
+                    "Could not connect to Portal node at {client_url} within {}s: {err:?}",
+                    connect_timeout.as_secs()
+                ))
+            }
+        }
+    }
+}
+
+/// Configuration with the CLI defaults and no Portal clients.
+impl Default for AuditConfig {
+    fn default() -> Self {
+        let args = Args::default();
+        AuditConfig {
+            database_url: DEFAULT_DB_URL.to_string(),
+            provider_url: args.provider_url,
+            strategies: vec![],
+            weights: HashMap::new(),
+            concurrency: args.concurrency,
+            portal_clients: vec![],
+            stats_recording_period: args.stats_recording_period,
+            content_type: args.content_type,
+            content_timeout: args.content_timeout_ms.map(Duration::from_millis),
+            audit_period_seconds: args.audit_period_seconds,
+            header_period: args.header_period,
+            body_period: args.body_period,
+            receipts_period: args.receipts_period,
+            batch_size: args.batch_size,
+            staleness_threshold_seconds: args.staleness_threshold_seconds,
+            recheck_cooldown_seconds: args.recheck_cooldown_seconds,
+            skip_if_passed_within: args.skip_if_passed_within,
+            epoch_boundary_window: args.epoch_boundary_window,
+            retention_days: args.retention_days,
+            compare_clients: args.compare_clients,
+            dry_run: args.dry_run,
+            run_label: args.run_label.clone(),
+            alert: None,
+            rate_limiter: None,
+            audit_writer: None,
+            block_range: None,
+            pre_merge_accumulator: None,
+            radius: None,
+            shard: None,
+            trigger: AuditTrigger::default(),
+            metrics: Arc::new(AuditMetrics::default()),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct AuditTask {
     pub strategy: SelectionStrategy,
     pub content_key: HistoryContentKey,
+    /// When the content was selected, for reporting how far behind the workers are.
+    pub selected_at: DateTime<Utc>,
 }
 
 // Associates strategies with their channels and weights.
@@ -125,7 +445,33 @@ pub struct AuditTask {
 pub struct TaskChannel {
     strategy: SelectionStrategy,
     weight: u8,
-    rx: Receiver<AuditTask>,
+    /// A channel for each selection task of the strategy, so that a task selecting
+    /// often cannot fill the channel of another.
+    rxs: Vec<Receiver<AuditTask>>,
+    /// The channel to take the next task from.
+    next: usize,
+}
+
+impl TaskChannel {
+    /// Takes a task from the strategy's channels, trying them in turn from the one after
+    /// the last task taken.
+    ///
+    /// Returns [TryRecvError::Disconnected] once every channel is closed and empty.
+    fn try_recv(&mut self) -> Result<AuditTask, TryRecvError> {
+        let mut result = Err(TryRecvError::Disconnected);
+        for offset in 0..self.rxs.len() {
+            let index = (self.next + offset) % self.rxs.len();
+            match self.rxs[index].try_recv() {
+                Ok(task) => {
+                    self.next = (index + 1) % self.rxs.len();
+                    return Ok(task);
+                }
+                Err(TryRecvError::Empty) => result = Err(TryRecvError::Empty),
+                Err(TryRecvError::Disconnected) => {}
+            }
+        }
+        result
+    }
 }
 
 pub async fn run_glados_command(conn: DatabaseConnection, command: cli::Command) -> Result<()> {
@@ -135,6 +481,44 @@ pub async fn run_glados_command(conn: DatabaseConnection, command: cli::Command)
             portal_client,
             ..
         } => (content_key, portal_client),
+        cli::Command::Backfill { path, .. } => return backfill_from_file(&path, &conn).await,
+        cli::Command::Report {
+            output,
+            format,
+            window,
+            min_failures,
+            ..
+        } => return write_report(&output, format, window, min_failures, &conn).await,
+        cli::Command::Check {
+            content_key,
+            portal_client,
+            persist,
+            pre_merge_accumulator,
+            ..
+        } => {
+            return run_check(
+                &content_key,
+                &portal_client,
+                pre_merge_accumulator.as_deref(),
+                persist.then_some(&conn),
+            )
+            .await
+        }
+        cli::Command::VerifyManifest {
+            path,
+            portal_client,
+            persist,
+            pre_merge_accumulator,
+            ..
+        } => {
+            return run_verify_manifest(
+                &path,
+                &portal_client,
+                pre_merge_accumulator.as_deref(),
+                persist.then_some(&conn),
+            )
+            .await
+        }
     };
     let content_key = hex_decode(&content_key).unwrap();
     let content_key = HistoryContentKey::try_from(content_key).unwrap();
@@ -142,245 +526,772 @@ pub async fn run_glados_command(conn: DatabaseConnection, command: cli::Command)
     let task = AuditTask {
         strategy: SelectionStrategy::SpecificContentKey,
         content_key,
+        selected_at: Utc::now(),
     };
-    let client = PortalClient::from(portal_client).await?;
-    let active_threads = Arc::new(AtomicU8::new(0));
-    perform_single_audit(active_threads, task, client.clone(), conn).await;
+    let client = PortalClient::from(portal_client, DEFAULT_REQUEST_TIMEOUT).await?;
+    perform_single_audit(task, client, conn, Arc::new(AuditConfig::default()), None).await;
     Ok(())
 }
 
+/// Starts the tasks that run alongside the audits: recording audit stats, refreshing
+/// the info of the audited Portal nodes and, if set, deleting old audits and
+/// writing buffered audit results.
+///
+/// None of these run in a dry run, as they all write to the database.
+pub fn spawn_periodic_tasks(conn: &DatabaseConnection, config: &AuditConfig) {
+    if config.dry_run {
+        return;
+    }
+    tokio::spawn(periodically_record_stats(
+        Duration::from_secs(config.stats_recording_period),
+        config.run_label.clone(),
+        conn.clone(),
+    ));
+    if let Some(retention_days) = config.retention_days {
+        tokio::spawn(periodically_prune_audits(retention_days, conn.clone()));
+    }
+    if let Some(audit_writer) = &config.audit_writer {
+        tokio::spawn(periodically_flush_audits(
+            audit_writer.clone(),
+            conn.clone(),
+        ));
+    }
+    if !config.portal_clients.is_empty() {
+        tokio::spawn(periodically_refresh_local_nodes(
+            config
+                .portal_clients
+                .iter()
+                .map(|client| client.api.clone())
+                .collect(),
+            conn.clone(),
+        ));
+    }
+}
+
 pub async fn run_glados_audit(conn: DatabaseConnection, config: AuditConfig) {
     let mut task_channels: Vec<TaskChannel> = vec![];
+    let mut selection_tasks = vec![];
     for strategy in &config.strategies {
-        // Each strategy sends tasks to a separate channel.
-        let (tx, rx) = mpsc::channel::<AuditTask>(100);
+        let selectors: Vec<_> = config
+            .selection_configs(strategy)
+            .into_iter()
+            .filter_map(|task_config| {
+                content_selector(strategy, &task_config).map(|selector| (selector, task_config))
+            })
+            .collect();
+        if selectors.is_empty() {
+            continue;
+        }
         let Some(weight) = config.weights.get(strategy) else {
             error!(strategy=?strategy, "no weight for strategy");
             return;
         };
-        let task_channel = TaskChannel {
+        // Strategies generate tasks in their own thread for their own channel, with
+        // a thread and channel for each content type if they have their own periods.
+        let mut rxs = vec![];
+        for (selector, task_config) in selectors {
+            let (tx, rx) = mpsc::channel::<AuditTask>(100);
+            rxs.push(rx);
+            selection_tasks.push(tokio::spawn(start_audit_selection_task(
+                selector,
+                tx,
+                conn.clone(),
+                task_config,
+            )));
+        }
+        task_channels.push(TaskChannel {
             strategy: strategy.clone(),
             weight: *weight,
-            rx,
-        };
-        task_channels.push(task_channel);
-        // Strategies generate tasks in their own thread for their own channel.
-        tokio::spawn(start_audit_selection_task(
-            strategy.clone(),
-            tx,
-            conn.clone(),
-            config.clone(),
-        ));
+            rxs,
+            next: 0,
+        });
     }
     // Collation of generated tasks, taken proportional to weights.
     let (collation_tx, collation_rx) = mpsc::channel::<AuditTask>(100);
-    tokio::spawn(start_collation(collation_tx, task_channels));
+    tokio::spawn(start_collation(
+        collation_tx,
+        task_channels,
+        config.metrics.clone(),
+    ));
     // Perform collated audit tasks.
-    tokio::spawn(perform_content_audits(config, collation_rx, conn));
+    let audits_performed = Arc::new(AtomicUsize::new(0));
+    let audit_writer = config.audit_writer.clone();
+    let writer_conn = conn.clone();
+    let audits = tokio::spawn(perform_content_audits(
+        config,
+        collation_rx,
+        conn,
+        audits_performed.clone(),
+    ));
     debug!("setting up CTRL+C listener");
     tokio::signal::ctrl_c()
         .await
         .expect("failed to pause until ctrl-c");
 
-    info!("got CTRL+C. shutting down...");
+    // Stopping the selection tasks closes their channels. Collation and the audit
+    // workers then finish once the tasks already queued have been audited.
+    info!("got CTRL+C. stopping audit selection and draining the audit queue...");
+    for selection_task in selection_tasks {
+        selection_task.abort();
+    }
+    let performed_before_shutdown = audits_performed.load(Ordering::Relaxed);
+    tokio::select! {
+        _ = audits => {
+            let drained = audits_performed.load(Ordering::Relaxed) - performed_before_shutdown;
+            info!(audits.drained = drained, "audit queue drained. shutting down...");
+        }
+        _ = tokio::signal::ctrl_c() => {
+            warn!("got second CTRL+C. shutting down without draining the audit queue...");
+            // Audits still being written are lost, but those already finished are kept.
+            if let Some(audit_writer) = audit_writer {
+                audit_writer.flush(&writer_conn).await;
+            }
+        }
+    }
 }
 
 /// Listens to tasks coming on different strategy channels and selects
-/// according to strategy weight. Collated audit tasks are sent in a single
+/// according to strategy weight, a strategy with a task per content type taking
+/// from each type's channel in turn. Collated audit tasks are sent in a single
 /// channel for completion.
+///
+/// Returns once every strategy channel has been closed and emptied.
 async fn start_collation(
     collation_tx: mpsc::Sender<AuditTask>,
     mut task_channels: Vec<TaskChannel>,
+    metrics: Arc<AuditMetrics>,
 ) {
     loop {
+        let mut all_strategies_stopped = true;
         for tasks in task_channels.iter_mut() {
             debug!(strategy=?tasks.strategy, max=tasks.weight, "collating");
             for _ in 0..tasks.weight {
-                match tasks.rx.try_recv() {
-                    Ok(task) => collation_tx
-                        .send(task)
-                        .await
-                        .expect("Unable to collate task"),
-                    Err(_) => break,
+                match tasks.try_recv() {
+                    Ok(task) => {
+                        all_strategies_stopped = false;
+                        collation_tx
+                            .send(task)
+                            .await
+                            .expect("Unable to collate task")
+                    }
+                    Err(TryRecvError::Empty) => {
+                        all_strategies_stopped = false;
+                        break;
+                    }
+                    Err(TryRecvError::Disconnected) => break,
                 }
             }
         }
+        metrics.queue_depth.store(
+            collation_tx.max_capacity() - collation_tx.capacity(),
+            Ordering::Relaxed,
+        );
+        if all_strategies_stopped {
+            debug!("all strategy channels closed, stopping collation");
+            return;
+        }
     }
 }
 
+/// Performs collated audit tasks using a pool of workers.
+///
+/// Each worker is assigned a Portal client (round robin over the configured clients)
+/// and pulls tasks from the shared channel until it closes. When comparing clients,
+/// every worker audits each task against all of the configured clients instead.
+/// Buffered audit results are written once all of the workers have stopped.
+/// Content already being audited by one worker is skipped by the others.
 async fn perform_content_audits(
     config: AuditConfig,
-    mut rx: mpsc::Receiver<AuditTask>,
+    rx: mpsc::Receiver<AuditTask>,
     conn: DatabaseConnection,
+    audits_performed: Arc<AtomicUsize>,
 ) {
-    let concurrency = config.concurrency;
-    let active_threads = Arc::new(AtomicU8::new(0));
+    if config.portal_clients.is_empty() {
+        error!("Empty list of clients for audit.");
+        return;
+    }
+    let rx = Arc::new(Mutex::new(rx));
+    let config = Arc::new(config);
+    let in_flight = Arc::new(InFlightAudits::default());
+    let report = tokio::spawn(periodically_report_workers(config.metrics.clone()));
+    let mut workers = vec![];
+    for (worker_id, client) in (0..config.concurrency).zip(config.portal_clients.iter().cycle()) {
+        workers.push(tokio::spawn(run_audit_worker(
+            worker_id,
+            rx.clone(),
+            client.clone(),
+            conn.clone(),
+            config.clone(),
+            in_flight.clone(),
+            audits_performed.clone(),
+        )));
+    }
+    for worker in workers {
+        if let Err(err) = worker.await {
+            error!(err=?err, "Audit worker stopped unexpectedly.");
+        }
+    }
+    if let Some(audit_writer) = &config.audit_writer {
+        audit_writer.flush(&conn).await;
+    }
+    report.abort();
+}
+
+/// Logs how many audit workers are busy, to help tune `--concurrency`.
+async fn periodically_report_workers(metrics: Arc<AuditMetrics>) {
+    let mut interval = tokio::time::interval(WORKER_REPORT_PERIOD);
+    loop {
+        interval.tick().await;
+        info!(
+            audit.workers = metrics.workers.load(Ordering::Relaxed),
+            audit.workers.active = metrics.active_workers.load(Ordering::Relaxed),
+            audit.queue_depth = metrics.queue_depth.load(Ordering::Relaxed),
+            audit.queue_lag_secs = metrics.queue_lag(Utc::now()).as_secs(),
+            "Audit worker utilization."
+        );
+    }
+}
+
+/// Adds one to a gauge until dropped, which also happens if the task holding it panics.
+struct GaugeGuard<'a>(&'a AtomicUsize);
+
+impl<'a> GaugeGuard<'a> {
+    fn increment(gauge: &'a AtomicUsize) -> Self {
+        gauge.fetch_add(1, Ordering::Relaxed);
+        GaugeGuard(gauge)
+    }
+}
+
+impl Drop for GaugeGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Content ids being audited by any worker.
+#[derive(Debug, Default)]
+struct InFlightAudits(std::sync::Mutex<HashSet<[u8; 32]>>);
+
+impl InFlightAudits {
+    /// Marks content as being audited until the returned guard is dropped, or returns
+    /// `None` if it is already being audited.
+    fn start(self: &Arc<Self>, content_id: [u8; 32]) -> Option<InFlightGuard> {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(content_id)
+            .then(|| InFlightGuard {
+                in_flight: self.clone(),
+                content_id,
+            })
+    }
+}
 
-    let mut cycle_of_clients = config.portal_clients.iter().cycle();
+/// Removes content from the in flight set when dropped, which also happens if the
+/// audit panics.
+///
+/// The audits of a task share the guard, and the audit writer holds on to it until
+/// they are written, so that the content is not audited again before its audits are
+/// stored.
+#[derive(Debug)]
+pub struct InFlightGuard {
+    in_flight: Arc<InFlightAudits>,
+    content_id: [u8; 32],
+}
 
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.in_flight.0.lock().unwrap().remove(&self.content_id);
+    }
+}
+
+/// Audits tasks from the shared channel one at a time.
+///
+/// Each audit runs in its own task, so a panic during an audit is logged and
+/// the worker carries on with the next task.
+async fn run_audit_worker(
+    worker_id: u8,
+    rx: Arc<Mutex<Receiver<AuditTask>>>,
+    client: PortalClient,
+    conn: DatabaseConnection,
+    config: Arc<AuditConfig>,
+    in_flight: Arc<InFlightAudits>,
+    audits_performed: Arc<AtomicUsize>,
+) {
+    debug!(
+        audit.worker = worker_id,
+        client.url = client.api.client_url.clone(),
+        "Starting audit worker."
+    );
+    let _running = GaugeGuard::increment(&config.metrics.workers);
     loop {
-        let active_count = active_threads.load(Ordering::Relaxed);
-        if active_count >= concurrency {
-            // Each audit is performed in new thread if enough concurrency is available.
+        // The lock is only held while waiting for the next task.
+        let Some(task) = rx.lock().await.recv().await else {
             debug!(
-                active.threads = active_count,
-                max.threads = concurrency,
-                "Waiting for responses on all audit threads... Sleeping..."
+                audit.worker = worker_id,
+                "Audit channel closed, stopping worker."
             );
-            sleep(Duration::from_millis(5000)).await;
-            continue;
-        }
-
+            return;
+        };
+        let _active = GaugeGuard::increment(&config.metrics.active_workers);
+        config.metrics.record_task_taken(task.selected_at);
+        let content_key = hex_encode(task.content_key.to_bytes());
         debug!(
-            active.threads = active_count,
-            max.threads = concurrency,
-            "Checking Rx channel for audits"
+            audit.worker = worker_id,
+            content.key = content_key,
+            "Worker picked up audit task."
         );
-
-        match rx.recv().await {
-            Some(task) => {
-                active_threads.fetch_add(1, Ordering::Relaxed);
-                let client = match cycle_of_clients.next() {
-                    Some(client) => client,
-                    None => {
-                        error!("Empty list of clients for audit.");
-                        return;
-                    }
-                };
-                tokio::spawn(perform_single_audit(
-                    active_threads.clone(),
-                    task,
-                    client.clone(),
-                    conn.clone(),
-                ))
+        let Some(in_flight_guard) = in_flight.start(task.content_key.content_id()) else {
+            info!(
+                audit.worker = worker_id,
+                content.key = content_key,
+                "Content is already being audited, skipping duplicate task."
+            );
+            continue;
+        };
+        let in_flight_guard = Arc::new(in_flight_guard);
+        let clients = match config.compare_clients {
+            true => config.portal_clients.clone(),
+            false => vec![client.clone()],
+        };
+        for audit_client in clients {
+            if let Some(rate_limiter) = &config.rate_limiter {
+                rate_limiter.acquire().await;
             }
-            None => {
-                continue;
+            // Events logged during the audit carry the content key and id from this span.
+            let span = info_span!(
+                "audit",
+                content.key = content_key,
+                content.id = hex_encode(task.content_key.content_id()),
+            );
+            let audit = tokio::spawn(
+                perform_single_audit(
+                    task.clone(),
+                    audit_client,
+                    conn.clone(),
+                    config.clone(),
+                    Some(in_flight_guard.clone()),
+                )
+                .instrument(span),
+            );
+            if let Err(err) = audit.await {
+                error!(
+                    audit.worker = worker_id,
+                    content.key = content_key,
+                    err=?err,
+                    "Audit task panicked."
+                );
             }
-        };
+            audits_performed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Requests content from a Portal node.
+///
+/// Content that the node could not find is a valid response. The duration of the
+/// request, including any reconnects after transport errors, is returned with the
+/// response.
+async fn request_content(
+    client: &PortalClient,
+    content_key: &HistoryContentKey,
+) -> Result<(Option<Content>, String, Duration), JsonRpcError> {
+    let request_start = Instant::now();
+    let (content, trace) = if client.clone().supports_trace() {
+        client
+            .api
+            .clone()
+            .get_content_with_trace(content_key)
+            .await?
+    } else {
+        (
+            client.api.clone().get_content(content_key).await?,
+            "".to_owned(),
+        )
+    };
+    Ok((content, trace, request_start.elapsed()))
+}
+
+/// Reads the id of the node that returned the content from a query trace.
+///
+/// Returns `None` if there is no trace, as for clients without trace support, or
+/// if the content was not found.
+fn served_by_node_id(trace: &str) -> Option<NodeId> {
+    let trace: serde_json::Value = serde_json::from_str(trace).ok()?;
+    let node_id = hex_decode(trace.get("receivedFrom")?.as_str()?).ok()?;
+    Some(NodeId(node_id.try_into().ok()?))
+}
+
+/// Counts the responses in a query trace that returned closer peers instead of the content.
+///
+/// Returns `None` if there is no trace, as for clients without trace support.
+fn closer_peer_responses(trace: &str) -> Option<i32> {
+    let trace: serde_json::Value = serde_json::from_str(trace).ok()?;
+    let responses = trace.get("responses")?.as_object()?;
+    let count = responses
+        .values()
+        .filter(|response| {
+            response
+                .get("respondedWith")
+                .and_then(|peers| peers.as_array())
+                .is_some_and(|peers| !peers.is_empty())
+        })
+        .count();
+    Some(i32::try_from(count).unwrap_or(i32::MAX))
+}
+
+/// Classifies a content request that failed after all retries.
+///
+/// Responses that could not be read are grouped with transport errors, as the
+/// node's answer, if any, is unknown.
+fn request_failure_outcome(err: &PortalClientError) -> AuditOutcome {
+    match err {
+        PortalClientError::Timeout => AuditOutcome::Timeout,
+        PortalClientError::Rpc { .. } => AuditOutcome::RpcError,
+        PortalClientError::Transport(_) | PortalClientError::InvalidResponse(_) => {
+            AuditOutcome::TransportError
+        }
+    }
+}
+
+/// Fetches the header of the block a body or receipts key belongs to, for checking that content.
+///
+/// Returns `None` for other keys, or if the node can't provide a header matching the block hash.
+async fn fetch_verified_header(
+    client: &PortalClient,
+    content_key: &HistoryContentKey,
+) -> Option<Header> {
+    let block_hash = match content_key {
+        HistoryContentKey::BlockBody(key) => key.block_hash,
+        HistoryContentKey::BlockReceipts(key) => key.block_hash,
+        _ => return None,
+    };
+    let header_key = HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey { block_hash });
+    match client.api.clone().get_content(&header_key).await {
+        Ok(Some(content)) => verified_header(&header_key, &content.raw),
+        Ok(None) => None,
+        Err(err) => {
+            debug!(content.key=hex_encode(header_key.to_bytes()), err=?err, "Could not fetch header to check content against.");
+            None
+        }
     }
 }
 
+/// What a Portal node returned when asked for audited content, and how it was classified.
+#[derive(Clone, Debug)]
+pub struct AuditResponse {
+    pub outcome: AuditOutcome,
+    pub trace: String,
+    /// Time taken by the final request, absent if the request failed.
+    pub duration_ms: Option<i32>,
+    /// Size of the content returned, absent if none was.
+    pub content_length: Option<usize>,
+    /// Number of the block, for a valid block header.
+    pub block_number: Option<i32>,
+    /// Whether the content returned was shown to be that of the requested content id,
+    /// absent if none was returned or it could not be told.
+    pub content_id_verified: Option<bool>,
+    /// Why the request failed, if it did.
+    pub error: Option<String>,
+}
+
 /// Performs an audit against a Portal node.
 ///
-/// After auditing finishes the thread counter is deprecated. This
-/// applies even if the audit process encounters an error.
+/// Requests that fail after all retries are recorded as transport errors, timeouts
+/// or JSON-RPC errors, along with the error itself. Audit stats leave these out so
+/// that they are not counted as content being unavailable.
 async fn perform_single_audit(
-    active_threads: Arc<AtomicU8>,
     task: AuditTask,
     client: PortalClient,
     conn: DatabaseConnection,
+    config: Arc<AuditConfig>,
+    in_flight: Option<Arc<InFlightGuard>>,
 ) {
-    let client_info = client.client_info.clone();
+    let response = request_audit(&task, &client, &config).await;
+    let content_key = hex_encode(task.content_key.to_bytes());
+    if let Err(e) = record_audit(task, &client, response, in_flight, &conn, &config).await {
+        error!(content.key = content_key, err=?e, "Could not record audit.");
+    }
+}
+
+/// Requests the content of an audit task from a Portal node and checks what came back.
+///
+/// An audit that takes longer than the content timeout, if one is configured, is
+/// abandoned and recorded as a timeout.
+async fn request_audit(
+    task: &AuditTask,
+    client: &PortalClient,
+    config: &AuditConfig,
+) -> AuditResponse {
+    with_content_timeout(config.content_timeout, fetch_audit(task, client, config))
+        .await
+        .unwrap_or_else(|elapsed| {
+            warn!(
+                content.key = hex_encode(task.content_key.to_bytes()),
+                client.url = client.api.client_url.clone(),
+                timeout = ?elapsed,
+                "Audit did not finish within the content timeout."
+            );
+            AuditResponse {
+                outcome: AuditOutcome::Timeout,
+                trace: "".to_owned(),
+                duration_ms: None,
+                content_length: None,
+                block_number: None,
+                content_id_verified: None,
+                error: Some(format!(
+                    "audit did not finish within {} ms",
+                    elapsed.as_millis()
+                )),
+            }
+        })
+}
+
+/// Waits for an audit, giving up after `limit` if one is set.
+///
+/// Returns the limit that elapsed if the audit did not finish in time.
+async fn with_content_timeout(
+    limit: Option<Duration>,
+    audit: impl Future<Output = AuditResponse>,
+) -> Result<AuditResponse, Duration> {
+    match limit {
+        Some(limit) => timeout(limit, audit).await.map_err(|_| limit),
+        None => Ok(audit.await),
+    }
+}
 
+/// Fetches the content of an audit task and checks it, without a time limit.
+///
+/// The proof of a header is also checked against the pre-merge accumulator, when one
+/// is configured.
+async fn fetch_audit(
+    task: &AuditTask,
+    client: &PortalClient,
+    config: &AuditConfig,
+) -> AuditResponse {
     debug!(
         content.key = hex_encode(task.content_key.to_bytes()),
         client.url = client.api.client_url.clone(),
         "auditing content",
     );
-    let (content_response, trace) = if client.clone().supports_trace() {
-        match client.api.get_content_with_trace(&task.content_key).await {
-            Ok(c) => c,
-            Err(e) => {
-                error!(
-                    content.key=hex_encode(task.content_key.to_bytes()),
-                    err=?e,
-                    "Problem requesting content with trace from Portal node."
-                );
-                active_threads.fetch_sub(1, Ordering::Relaxed);
-                return;
+    match request_content(client, &task.content_key).await {
+        Ok((content_response, trace, duration)) => {
+            let content_length = content_response.as_ref().map(|content| content.raw.len());
+            let (outcome, content_id_verified, block_number) = match content_response {
+                Some(content_bytes) => {
+                    let header = fetch_verified_header(client, &task.content_key).await;
+                    check_content(
+                        &task.content_key,
+                        &content_bytes.raw,
+                        header.as_ref(),
+                        config,
+                    )
+                }
+                None => (AuditOutcome::NotFound, None, None),
+            };
+            AuditResponse {
+                outcome,
+                trace,
+                duration_ms: Some(i32::try_from(duration.as_millis()).unwrap_or(i32::MAX)),
+                content_length,
+                block_number,
+                content_id_verified,
+                error: None,
             }
         }
-    } else {
-        match client.api.get_content(&task.content_key).await {
-            Ok(c) => (c, "".to_owned()),
-            Err(e) => {
-                error!(
+        Err(e) => {
+            let err = PortalClientError::from(e);
+            let outcome = request_failure_outcome(&err);
+            match &err {
+                PortalClientError::Rpc { code, message } => error!(
+                    content.key = hex_encode(task.content_key.to_bytes()),
+                    rpc.code = code,
+                    rpc.message = message,
+                    "Portal node returned an error for content request."
+                ),
+                _ => error!(
                     content.key=hex_encode(task.content_key.to_bytes()),
-                    err=?e,
+                    audit.outcome=outcome.as_text(),
+                    err=%err,
                     "Problem requesting content from Portal node."
-                );
-                active_threads.fetch_sub(1, Ordering::Relaxed);
-                return;
+                ),
+            }
+            AuditResponse {
+                outcome,
+                trace: "".to_owned(),
+                duration_ms: None,
+                content_length: None,
+                block_number: None,
+                content_id_verified: None,
+                error: Some(err.to_string()),
             }
         }
-    };
-
-    // If content was absent audit result is 'fail'.
-    let audit_result = match content_response {
-        Some(content_bytes) => content_is_valid(&task.content_key, &content_bytes.raw),
-        None => false,
-    };
+    }
+}
 
-    let content_key_model = match content::get(&task.content_key, &conn).await {
-        Ok(Some(m)) => m,
-        Ok(None) => {
-            error!(
-                content.key=?task.content_key,
-                audit.pass=?audit_result,
-                "Content key not found in db."
-            );
-            active_threads.fetch_sub(1, Ordering::Relaxed);
-            return;
-        }
-        Err(e) => {
-            error!(
-                content.key=?task.content_key,
-                err=?e,
-                "Could not look up content key in db."
-            );
-            active_threads.fetch_sub(1, Ordering::Relaxed);
-            return;
+/// Checks content returned by a node, giving the outcome of its audit, whether it was
+/// verified against its content id and, for a valid header, its block number.
+///
+/// Bodies and receipts are checked against `header` when one was found.
+fn check_content(
+    content_key: &HistoryContentKey,
+    content_bytes: &[u8],
+    header: Option<&Header>,
+    config: &AuditConfig,
+) -> (AuditOutcome, Option<bool>, Option<i32>) {
+    // A header is decoded once, to check it and its proof and for its block number.
+    // Only headers carry a proof.
+    let (content_valid, proof_valid, block_number) = match content_key {
+        HistoryContentKey::BlockHeaderWithProof(_) => {
+            match verified_header_with_proof(content_key, content_bytes) {
+                Some(own_header) => (
+                    true,
+                    config
+                        .pre_merge_accumulator
+                        .as_ref()
+                        .and_then(|accumulator| proof_is_valid(&own_header, accumulator)),
+                    i32::try_from(own_header.header.number).ok(),
+                ),
+                None => (false, None, None),
+            }
         }
+        _ => (
+            content_is_valid(content_key, content_bytes, header),
+            None,
+            None,
+        ),
     };
-
-    let client_info_id = match client_info::get_or_create(client_info, &conn).await {
-        Ok(client_info) => client_info.id,
-        Err(error) => {
-            error!(content.key=?task.content_key,
-                err=?error,
-                "Could not create/lookup client info in db."
-            );
-            return;
-        }
+    // Content that failed validation is not that of its id, and content that is only
+    // well formed passes without being tied to its id.
+    let content_id_verified = match content_valid {
+        true => content_id_matches(content_key, content_bytes, header),
+        false => Some(false),
+    };
+    let outcome = if !content_valid {
+        AuditOutcome::InvalidContent
+    } else if proof_valid == Some(false) {
+        AuditOutcome::InvalidProof
+    } else {
+        AuditOutcome::Success
     };
+    (outcome, content_id_verified, block_number)
+}
 
-    let node_id = match node::get_or_create(client.enr.node_id().into(), &conn).await {
-        Ok(enr) => enr.id,
-        Err(err) => {
-            error!(
-                err=?err,
-                "Failed to created node."
-            );
-            return;
-        }
+/// Stores the result of an audit and passes it on to the pass rate alert, if there is one.
+///
+/// The content key must already be in the database. In a dry run the result is only
+/// logged, and the alert is not told about it. The in flight guard of the task, if
+/// any, is held until the audit is written.
+///
+/// Returns an error if the audit could not be written. A buffered audit counts as
+/// written once it is handed to the audit writer.
+async fn record_audit(
+    task: AuditTask,
+    client: &PortalClient,
+    response: AuditResponse,
+    in_flight: Option<Arc<InFlightGuard>>,
+    conn: &DatabaseConnection,
+    config: &AuditConfig,
+) -> Result<()> {
+    let AuditResponse {
+        outcome,
+        trace,
+        duration_ms,
+        content_length,
+        block_number,
+        content_id_verified,
+        error: request_error,
+    } = response;
+    let audit_result = outcome == AuditOutcome::Success;
+    if config.dry_run {
+        info!(
+            content.key=hex_encode(task.content_key.to_bytes()),
+            client.url=client.api.client_url.clone(),
+            audit.pass=?audit_result,
+            audit.outcome=outcome.as_text(),
+            audit.duration_ms=?duration_ms,
+            audit.error=?request_error,
+            "Dry run, audit not recorded."
+        );
+        return Ok(());
+    }
+
+    let content_key_model = content::get(&task.content_key, conn)
+        .await
+        .context("Could not look up content key in db")?
+        .ok_or_else(|| anyhow!("Content key not found in db"))?;
+    let client_info_id = client_info::get_or_create(client.client_info.clone(), conn)
+        .await
+        .context("Could not create/lookup client info in db")?
+        .id;
+    let node_id = node::get_or_create(client.enr.node_id().into(), conn)
+        .await
+        .context("Failed to create node")?
+        .id;
+    let served_by = match served_by_node_id(&trace) {
+        Some(serving_node_id) => match node::get_or_create(serving_node_id, conn).await {
+            Ok(serving_node) => Some(serving_node.id),
+            Err(err) => {
+                error!(err=?err, "Failed to create serving node.");
+                None
+            }
+        },
+        None => None,
+    };
+    // Shows how far a failed lookup got, telling apart content that no peer had from
+    // lookups that could not make progress.
+    let closer_peers = match audit_result {
+        true => None,
+        false => closer_peer_responses(&trace),
     };
-    if let Err(e) = content_audit::create(
+    let mut audit = content_audit::new_audit(
         content_key_model.id,
         client_info_id,
         node_id,
-        audit_result,
+        outcome.clone(),
         task.strategy,
         trace,
-        &conn,
-    )
-    .await
-    {
-        error!(
-            content.key=?task.content_key,
-            err=?e,
-            "Could not create audit entry in db."
-        );
-        active_threads.fetch_sub(1, Ordering::Relaxed);
-        return;
-    };
+        duration_ms,
+        served_by,
+        request_error,
+        config.run_label.clone(),
+    );
+    audit.closer_peer_responses = Set(closer_peers);
+    audit.content_length = Set(content_length.map(|len| i32::try_from(len).unwrap_or(i32::MAX)));
+    audit.content_id_verified = Set(content_id_verified);
+    match &config.audit_writer {
+        Some(audit_writer) => audit_writer.push(audit, in_flight, conn).await,
+        None => {
+            content_audit::store(audit, conn)
+                .await
+                .context("Could not create audit entry in db")?;
+        }
+    }
+    // Failed requests say nothing about whether the content is available.
+    if let Some(alert) = &config.alert {
+        if !outcome.is_request_failure() {
+            alert.record_and_notify(audit_result).await;
+        }
+    }
+
+    // Headers give the number of their block, which other sources of content keys
+    // may not have stored.
+    if let Some(block_number) = block_number {
+        if let Err(e) =
+            execution_metadata::get_or_create(content_key_model.id, block_number, conn).await
+        {
+            error!(content.key=?task.content_key, err=?e, "Could not store block number of header.");
+        }
+    }
 
     // Display audit result with block metadata.
-    match execution_metadata::get(content_key_model.id, &conn).await {
+    match execution_metadata::get(content_key_model.id, conn).await {
         Ok(Some(b)) => {
             info!(
                 content.key=hex_encode(task.content_key.to_bytes()),
                 audit.pass=?audit_result,
+                audit.outcome=outcome.as_text(),
+                audit.duration_ms=?duration_ms,
                 block = b.block_number,
             );
         }
@@ -396,5 +1307,333 @@ async fn perform_single_audit(
                 err=?e,
                 "Problem getting block metadata."),
     };
-    active_threads.fetch_sub(1, Ordering::Relaxed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use glados_core::jsonrpc::{PortalApi, DEFAULT_MAX_RETRIES};
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::Database;
+
+    use super::*;
+
+    /// Tests that a strategy's channels are taken from in turn, so that one filling
+    /// faster does not hold back the others.
+    #[test]
+    fn test_task_channel_takes_in_turn() {
+        let task = |block_hash| AuditTask {
+            strategy: SelectionStrategy::Latest,
+            content_key: HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey { block_hash }),
+            selected_at: Utc::now(),
+        };
+        let (headers_tx, headers_rx) = mpsc::channel(10);
+        let (bodies_tx, bodies_rx) = mpsc::channel(10);
+        for block_hash in [[1; 32], [2; 32], [3; 32]] {
+            headers_tx.try_send(task(block_hash)).unwrap();
+        }
+        bodies_tx.try_send(task([4; 32])).unwrap();
+        let mut channel = TaskChannel {
+            strategy: SelectionStrategy::Latest,
+            weight: 1,
+            rxs: vec![headers_rx, bodies_rx],
+            next: 0,
+        };
+        let mut taken = vec![];
+        while let Ok(task) = channel.try_recv() {
+            taken.push(task.content_key);
+        }
+        assert_eq!(
+            taken,
+            vec![
+                task([1; 32]).content_key,
+                task([4; 32]).content_key,
+                task([2; 32]).content_key,
+                task([3; 32]).content_key,
+            ]
+        );
+        assert_eq!(channel.try_recv().unwrap_err(), TryRecvError::Empty);
+
+        drop(headers_tx);
+        drop(bodies_tx);
+        assert_eq!(channel.try_recv().unwrap_err(), TryRecvError::Disconnected);
+    }
+
+    /// Tests that a gauge is decremented when its guard is dropped, including by a panic.
+    #[test]
+    fn test_gauge_guard() {
+        let gauge = AtomicUsize::new(0);
+        {
+            let _first = GaugeGuard::increment(&gauge);
+            let _second = GaugeGuard::increment(&gauge);
+            assert_eq!(gauge.load(Ordering::Relaxed), 2);
+        }
+        assert_eq!(gauge.load(Ordering::Relaxed), 0);
+
+        let result = std::panic::catch_unwind(|| {
+            let _guard = GaugeGuard::increment(&gauge);
+            panic!("audit worker panicked");
+        });
+        assert!(result.is_err());
+        assert_eq!(gauge.load(Ordering::Relaxed), 0);
+    }
+
+    /// Tests that content can only be started once until its audit finishes.
+    #[test]
+    fn test_in_flight_audits() {
+        let in_flight = Arc::new(InFlightAudits::default());
+        {
+            let _first = in_flight.start([1; 32]).unwrap();
+            assert!(in_flight.start([1; 32]).is_none());
+            let _other = in_flight.start([2; 32]).unwrap();
+        }
+        assert!(in_flight.start([1; 32]).is_some());
+    }
+
+    /// Tests that strategies reading the database get a task per content type when
+    /// any type has its own period.
+    #[test]
+    fn test_selection_configs() {
+        let config = AuditConfig::default();
+        assert_eq!(
+            config.selection_configs(&SelectionStrategy::Latest).len(),
+            1
+        );
+
+        let config = AuditConfig {
+            audit_period_seconds: 10,
+            header_period: Some(5),
+            ..Default::default()
+        };
+        let periods: Vec<(ContentType, u64)> = config
+            .selection_configs(&SelectionStrategy::Latest)
+            .into_iter()
+            .map(|config| (config.content_type, config.audit_period_seconds))
+            .collect();
+        assert_eq!(
+            periods,
+            vec![
+                (ContentType::Header, 5),
+                (ContentType::Body, 10),
+                (ContentType::Receipts, 10)
+            ]
+        );
+        assert_eq!(
+            config
+                .selection_configs(&SelectionStrategy::FollowHead)
+                .len(),
+            1
+        );
+
+        let config = AuditConfig {
+            content_type: ContentType::Body,
+            ..config
+        };
+        let configs = config.selection_configs(&SelectionStrategy::Random);
+        assert_eq!(configs.len(), 1);
+        assert_eq!(configs[0].content_type, ContentType::Body);
+    }
+
+    /// Tests that only the responses with closer peers are counted.
+    #[test]
+    fn test_closer_peer_responses() {
+        let trace = r#"{
+            "origin": "0x01",
+            "responses": {
+                "0x01": {"durationMs": 0, "respondedWith": ["0x02", "0x03"]},
+                "0x02": {"durationMs": 12, "respondedWith": ["0x04"]},
+                "0x03": {"durationMs": 15, "respondedWith": []}
+            }
+        }"#;
+        assert_eq!(closer_peer_responses(trace), Some(2));
+        assert_eq!(closer_peer_responses(r#"{"responses": {}}"#), Some(0));
+        assert_eq!(closer_peer_responses(""), None);
+    }
+
+    /// Tests that the serving node is read from a trace, and absent without one.
+    #[test]
+    fn test_served_by_node_id() {
+        let node_id_hex = "0x0102030405060708091011121314151617181920212223242526272829303132";
+        let trace = format!("{{\"origin\":\"0x00\",\"receivedFrom\":\"{node_id_hex}\"}}");
+        let node_id = served_by_node_id(&trace).unwrap();
+        assert_eq!(hex_encode(node_id.0), node_id_hex);
+
+        assert!(served_by_node_id("").is_none());
+        assert!(served_by_node_id("{\"origin\":\"0x00\"}").is_none());
+        assert!(served_by_node_id("{\"receivedFrom\":\"0x0102\"}").is_none());
+    }
+
+    /// Tests that an audit is abandoned once the content timeout elapses.
+    #[tokio::test]
+    async fn test_content_timeout() {
+        let response = AuditResponse {
+            outcome: AuditOutcome::Success,
+            trace: "".to_owned(),
+            duration_ms: Some(1),
+            content_length: Some(1),
+            block_number: None,
+            content_id_verified: Some(true),
+            error: None,
+        };
+        let finished = with_content_timeout(
+            Some(Duration::from_secs(10)),
+            std::future::ready(response.clone()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(finished.outcome, AuditOutcome::Success);
+
+        let unlimited = with_content_timeout(None, std::future::ready(response))
+            .await
+            .unwrap();
+        assert_eq!(unlimited.outcome, AuditOutcome::Success);
+
+        let limit = Duration::from_millis(10);
+        let hung = with_content_timeout(Some(limit), std::future::pending()).await;
+        assert_eq!(hung.unwrap_err(), limit);
+    }
+
+    /// Tests that checking a header gives its block number, which recording the audit
+    /// stores for the content.
+    #[tokio::test]
+    async fn test_record_audit_stores_header_block_number() {
+        let conn = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&conn, None).await.unwrap();
+        let config = AuditConfig::default();
+        // Mainnet block 1.
+        let content_bytes =
+            hex_decode(include_str!("../testdata/block_1_header.hex").trim()).unwrap();
+        let block_hash =
+            hex_decode("0x88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6")
+                .unwrap();
+        let content_key = HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+            block_hash: block_hash.try_into().unwrap(),
+        });
+        let content = content::get_or_create(&content_key, &conn).await.unwrap();
+
+        let (outcome, content_id_verified, block_number) =
+            check_content(&content_key, &content_bytes, None, &config);
+        assert_eq!(
+            (&outcome, content_id_verified, block_number),
+            (&AuditOutcome::Success, Some(true), Some(1))
+        );
+
+        let client = PortalClient {
+            api: PortalApi {
+                client_url: "http://127.0.0.1:8545".to_owned(),
+                request_timeout: DEFAULT_REQUEST_TIMEOUT,
+                max_retries: DEFAULT_MAX_RETRIES,
+            },
+            client_info: "trin v0.1.0".to_owned(),
+            enr: "enr:-IS4QHCYrYZbAKWCBRlAy5zzaDZXJBGkcnh4MHcBFZntXNFrdvJjX04jRzjzCBOonrkTfj499SZuOh8R33Ls8RRcy5wBgmlkgnY0gmlwhH8AAAGJc2VjcDI1NmsxoQPKY0yuDUmstAHYpMa2_oxVtw0RW_QAdpzBQA8yWM0xOIN1ZHCCdl8"
+                .parse()
+                .unwrap(),
+        };
+        let task = AuditTask {
+            strategy: SelectionStrategy::Latest,
+            content_key,
+            selected_at: Utc::now(),
+        };
+        let response = AuditResponse {
+            outcome,
+            trace: "".to_owned(),
+            duration_ms: Some(1),
+            content_length: Some(content_bytes.len()),
+            block_number,
+            content_id_verified,
+            error: None,
+        };
+        record_audit(task, &client, response, None, &conn, &config)
+            .await
+            .unwrap();
+
+        let metadata = execution_metadata::get(content.id, &conn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(metadata.block_number, 1);
+    }
+
+    /// Tests that content whose audit is waiting in the audit writer is not audited
+    /// again by the workers until the audit is written.
+    #[tokio::test]
+    async fn test_worker_holds_buffered_content_in_flight() {
+        let conn = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&conn, None).await.unwrap();
+        let content_key = HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+            block_hash: [1; 32],
+        });
+        content::get_or_create(&content_key, &conn).await.unwrap();
+        let audit_writer = Arc::new(AuditWriter::new(10, Duration::from_secs(60)));
+        let config = Arc::new(AuditConfig {
+            audit_writer: Some(audit_writer.clone()),
+            ..Default::default()
+        });
+        // Nothing listens on the discard port, so each audit is a quick transport error.
+        let client = PortalClient {
+            api: PortalApi {
+                client_url: "http://127.0.0.1:9".to_owned(),
+                request_timeout: Duration::from_secs(1),
+                max_retries: 0,
+            },
+            client_info: "trin v0.1.0".to_owned(),
+            enr: "enr:-IS4QHCYrYZbAKWCBRlAy5zzaDZXJBGkcnh4MHcBFZntXNFrdvJjX04jRzjzCBOonrkTfj499SZuOh8R33Ls8RRcy5wBgmlkgnY0gmlwhH8AAAGJc2VjcDI1NmsxoQPKY0yuDUmstAHYpMa2_oxVtw0RW_QAdpzBQA8yWM0xOIN1ZHCCdl8"
+                .parse()
+                .unwrap(),
+        };
+        let (tx, rx) = mpsc::channel(10);
+        for _ in 0..2 {
+            tx.try_send(AuditTask {
+                strategy: SelectionStrategy::Latest,
+                content_key: content_key.clone(),
+                selected_at: Utc::now(),
+            })
+            .unwrap();
+        }
+        drop(tx);
+        let in_flight = Arc::new(InFlightAudits::default());
+
+        run_audit_worker(
+            0,
+            Arc::new(Mutex::new(rx)),
+            client,
+            conn.clone(),
+            config,
+            in_flight.clone(),
+            Arc::new(AtomicUsize::new(0)),
+        )
+        .await;
+        // The second task was skipped, as the first audit was not yet written.
+        assert_eq!(audit_writer.pending().await, 1);
+        assert!(in_flight.start(content_key.content_id()).is_none());
+
+        audit_writer.flush(&conn).await;
+        assert!(in_flight.start(content_key.content_id()).is_some());
+    }
+
+    /// Tests that failed requests are recorded with the outcome matching why they failed.
+    #[test]
+    fn test_request_failure_outcome() {
+        let rpc_error = PortalClientError::Rpc {
+            code: -32603,
+            message: "Internal error".to_string(),
+        };
+        assert_eq!(request_failure_outcome(&rpc_error), AuditOutcome::RpcError);
+        assert_eq!(
+            request_failure_outcome(&PortalClientError::Timeout),
+            AuditOutcome::Timeout
+        );
+        let transport = PortalClientError::Transport("connection refused".to_string());
+        assert_eq!(
+            request_failure_outcome(&transport),
+            AuditOutcome::TransportError
+        );
+        let invalid = PortalClientError::InvalidResponse("received empty response".to_string());
+        assert_eq!(
+            request_failure_outcome(&invalid),
+            AuditOutcome::TransportError
+        );
+        assert!(AuditOutcome::RpcError.is_request_failure());
+        assert!(!AuditOutcome::NotFound.is_request_failure());
+    }
 }
@@ -0,0 +1,45 @@
+use entity::node;
+use glados_core::jsonrpc::{PortalApi, PortalClient};
+use sea_orm::DatabaseConnection;
+use tokio::time::{interval, Duration};
+use tracing::{debug, error, info};
+
+/// Time between refreshes of the info of the Portal nodes used for audits.
+const LOCAL_NODE_REFRESH_PERIOD: Duration = Duration::from_secs(300);
+
+/// Loops indefinitely, periodically storing what each Portal node reports about itself.
+///
+/// Runs regardless of whether anyone views the dashboard, so that the nodes are up
+/// to date after they restart with a new ENR or client version.
+pub async fn periodically_refresh_local_nodes(apis: Vec<PortalApi>, conn: DatabaseConnection) -> ! {
+    debug!(
+        nodes = apis.len(),
+        "initializing task for refreshing local node info"
+    );
+    let mut interval = interval(LOCAL_NODE_REFRESH_PERIOD);
+
+    loop {
+        interval.tick().await;
+        for api in &apis {
+            // Reads the client version and node info afresh.
+            let client = match PortalClient::from(api.client_url.clone(), api.request_timeout).await
+            {
+                Ok(client) => client,
+                Err(e) => {
+                    error!(client.url = api.client_url, err=?e, "failed to read local node info");
+                    continue;
+                }
+            };
+            match node::record_local_info(&client.enr, &client.client_info, &conn).await {
+                Ok(node) => info!(
+                    node.id = node.node_id_as_hex(),
+                    node.client_version = client.client_info,
+                    "refreshed local node info"
+                ),
+                Err(e) => {
+                    error!(client.url = api.client_url, err=?e, "failed to store local node info")
+                }
+            }
+        }
+    }
+}
@@ -1,19 +1,23 @@
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use clap::Parser;
-use glados_audit::stats::periodically_record_stats;
-use sea_orm::Database;
-use tokio::time::Duration;
+use glados_audit::block_range::run_block_range_audit;
+use glados_audit::check::run_check;
+use glados_audit::manifest::run_verify_manifest;
 use tracing::{debug, info};
 
 use glados_audit::cli::{Args, Command};
-use glados_audit::{run_glados_audit, run_glados_command, AuditConfig};
+use glados_audit::{run_glados_audit, run_glados_command, spawn_periodic_tasks, AuditConfig};
+use glados_core::{
+    logging::init_logging,
+    pool::{connect, redact_database_url, PoolArgs},
+};
 use migration::{Migrator, MigratorTrait};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Setup logging
-    env_logger::init();
     let args = Args::parse();
+    init_logging(&args.log)?;
     info!("Starting glados-audit");
 
     //
@@ -22,24 +26,63 @@ async fn main() -> Result<()> {
     debug!("Parsing CLI arguments");
 
     match args.subcommand {
-        Some(command) => run_command(command).await?,
+        Some(command) => run_command(command, &args.pool).await?,
         None => run_audit(args).await?,
     }
     Ok(())
 }
 
-async fn run_command(command: Command) -> Result<()> {
+async fn run_command(command: Command, pool: &PoolArgs) -> Result<()> {
+    // Checks and manifests only need a database when the results are kept.
+    if let Command::Check {
+        content_key,
+        portal_client,
+        persist: false,
+        pre_merge_accumulator,
+        ..
+    } = &command
+    {
+        return run_check(
+            content_key,
+            portal_client,
+            pre_merge_accumulator.as_deref(),
+            None,
+        )
+        .await;
+    }
+    if let Command::VerifyManifest {
+        path,
+        portal_client,
+        persist: false,
+        pre_merge_accumulator,
+        ..
+    } = &command
+    {
+        return run_verify_manifest(path, portal_client, pre_merge_accumulator.as_deref(), None)
+            .await;
+    }
+
     //
     // Database Connection
     //
     let database_url = match &command {
-        Command::Audit { database_url, .. } => database_url,
+        Command::Audit { database_url, .. }
+        | Command::Backfill { database_url, .. }
+        | Command::Report { database_url, .. } => database_url,
+        Command::Check { database_url, .. } | Command::VerifyManifest { database_url, .. } => {
+            database_url.as_ref().ok_or_else(|| {
+                anyhow!("--persist needs a database, pass --database-url or set DATABASE_URL.")
+            })?
+        }
     };
-    debug!(database_url = database_url, "Connecting to database");
+    let redacted_url = redact_database_url(database_url);
+    debug!(database_url = %redacted_url, "Connecting to database");
 
-    let conn = Database::connect(database_url).await?;
+    let conn = connect(database_url, pool)
+        .await
+        .with_context(|| format!("Could not connect to the database at {redacted_url}"))?;
     info!(
-        database_url = database_url,
+        database_url = %redacted_url,
         "database connection established"
     );
 
@@ -51,23 +94,24 @@ async fn run_audit(args: Args) -> Result<()> {
     //
     // Database Connection
     //
+    let pool = args.pool.clone();
     let config = AuditConfig::from_args(args).await?;
-    debug!(
-        database_url = &config.database_url,
-        "Connecting to database"
-    );
+    let redacted_url = redact_database_url(&config.database_url);
+    debug!(database_url = %redacted_url, "Connecting to database");
 
-    let conn = Database::connect(&config.database_url).await?;
+    let conn = connect(&config.database_url, &pool)
+        .await
+        .with_context(|| format!("Could not connect to the database at {redacted_url}"))?;
     info!(
-        database_url = &config.database_url,
+        database_url = %redacted_url,
         "database connection established"
     );
 
     Migrator::up(&conn, None).await?;
-    tokio::spawn(periodically_record_stats(
-        Duration::from_secs(config.stats_recording_period),
-        conn.clone(),
-    ));
+    if let Some(blocks) = config.block_range.clone() {
+        return run_block_range_audit(conn, config, blocks).await;
+    }
+    spawn_periodic_tasks(&conn, &config);
     run_glados_audit(conn, config).await;
     Ok(())
 }
@@ -0,0 +1,115 @@
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use entity::{
+    content,
+    content_audit::{AuditOutcome, SelectionStrategy},
+};
+use ethportal_api::utils::bytes::{hex_decode, hex_encode};
+use ethportal_api::{HistoryContentKey, OverlayContentKey};
+use glados_core::jsonrpc::{PortalClient, DEFAULT_REQUEST_TIMEOUT};
+use sea_orm::DatabaseConnection;
+use serde::Deserialize;
+
+use crate::{check::command_audit_config, record_audit, request_audit, AuditTask};
+
+/// Content keys that the network is expected to have, read from a JSON file such as
+/// `{"content_keys": ["0x00...", "0x01..."]}`.
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    content_keys: Vec<String>,
+}
+
+/// Parses a manifest, failing on the first entry that is not a history content key.
+fn parse_manifest(json: &str) -> Result<Vec<HistoryContentKey>> {
+    let manifest: Manifest =
+        serde_json::from_str(json).map_err(|e| anyhow!("Invalid manifest: {e}"))?;
+    manifest
+        .content_keys
+        .iter()
+        .enumerate()
+        .map(|(index, content_key)| {
+            let raw = hex_decode(content_key)
+                .map_err(|e| anyhow!("Entry {index} ({content_key}) is not hex: {e}"))?;
+            HistoryContentKey::try_from(raw)
+                .map_err(|e| anyhow!("Entry {index} ({content_key}) is not a content key: {e}"))
+        })
+        .collect()
+}
+
+/// Audits every content key of a manifest once and prints which are present.
+///
+/// Content counts as present when the node returns it and it passes the same checks
+/// as any other audit, including the header proof check with `pre_merge_accumulator`. Returns an error, for a non-zero exit code, if any is absent.
+/// When a database connection is given, the content keys are stored if needed and the
+/// audits are recorded like any other.
+pub async fn run_verify_manifest(
+    path: &Path,
+    portal_client: &str,
+    pre_merge_accumulator: Option<&Path>,
+    conn: Option<&DatabaseConnection>,
+) -> Result<()> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Could not read manifest {}: {e}", path.display()))?;
+    let content_keys = parse_manifest(&json)?;
+    let client = PortalClient::from(portal_client.to_string(), DEFAULT_REQUEST_TIMEOUT).await?;
+    let config = command_audit_config(pre_merge_accumulator)?;
+
+    let mut absent = 0;
+    for content_key in &content_keys {
+        let task = AuditTask {
+            strategy: SelectionStrategy::SpecificContentKey,
+            content_key: content_key.clone(),
+            selected_at: Utc::now(),
+        };
+        let response = request_audit(&task, &client, &config).await;
+        match &response.outcome {
+            AuditOutcome::Success => {
+                println!("present: {}", hex_encode(content_key.to_bytes()))
+            }
+            outcome => {
+                absent += 1;
+                println!(
+                    "absent:  {} ({})",
+                    hex_encode(content_key.to_bytes()),
+                    outcome.as_text()
+                );
+            }
+        }
+        if let Some(conn) = conn {
+            content::get_or_create(content_key, conn).await?;
+            record_audit(task, &client, response, None, conn, &config).await?;
+        }
+    }
+
+    println!(
+        "{} of {} content keys present",
+        content_keys.len() - absent,
+        content_keys.len()
+    );
+    if absent > 0 {
+        bail!("{absent} content keys from the manifest are absent");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let json = r#"{"content_keys": [
+            "0x00720704f3aa11c53cf344ea069db95cecb81ad7453c8f276b2a1062979611f09c",
+            "0x01720704f3aa11c53cf344ea069db95cecb81ad7453c8f276b2a1062979611f09c"
+        ]}"#;
+        let content_keys = parse_manifest(json).unwrap();
+        assert_eq!(content_keys.len(), 2);
+        assert!(matches!(content_keys[1], HistoryContentKey::BlockBody(_)));
+
+        assert!(parse_manifest(r#"{"content_keys": ["0xzz"]}"#).is_err());
+        assert!(parse_manifest(r#"{"content_keys": ["0x09"]}"#).is_err());
+        assert!(parse_manifest(r#"["0x00"]"#).is_err());
+    }
+}
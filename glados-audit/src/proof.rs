@@ -0,0 +1,98 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+use trin_validation::accumulator::MasterAccumulator;
+
+/// Number of block headers summarized by each pre-merge epoch accumulator.
+pub(crate) const EPOCH_SIZE: u64 = 8192;
+/// Levels of hashing above the header records of an epoch accumulator.
+const EPOCH_DEPTH: u32 = EPOCH_SIZE.trailing_zeros();
+/// Length of an SSZ encoded header record, a block hash and a total difficulty.
+const HEADER_RECORD_LENGTH: usize = 64;
+
+/// The roots of the pre-merge epoch accumulators, which header proofs are checked against.
+pub type PreMergeAccumulator = MasterAccumulator;
+
+/// Reads an SSZ encoded master accumulator, such as trin's `merge_macc.bin`.
+pub fn read_pre_merge_accumulator(path: &Path) -> Result<PreMergeAccumulator> {
+    MasterAccumulator::try_from_file(path.to_path_buf()).map_err(|e| {
+        anyhow!(
+            "Could not read pre-merge accumulator from {}: {e}",
+            path.display()
+        )
+    })
+}
+
+/// Computes the SSZ hash tree root of an encoded epoch accumulator, which is the hash
+/// in its content key.
+///
+/// Returns `None` if the bytes are not a list of at most [EPOCH_SIZE] header records.
+pub(crate) fn epoch_accumulator_root(bytes: &[u8]) -> Option<[u8; 32]> {
+    let records = bytes.len() / HEADER_RECORD_LENGTH;
+    if bytes.len() % HEADER_RECORD_LENGTH != 0 || records as u64 > EPOCH_SIZE {
+        return None;
+    }
+    // Each record is a container of two 32 byte fields, hashed together.
+    let mut layer: Vec<[u8; 32]> = bytes
+        .chunks_exact(HEADER_RECORD_LENGTH)
+        .map(|record| hash_pair(&record[..32], &record[32..]))
+        .collect();
+    // The list is padded to its limit with empty records.
+    let mut empty_subtree = [0; 32];
+    for _ in 0..EPOCH_DEPTH {
+        if layer.len() % 2 == 1 {
+            layer.push(empty_subtree);
+        }
+        layer = layer
+            .chunks_exact(2)
+            .map(|pair| hash_pair(&pair[0], &pair[1]))
+            .collect();
+        empty_subtree = hash_pair(&empty_subtree, &empty_subtree);
+    }
+    let records_root = layer.first().copied().unwrap_or(empty_subtree);
+    let mut length = [0; 32];
+    length[..8].copy_from_slice(&(records as u64).to_le_bytes());
+    Some(hash_pair(&records_root, &length))
+}
+
+/// Hashes two nodes of a Merkle tree into their parent.
+fn hash_pair(left: &[u8], right: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that an epoch accumulator is hashed as a list of header records padded to
+    /// the epoch size, and that other lengths are rejected.
+    #[test]
+    fn test_epoch_accumulator_root() {
+        let mut record = [3; HEADER_RECORD_LENGTH];
+        record[32..].copy_from_slice(&[4; 32]);
+        let mut subtree = hash_pair(&[3; 32], &[4; 32]);
+        let mut empty_subtree = [0; 32];
+        for _ in 0..EPOCH_DEPTH {
+            subtree = hash_pair(&subtree, &empty_subtree);
+            empty_subtree = hash_pair(&empty_subtree, &empty_subtree);
+        }
+        let mut length = [0; 32];
+        length[0] = 1;
+        assert_eq!(
+            epoch_accumulator_root(&record),
+            Some(hash_pair(&subtree, &length))
+        );
+        assert_eq!(
+            epoch_accumulator_root(&[]),
+            Some(hash_pair(&empty_subtree, &[0; 32]))
+        );
+
+        assert_eq!(epoch_accumulator_root(&record[..63]), None);
+        let too_many = vec![0; HEADER_RECORD_LENGTH * (EPOCH_SIZE as usize + 1)];
+        assert_eq!(epoch_accumulator_root(&too_many), None);
+    }
+}
@@ -0,0 +1,152 @@
+use std::str::FromStr;
+
+use entity::content;
+use ethereum_types::U256;
+use sea_orm::{ColumnTrait, Condition};
+
+/// Largest XOR distance from a node id, given on the command line in hex.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Radius(U256);
+
+impl FromStr for Radius {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix("0x").unwrap_or(s);
+        if digits.is_empty() || digits.len() > 64 {
+            return Err(format!("'{s}' is not a 256 bit hex number"));
+        }
+        U256::from_str_radix(digits, 16)
+            .map(Radius)
+            .map_err(|_| format!("'{s}' is not a 256 bit hex number"))
+    }
+}
+
+/// The content ids within a radius of a node id, the content that the node stores.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ContentRadius {
+    node_id: U256,
+    radius: U256,
+}
+
+impl ContentRadius {
+    pub fn new(node_id: [u8; 32], radius: Radius) -> Self {
+        ContentRadius {
+            node_id: U256::from_big_endian(&node_id),
+            radius: radius.0,
+        }
+    }
+
+    /// Returns true if the content id is within the radius of the node id.
+    pub fn contains(&self, content_id: &[u8]) -> bool {
+        if content_id.len() != 32 {
+            return false;
+        }
+        (self.node_id ^ U256::from_big_endian(content_id)) <= self.radius
+    }
+
+    /// The ranges of content ids within the radius, from the highest ids to the lowest.
+    ///
+    /// A distance is at most the radius when it is below `radius + 1`. For each bit set
+    /// in `radius + 1`, the distances that match it above that bit and have the bit
+    /// clear are below it, and their content ids form one range.
+    fn id_ranges(&self) -> Vec<([u8; 32], [u8; 32])> {
+        if self.radius == U256::MAX {
+            return vec![([0; 32], [0xff; 32])];
+        }
+        let bound = self.radius + 1;
+        (0..256)
+            .rev()
+            .filter(|&index| bound.bit(index))
+            .map(|index| {
+                let bit = U256::one() << index;
+                let low_bits = bit - 1;
+                let prefix = (self.node_id ^ bound ^ bit) & !low_bits;
+                let mut lowest = [0; 32];
+                let mut highest = [0; 32];
+                prefix.to_big_endian(&mut lowest);
+                (prefix | low_bits).to_big_endian(&mut highest);
+                (lowest, highest)
+            })
+            .collect()
+    }
+
+    /// Matches the content whose id is within the radius, as [`ContentRadius::contains`].
+    pub fn condition(&self) -> Condition {
+        self.id_ranges()
+            .into_iter()
+            .fold(Condition::any(), |condition, (lowest, highest)| {
+                condition.add(content::Column::ContentId.between(lowest.to_vec(), highest.to_vec()))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_radius() {
+        assert_eq!(Radius::from_str("0xff"), Ok(Radius(U256::from(255))));
+        assert_eq!(Radius::from_str("ff"), Ok(Radius(U256::from(255))));
+        assert_eq!(
+            Radius::from_str(&format!("0x{}", "f".repeat(64))),
+            Ok(Radius(U256::MAX))
+        );
+        assert!(Radius::from_str("0x").is_err());
+        assert!(Radius::from_str("0xzz").is_err());
+        assert!(Radius::from_str(&format!("0x1{}", "0".repeat(64))).is_err());
+    }
+
+    #[test]
+    fn test_content_radius() {
+        let mut node_id = [0x55; 32];
+        node_id[31] = 0x0f;
+        // A radius of 0x10 covers the ids whose last byte is 0x00 to 0x1f, apart
+        // from those more than 0x10 from 0x0f.
+        let radius = ContentRadius::new(node_id, Radius::from_str("0x10").unwrap());
+        let with_last_byte = |byte| {
+            let mut content_id = node_id;
+            content_id[31] = byte;
+            content_id
+        };
+        assert!(radius.contains(&with_last_byte(0x0f)));
+        assert!(radius.contains(&with_last_byte(0x1f)));
+        assert!(radius.contains(&with_last_byte(0x00)));
+        assert!(!radius.contains(&with_last_byte(0x10)));
+        assert!(!radius.contains(&with_last_byte(0x20)));
+        assert!(!radius.contains(&[0x55; 31]));
+
+        assert_eq!(
+            radius.id_ranges(),
+            vec![
+                (with_last_byte(0x00), with_last_byte(0x0f)),
+                (with_last_byte(0x1f), with_last_byte(0x1f)),
+            ]
+        );
+        // The ranges hold exactly the ids within the radius.
+        for byte in 0..=0xff {
+            let content_id = with_last_byte(byte);
+            let in_ranges = radius
+                .id_ranges()
+                .iter()
+                .any(|(lowest, highest)| (lowest..=highest).contains(&&content_id));
+            assert_eq!(
+                in_ranges,
+                radius.contains(&content_id),
+                "last byte {byte:#x}"
+            );
+        }
+
+        // A radius one less than a power of two is a single range.
+        let power_of_two = ContentRadius::new(node_id, Radius::from_str("0x1f").unwrap());
+        assert_eq!(
+            power_of_two.id_ranges(),
+            vec![(with_last_byte(0x00), with_last_byte(0x1f))]
+        );
+
+        let everything = ContentRadius::new(node_id, Radius(U256::MAX));
+        assert_eq!(everything.id_ranges(), vec![([0; 32], [0xff; 32])]);
+        assert!(everything.contains(&[0; 32]));
+    }
+}
@@ -0,0 +1,75 @@
+use tokio::{
+    sync::Mutex,
+    time::{sleep, Duration, Instant},
+};
+
+/// Token bucket limiting how often audits start, shared by all audit workers.
+///
+/// The bucket holds up to one second's worth of tokens, so short bursts are
+/// allowed while the average rate stays at or below the configured limit.
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Tokens added per second.
+    rate: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(per_second: u32) -> Self {
+        let rate = f64::from(per_second);
+        RateLimiter {
+            rate,
+            bucket: Mutex::new(Bucket {
+                tokens: rate,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Waits until a token is available and takes it.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().await;
+                let now = Instant::now();
+                let refill = now.duration_since(bucket.last_refill).as_secs_f64() * self.rate;
+                bucket.tokens = (bucket.tokens + refill).min(self.rate);
+                bucket.last_refill = now;
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate)
+            };
+            sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that a full bucket is spent immediately and then refills at the configured rate.
+    #[tokio::test]
+    async fn test_rate_limiter() {
+        tokio::time::pause();
+        let limiter = RateLimiter::new(20);
+        let start = Instant::now();
+        for _ in 0..20 {
+            limiter.acquire().await;
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        // The paused clock only moves while the limiter sleeps for the next token.
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(49));
+        assert!(start.elapsed() < Duration::from_millis(51));
+    }
+}
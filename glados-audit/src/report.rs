@@ -0,0 +1,156 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use clap::ValueEnum;
+use ethportal_api::utils::bytes::hex_encode;
+use glados_core::stats::{get_content_type_breakdown, get_failing_content, Period};
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+use tracing::info;
+
+/// File format of a content availability report.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, ValueEnum)]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+/// A summary of audit results over a time window, for sharing outside of glados.
+#[derive(Debug, Serialize)]
+pub struct AvailabilityReport {
+    pub window: String,
+    pub generated_at: String,
+    pub total_audits: i64,
+    pub total_passes: i64,
+    pub content_types: Vec<ContentTypeReport>,
+    pub failing_content: Vec<FailingContentReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContentTypeReport {
+    pub content_type: String,
+    pub audits: i64,
+    pub passes: i64,
+    pub pass_percent: f32,
+}
+
+/// Content that failed every audit in the window.
+#[derive(Debug, Serialize)]
+pub struct FailingContentReport {
+    pub content_key: String,
+    pub audits: i64,
+}
+
+impl AvailabilityReport {
+    /// Builds a report from the same aggregations that back the web dashboard.
+    pub async fn build(
+        window: Period,
+        min_failures: u64,
+        conn: &DatabaseConnection,
+    ) -> Result<Self> {
+        let breakdown = get_content_type_breakdown(window, None, conn).await?;
+        let failing = get_failing_content(window, min_failures, conn).await?;
+        let content_types: Vec<ContentTypeReport> = breakdown
+            .types
+            .iter()
+            .map(|stats| ContentTypeReport {
+                content_type: stats.content_type.clone(),
+                audits: stats.total,
+                passes: stats.passed,
+                pass_percent: stats.pass_percent(),
+            })
+            .collect();
+        Ok(AvailabilityReport {
+            window: window.to_string(),
+            generated_at: Utc::now().to_rfc3339(),
+            total_audits: content_types.iter().map(|t| t.audits).sum(),
+            total_passes: content_types.iter().map(|t| t.passes).sum(),
+            content_types,
+            failing_content: failing
+                .into_iter()
+                .map(|content| FailingContentReport {
+                    content_key: hex_encode(content.content_key),
+                    audits: content.audits,
+                })
+                .collect(),
+        })
+    }
+
+    /// Renders the report as CSV, one row per content type and per failing content key.
+    pub fn to_csv(&self) -> String {
+        let mut csv = "kind,name,audits,passes\n".to_string();
+        for stats in &self.content_types {
+            let _ = writeln!(
+                csv,
+                "content_type,{},{},{}",
+                stats.content_type, stats.audits, stats.passes
+            );
+        }
+        for content in &self.failing_content {
+            let _ = writeln!(
+                csv,
+                "failing_content,{},{},0",
+                content.content_key, content.audits
+            );
+        }
+        csv
+    }
+}
+
+/// Writes a content availability report for the given window to `output`.
+pub async fn write_report(
+    output: &Path,
+    format: ReportFormat,
+    window: Period,
+    min_failures: u64,
+    conn: &DatabaseConnection,
+) -> Result<()> {
+    let report = AvailabilityReport::build(window, min_failures, conn).await?;
+    let contents = match format {
+        ReportFormat::Json => serde_json::to_string_pretty(&report)?,
+        ReportFormat::Csv => report.to_csv(),
+    };
+    std::fs::write(output, contents)
+        .map_err(|e| anyhow!("Could not write report to {}: {e}", output.display()))?;
+    info!(
+        output = %output.display(),
+        audits = report.total_audits,
+        failing = report.failing_content.len(),
+        "Wrote content availability report"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_to_csv() {
+        let report = AvailabilityReport {
+            window: Period::Day.to_string(),
+            generated_at: "2024-01-01T00:00:00+00:00".to_string(),
+            total_audits: 12,
+            total_passes: 9,
+            content_types: vec![ContentTypeReport {
+                content_type: "Block headers".to_string(),
+                audits: 12,
+                passes: 9,
+                pass_percent: 75.0,
+            }],
+            failing_content: vec![FailingContentReport {
+                content_key: "0x00aa".to_string(),
+                audits: 3,
+            }],
+        };
+        assert_eq!(
+            report.to_csv(),
+            "kind,name,audits,passes\n\
+             content_type,Block headers,12,9\n\
+             failing_content,0x00aa,3,0\n"
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use chrono::Utc;
+use entity::content_audit;
+use sea_orm::DatabaseConnection;
+use tokio::time::{interval, Duration};
+use tracing::{debug, error, info};
+
+/// Time between deletions of expired audits.
+const PRUNE_PERIOD: Duration = Duration::from_secs(3600);
+
+/// Loops indefinitely, periodically deleting audits older than the retention period.
+///
+/// The latest audit of each content key is kept so that content history is not emptied.
+pub async fn periodically_prune_audits(retention_days: u32, conn: DatabaseConnection) -> ! {
+    debug!(retention_days, "initializing task for pruning old audits");
+    let mut interval = interval(PRUNE_PERIOD);
+
+    loop {
+        interval.tick().await;
+        let cutoff = Utc::now() - chrono::Duration::days(retention_days.into());
+        match content_audit::delete_older_than(cutoff, &conn).await {
+            Ok(deleted) => info!(
+                audits.deleted = deleted,
+                retention_days, "pruned old audits"
+            ),
+            Err(e) => error!("failed to prune old audits: {e}"),
+        }
+    }
+}
@@ -1,13 +1,25 @@
 use std::collections::HashSet;
+use std::sync::{
+    atomic::{AtomicI32, Ordering},
+    Mutex,
+};
 
-use chrono::{DateTime, TimeZone, Utc};
-use ethportal_api::HistoryContentKey;
-use glados_core::db::store_block_keys;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use ethportal_api::{BlockHeaderKey, HistoryContentKey};
+use glados_core::{
+    db::{store_block_keys, store_content_key},
+    dialect::Dialect,
+    metrics::AuditMetrics,
+};
 use rand::{thread_rng, Rng};
 use sea_orm::{
-    ColumnTrait, DatabaseConnection, EntityTrait, JoinType, PaginatorTrait, QueryFilter,
-    QueryOrder, QuerySelect, RelationTrait,
+    sea_query::{Expr, Query, SelectStatement},
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, JoinType, Order, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect, RelationTrait,
 };
+use serde_json::json;
 use tokio::{
     sync::mpsc,
     time::{interval, Duration},
@@ -16,68 +28,208 @@ use tracing::{debug, error, warn};
 
 use entity::{
     content::{self, Model},
-    content_audit::{self, SelectionStrategy},
+    content_audit::{self, AuditResult, SelectionStrategy},
     execution_metadata,
 };
-use web3::types::{BlockId, BlockNumber};
+use web3::{
+    types::{Block, BlockId, BlockNumber, H256},
+    Transport,
+};
 
-use crate::{AuditConfig, AuditTask};
+use crate::{
+    cli::ContentType, proof::EPOCH_SIZE, radius::ContentRadius, shard::Shard, AuditConfig,
+    AuditTask,
+};
 
 pub const MERGE_BLOCK_HEIGHT: i32 = 15537393;
 
-pub async fn start_audit_selection_task(
-    strategy: SelectionStrategy,
-    tx: mpsc::Sender<AuditTask>,
-    conn: DatabaseConnection,
-    config: AuditConfig,
-) {
-    match strategy {
-        SelectionStrategy::Latest => select_latest_content_for_audit(tx, conn).await,
-        SelectionStrategy::Random => select_random_content_for_audit(tx, conn).await,
+/// Chooses the content to audit for one selection strategy.
+///
+/// Each period, [start_audit_selection_task] asks the selector for as many records as
+/// fit in the strategy's channel and queues them. Anything a selector remembers between
+/// periods, such as how far through the failed audits it has got, is kept by the selector.
+#[async_trait]
+pub trait ContentSelector: Send + Sync {
+    /// The strategy recorded with the audits of the selected content.
+    fn strategy(&self) -> SelectionStrategy;
+
+    /// Time between selections.
+    fn period(&self, config: &AuditConfig) -> Duration {
+        Duration::from_secs(config.audit_period_seconds)
+    }
+
+    /// Selects up to `limit` content records, in the order they should be audited.
+    async fn select(&self, conn: &DatabaseConnection, limit: u64) -> Result<Vec<Model>>;
+}
+
+/// Creates the selector for a strategy given on the command line.
+///
+/// Returns `None` for strategies that are not run by a selection task.
+pub fn content_selector(
+    strategy: &SelectionStrategy,
+    config: &AuditConfig,
+) -> Option<Box<dyn ContentSelector>> {
+    Some(match strategy {
+        SelectionStrategy::Latest => Box::new(LatestSelector::new(config)),
+        SelectionStrategy::Random => Box::new(RandomSelector::new(config)),
         SelectionStrategy::FourFours => {
             // Fourfours strategy downloads its own keys rather than waiting on glados-monitor to put them in the DB.
             let w3 = web3::Web3::new(web3::transports::Http::new(&config.provider_url).unwrap());
-            select_fourfours_content_for_audit(tx, conn, w3).await
+            Box::new(FourFoursSelector {
+                w3,
+                content_type: config.content_type.clone(),
+            })
         }
-        SelectionStrategy::Failed => warn!("Need to implement SelectionStrategy::Failed"),
-        SelectionStrategy::SelectOldestUnaudited => {
-            select_oldest_unaudited_content_for_audit(tx, conn).await
+        SelectionStrategy::Failed => Box::new(FailedSelector::new(config)),
+        SelectionStrategy::FollowHead => {
+            let w3 = web3::Web3::new(web3::transports::Http::new(&config.provider_url).unwrap());
+            Box::new(FollowHeadSelector::new(w3, config.content_type.clone()))
         }
+        SelectionStrategy::SelectOldestUnaudited => Box::new(OldestUnauditedSelector::new(config)),
+        SelectionStrategy::Unconfirmed => Box::new(UnconfirmedSelector::new(config)),
+        SelectionStrategy::EpochBoundary => Box::new(EpochBoundarySelector::new(config)),
         SelectionStrategy::SpecificContentKey => {
-            error!("SpecificContentKey is not a valid audit strategy")
+            error!("SpecificContentKey is not a valid audit strategy");
+            return None;
         }
-    }
+        SelectionStrategy::BlockRange => {
+            error!("BlockRange is not a valid audit strategy, use --start-block and --end-block");
+            return None;
+        }
+    })
 }
 
-/// Finds and sends audit tasks for [Strategy::Latest].
-///
-/// Strategy achieved by:
-/// 1. Left joining contentkey table to the contentaudit table to find audits per key.
-/// 2. Filter for null audits (Exclude any item with an existing audit).
-/// 3. Sort ascending to have most recently added content keys first.
-/// 4. Filter for content that is older than n seconds to allow the network a chance to propogate the content.
+/// Returns true for the strategies that select content already in the database,
+/// which can be limited to a content type when selecting.
+pub fn selects_from_database(strategy: &SelectionStrategy) -> bool {
+    matches!(
+        strategy,
+        SelectionStrategy::Latest
+            | SelectionStrategy::Random
+            | SelectionStrategy::Failed
+            | SelectionStrategy::SelectOldestUnaudited
+            | SelectionStrategy::Unconfirmed
+            | SelectionStrategy::EpochBoundary
+    )
+}
+
+/// Loops indefinitely, sending the content chosen by a selector to its channel.
 ///
 /// At regular intervals the channel capacity is assessed and new tasks are added to reach capacity.
-async fn select_latest_content_for_audit(
+/// A cycle also runs whenever one is requested through the config's trigger, which is told
+/// how many content keys it enqueued.
+pub async fn start_audit_selection_task(
+    selector: Box<dyn ContentSelector>,
     tx: mpsc::Sender<AuditTask>,
     conn: DatabaseConnection,
+    config: AuditConfig,
 ) -> ! {
-    debug!("initializing audit process for 'latest' strategy");
-    let mut interval = interval(Duration::from_secs(10));
+    let strategy = selector.strategy();
+    debug!(
+        strategy = strategy.as_text(),
+        "initializing audit selection task"
+    );
+    let mut interval = interval(selector.period(&config));
+    let mut triggers = config.trigger.subscribe();
 
     loop {
-        interval.tick().await;
+        let reply = tokio::select! {
+            _ = interval.tick() => None,
+            Some(reply) = triggers.recv() => Some(reply),
+        };
         if tx.is_closed() {
             error!("Channel is closed.");
             panic!();
         }
-        let keys_required = tx.capacity();
-        if keys_required == 0 {
-            continue;
-        };
-        let content_key_db_entries = match content::Entity::find()
+        let enqueued = run_selection_cycle(selector.as_ref(), &tx, &conn, &config).await;
+        if let Some(reply) = reply {
+            // The trigger stops listening if it gave up waiting.
+            let _ = reply.send(enqueued);
+        }
+    }
+}
+
+/// Selects content with a selector and sends it to the channel, returning the number of
+/// content keys sent.
+async fn run_selection_cycle(
+    selector: &dyn ContentSelector,
+    tx: &mpsc::Sender<AuditTask>,
+    conn: &DatabaseConnection,
+    config: &AuditConfig,
+) -> usize {
+    let strategy = selector.strategy();
+    let keys_required = keys_to_select(tx, strategy.clone(), config);
+    if keys_required == 0 {
+        return 0;
+    };
+    let content_key_db_entries = match selector.select(conn, keys_required as u64).await {
+        Ok(content_key_db_entries) => content_key_db_entries,
+        Err(err) => {
+            error!(strategy = strategy.as_text(), err=?err, "Could not select content for audit");
+            return 0;
+        }
+    };
+    let content_key_db_entries = retain_in_shard(&config.shard, content_key_db_entries);
+    let item_count = content_key_db_entries.len();
+    if item_count == 0 {
+        debug!(
+            strategy = strategy.as_text(),
+            "No content to audit, skipping cycle."
+        );
+        return 0;
+    }
+    debug!(
+        strategy = strategy.as_text(),
+        item_count, "Adding content keys to the audit queue."
+    );
+    // This task is the only sender on its channel and selected no more keys than the
+    // channel had room for, so the sends do not wait on the workers and a triggered
+    // cycle can always reply.
+    add_to_queue(
+        tx.clone(),
+        strategy,
+        content_key_db_entries,
+        &config.metrics,
+    )
+    .await
+}
+
+/// Selects content for [SelectionStrategy::Latest].
+///
+/// Strategy achieved by:
+/// 1. Left joining contentkey table to the contentaudit table to find audits per key.
+/// 2. Filter for null audits (Exclude any item with an existing audit).
+/// 3. Sort ascending to have most recently added content keys first.
+/// 4. Filter for content that is older than n seconds to allow the network a chance to propogate the content.
+pub struct LatestSelector {
+    radius: Option<ContentRadius>,
+    content_type: ContentType,
+    shard: Option<Shard>,
+}
+
+impl LatestSelector {
+    pub fn new(config: &AuditConfig) -> Self {
+        LatestSelector {
+            radius: config.radius.clone(),
+            content_type: config.content_type.clone(),
+            shard: config.shard,
+        }
+    }
+}
+
+#[async_trait]
+impl ContentSelector for LatestSelector {
+    fn strategy(&self) -> SelectionStrategy {
+        SelectionStrategy::Latest
+    }
+
+    async fn select(&self, conn: &DatabaseConnection, limit: u64) -> Result<Vec<Model>> {
+        let content = content::Entity::find()
             .left_join(entity::content_audit::Entity)
             .filter(content_audit::Column::CreatedAt.is_null())
+            .filter(within_radius(&self.radius))
+            .filter(is_content_type(&self.content_type))
+            .filter(in_shard(&self.shard, Dialect::of(conn)))
             .filter(
                 content::Column::FirstAvailableAt.lt(Utc::now() - chrono::Duration::seconds(10)),
             )
@@ -87,77 +239,258 @@ async fn select_latest_content_for_audit(
             )
             .filter(execution_metadata::Column::BlockNumber.gt(MERGE_BLOCK_HEIGHT))
             .order_by_desc(content::Column::FirstAvailableAt)
-            .limit(keys_required as u64)
-            .all(&conn)
-            .await
-        {
-            Ok(content_key_db_entries) => content_key_db_entries,
-            Err(err) => {
-                error!(audit.strategy="latest", err=?err, "Could not make audit query");
-                continue;
-            }
-        };
-        let item_count = content_key_db_entries.len();
-        debug!(
-            strategy = "latest",
-            item_count, "Adding content keys to the audit queue."
-        );
-        add_to_queue(
-            tx.clone(),
-            SelectionStrategy::Latest,
-            content_key_db_entries,
-        )
-        .await;
+            .limit(limit)
+            .all(conn)
+            .await?;
+        Ok(content)
     }
 }
 
-/// Finds and sends audit tasks for [SelectionStrategy::FourFours].
+/// Matches content within the radius, or any content if no radius is set.
+fn within_radius(radius: &Option<ContentRadius>) -> Condition {
+    match radius {
+        Some(radius) => radius.condition(),
+        None => Condition::all(),
+    }
+}
+
+/// Matches content of the type, or any content for [ContentType::All].
+///
+/// Compares against the range of keys starting with the type's selector byte, which
+/// reads the same on every database backend.
+fn is_content_type(content_type: &ContentType) -> Condition {
+    let selector: u8 = match content_type {
+        ContentType::All => return Condition::all(),
+        ContentType::Header => 0x00,
+        ContentType::Body => 0x01,
+        ContentType::Receipts => 0x02,
+    };
+    Condition::all()
+        .add(content::Column::ContentKey.gte(vec![selector]))
+        .add(content::Column::ContentKey.lt(vec![selector + 1]))
+}
+
+/// Matches the content in the shard, or any content if no shard is set.
+fn in_shard(shard: &Option<Shard>, dialect: Dialect) -> Condition {
+    match shard {
+        Some(shard) => shard.condition(dialect),
+        None => Condition::all(),
+    }
+}
+
+/// Drops the content of other types, for the strategies that do not select content from
+/// the database and so cannot filter it with [is_content_type].
+pub(crate) fn retain_content_type(
+    content_type: &ContentType,
+    mut content: Vec<content::Model>,
+) -> Vec<content::Model> {
+    content.retain(|content| {
+        HistoryContentKey::try_from(content.content_key.clone())
+            .is_ok_and(|content_key| content_type.matches(&content_key))
+    });
+    content
+}
+
+/// Drops the content of other shards, which is only selected by the strategies that
+/// do not select from the database, and by the block range audit.
+pub(crate) fn retain_in_shard(shard: &Option<Shard>, mut content: Vec<Model>) -> Vec<Model> {
+    if let Some(shard) = shard {
+        content.retain(|content| shard.contains(&content.content_id));
+    }
+    content
+}
+
+/// Selects content for [SelectionStrategy::FourFours].
 ///
 /// 1. Get a random block number between 1 and MERGE_BLOCK_HEIGHT.
 /// 2. Get the block hash for that block.
-/// 3. Send content keys for header, body, receipts.
-///
-async fn select_fourfours_content_for_audit(
-    tx: mpsc::Sender<AuditTask>,
-    conn: DatabaseConnection,
+/// 3. Store and select the content keys for header, body, receipts.
+/// 4. Repeat with more blocks until the batch is full.
+pub struct FourFoursSelector {
     w3: web3::Web3<web3::transports::Http>,
-) -> ! {
-    let mut interval = interval(Duration::from_secs(5));
+    content_type: ContentType,
+}
 
-    loop {
-        interval.tick().await;
-        let block_number = thread_rng().gen_range(1..MERGE_BLOCK_HEIGHT);
-        debug!(
-            strategy = "4444s",
-            "Getting hash for block number {block_number}."
+#[async_trait]
+impl ContentSelector for FourFoursSelector {
+    fn strategy(&self) -> SelectionStrategy {
+        SelectionStrategy::FourFours
+    }
+
+    fn period(&self, _config: &AuditConfig) -> Duration {
+        Duration::from_secs(5)
+    }
+
+    async fn select(&self, conn: &DatabaseConnection, limit: u64) -> Result<Vec<Model>> {
+        let limit = limit as usize;
+        let mut items_to_audit = vec![];
+        // Each block gives at least one key of the selected type, so `limit` blocks
+        // fill the batch unless storing their keys fails.
+        for _ in 0..limit {
+            if items_to_audit.len() >= limit {
+                break;
+            }
+            let block_number = thread_rng().gen_range(1..MERGE_BLOCK_HEIGHT);
+            debug!(
+                strategy = "4444s",
+                "Getting hash for block number {block_number}."
+            );
+            let block_hash = self
+                .w3
+                .eth()
+                .block(BlockId::Number(BlockNumber::Number(block_number.into())))
+                .await?
+                .and_then(|block| block.hash)
+                .ok_or_else(|| anyhow!("No hash for block {block_number}"))?;
+
+            items_to_audit.extend(retain_content_type(
+                &self.content_type,
+                store_block_keys(block_number, block_hash.as_fixed_bytes(), conn).await,
+            ));
+        }
+        items_to_audit.truncate(limit);
+        Ok(items_to_audit)
+    }
+}
+
+/// How many content keys to select this period: as many as fit in the channel, up to the batch size.
+///
+/// If more than half of the channel is still waiting for a worker, the workers are behind
+/// and no keys are selected. Keys selected then would often already be queued, such as
+/// the newest unaudited keys that the latest strategy would pick again.
+fn keys_to_select(
+    tx: &mpsc::Sender<AuditTask>,
+    strategy: SelectionStrategy,
+    config: &AuditConfig,
+) -> usize {
+    let capacity = tx.capacity();
+    let max_capacity = tx.max_capacity();
+    if capacity * 2 < max_capacity {
+        warn!(
+            strategy = strategy.as_text(),
+            channel.availability = capacity,
+            channel.size = max_capacity,
+            "Audit workers are behind, skipping selection this period."
         );
-        let block_hash = w3
-            .eth()
-            .block(BlockId::Number(BlockNumber::Number(block_number.into())))
-            .await
-            .unwrap()
-            .unwrap()
-            .hash
-            .unwrap();
+        config
+            .metrics
+            .backpressure_skips
+            .fetch_add(1, Ordering::Relaxed);
+        return 0;
+    }
+    capacity.min(config.batch_size as usize)
+}
+
+/// Selects content for [SelectionStrategy::FollowHead].
+///
+/// 1. Get the latest finalized block from the execution provider.
+/// 2. Store and select the content key for that block's header, once per block.
+///
+/// A finalized block cannot be reorganized away, so its header stays in the Portal
+/// network, and it is old enough to have been gossiped by the time it is audited.
+pub struct FollowHeadSelector {
+    w3: web3::Web3<web3::transports::Http>,
+    content_type: ContentType,
+    last_audited_block: Mutex<Option<u64>>,
+}
 
-        let items_to_audit =
-            store_block_keys(block_number, block_hash.as_fixed_bytes(), &conn).await;
+impl FollowHeadSelector {
+    pub fn new(w3: web3::Web3<web3::transports::Http>, content_type: ContentType) -> Self {
+        FollowHeadSelector {
+            w3,
+            content_type,
+            last_audited_block: Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl ContentSelector for FollowHeadSelector {
+    fn strategy(&self) -> SelectionStrategy {
+        SelectionStrategy::FollowHead
+    }
+
+    async fn select(&self, conn: &DatabaseConnection, _limit: u64) -> Result<Vec<Model>> {
+        // Only headers are followed, so there is nothing to select for other types.
+        if !matches!(self.content_type, ContentType::All | ContentType::Header) {
+            return Ok(vec![]);
+        }
+        // The `finalized` block tag is not one of web3's block numbers, so the block is
+        // requested directly.
+        let finalized = self
+            .w3
+            .transport()
+            .execute(
+                "eth_getBlockByNumber",
+                vec![json!("finalized"), json!(false)],
+            )
+            .await?;
+        let Some(block) = serde_json::from_value::<Option<Block<H256>>>(finalized)? else {
+            return Ok(vec![]);
+        };
+        let (Some(block_number), Some(block_hash)) = (block.number, block.hash) else {
+            return Ok(vec![]);
+        };
+        let block_number = block_number.as_u64();
+        let last_audited_block = *self.last_audited_block.lock().unwrap();
+        if last_audited_block == Some(block_number) {
+            return Ok(vec![]);
+        }
+        let header = HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+            block_hash: block_hash.to_fixed_bytes(),
+        });
+        let Some(header) =
+            store_content_key(&header, "block_header", block_number as i32, conn).await
+        else {
+            return Ok(vec![]);
+        };
+        *self.last_audited_block.lock().unwrap() = Some(block_number);
         debug!(
-            strategy = "4444s",
-            item_count = items_to_audit.len(),
-            "Adding content keys to the audit queue."
+            strategy = "follow head",
+            block.number = block_number,
+            "Selected finalized block header."
         );
-        add_to_queue(tx.clone(), SelectionStrategy::FourFours, items_to_audit).await;
+        Ok(vec![header])
     }
 }
 
+/// Query for the ids of content that has been audited within the staleness threshold.
+fn recently_audited_content(staleness_threshold_seconds: u64) -> SelectStatement {
+    let threshold = chrono::Duration::seconds(staleness_threshold_seconds as i64);
+    Query::select()
+        .column(content_audit::Column::ContentKey)
+        .from(content_audit::Entity)
+        .and_where(content_audit::Column::CreatedAt.gt(Utc::now() - threshold))
+        .to_owned()
+}
+
+/// Query for the ids of content whose latest audit passed within the window.
+fn recently_passed_content(window_seconds: u64) -> SelectStatement {
+    let window = chrono::Duration::seconds(window_seconds as i64);
+    let latest_audits = Query::select()
+        .expr(Expr::col(content_audit::Column::Id).max())
+        .from(content_audit::Entity)
+        .group_by_col(content_audit::Column::ContentKey)
+        .to_owned();
+    Query::select()
+        .column(content_audit::Column::ContentKey)
+        .from(content_audit::Entity)
+        .and_where(content_audit::Column::Id.in_subquery(latest_audits))
+        .and_where(content_audit::Column::Result.eq(AuditResult::Success))
+        .and_where(content_audit::Column::CreatedAt.gt(Utc::now() - window))
+        .to_owned()
+}
+
 /// Adds Glados database History sub-protocol search results
 /// to a channel for auditing against a Portal Node.
-async fn add_to_queue(
+///
+/// Content keys that cannot be decoded are skipped and counted in the metrics. Returns the number of content keys sent.
+pub(crate) async fn add_to_queue(
     tx: mpsc::Sender<AuditTask>,
     strategy: SelectionStrategy,
     items: Vec<content::Model>,
-) {
+    metrics: &AuditMetrics,
+) -> usize {
     let capacity = tx.capacity();
     let max_capacity = tx.max_capacity();
     debug!(
@@ -165,69 +498,103 @@ async fn add_to_queue(
         channel.size = max_capacity,
         "Adding items to audit task channel."
     );
+    let mut enqueued = 0;
     for content_key_model in items {
         // Create key from database bytes.
         let content_key = match HistoryContentKey::try_from(content_key_model.content_key) {
             Ok(key) => key,
             Err(err) => {
-                error!(database.id=?content_key_model.id, err=?err, "Could not decode content key from database record");
+                error!(database.id=?content_key_model.id, err=?err, "Could not decode content key from database record, skipping it");
+                metrics
+                    .malformed_content_keys
+                    .fetch_add(1, Ordering::Relaxed);
                 continue;
             }
         };
         let task = AuditTask {
             strategy: strategy.clone(),
             content_key,
+            selected_at: Utc::now(),
         };
-        if let Err(e) = tx.send(task).await {
-            debug!(audit.strategy=?strategy, err=?e, "Could not send key for audit, channel might be full or closed.")
+        match tx.send(task).await {
+            Ok(()) => enqueued += 1,
+            Err(e) => {
+                debug!(audit.strategy=?strategy, err=?e, "Could not send key for audit, channel might be full or closed.")
+            }
         }
     }
+    enqueued
 }
 
-/// Finds and sends audit tasks for [Strategy::Random].
+/// Selects content for [SelectionStrategy::Random].
 ///
 /// Strategy achieved by:
-/// 1. Checking number of keys in DB.
-/// 2. Generating random ids.
-/// 3. Looking up each one separately, then sending them all in the channel.
+/// 1. Counting the keys in DB that were not audited within the staleness threshold.
+/// 2. Generating random offsets into those keys.
+/// 3. Looking up each one separately.
 ///
-/// At regular intervals the channel capacity is assessed and new tasks are added to reach capacity.
-async fn select_random_content_for_audit(
-    tx: mpsc::Sender<AuditTask>,
-    conn: DatabaseConnection,
-) -> ! {
-    debug!("initializing audit process for 'random' strategy");
+/// Sampling by offset rather than by id keeps the selection uniform even if ids are not contiguous.
+pub struct RandomSelector {
+    staleness_threshold_seconds: u64,
+    radius: Option<ContentRadius>,
+    content_type: ContentType,
+    shard: Option<Shard>,
+}
 
-    let mut interval = interval(Duration::from_secs(10));
-    loop {
-        interval.tick().await;
+impl RandomSelector {
+    pub fn new(config: &AuditConfig) -> Self {
+        RandomSelector {
+            staleness_threshold_seconds: config.staleness_threshold_seconds,
+            radius: config.radius.clone(),
+            content_type: config.content_type.clone(),
+            shard: config.shard,
+        }
+    }
+}
 
-        let num_keys = match content::Entity::find().count(&conn).await {
-            // Skip if no keys yet.
-            Ok(0) => continue,
-            Ok(count) => count as u32,
-            Err(err) => {
-                error!(audit.strategy="random", err=?err, "Could not make audit query");
-                continue;
-            }
-        };
-        let keys_required = tx.capacity();
-        if keys_required == 0 {
-            continue;
-        };
-        let mut random_ids: HashSet<u32> = HashSet::new();
+#[async_trait]
+impl ContentSelector for RandomSelector {
+    fn strategy(&self) -> SelectionStrategy {
+        SelectionStrategy::Random
+    }
+
+    async fn select(&self, conn: &DatabaseConnection, limit: u64) -> Result<Vec<Model>> {
+        let num_keys = content::Entity::find()
+            .filter(
+                content::Column::Id
+                    .not_in_subquery(recently_audited_content(self.staleness_threshold_seconds)),
+            )
+            .filter(within_radius(&self.radius))
+            .filter(is_content_type(&self.content_type))
+            .filter(in_shard(&self.shard, Dialect::of(conn)))
+            .count(conn)
+            .await?;
+        // Skip if no keys yet.
+        if num_keys == 0 {
+            return Ok(vec![]);
+        }
+        let mut random_offsets: HashSet<u64> = HashSet::new();
         {
             // Thread safe block for the rng, which is not `Send`.
             let mut rng = thread_rng();
-            for _ in 0..keys_required {
-                random_ids.insert(rng.gen_range(0..num_keys));
+            for _ in 0..limit {
+                random_offsets.insert(rng.gen_range(0..num_keys));
             }
         }
         let mut content_key_db_entries: Vec<Model> = vec![];
-        for random_id in random_ids {
+        for random_offset in random_offsets {
             match content::Entity::find()
-                .filter(content::Column::Id.eq(random_id))
-                .all(&conn)
+                .filter(
+                    content::Column::Id.not_in_subquery(recently_audited_content(
+                        self.staleness_threshold_seconds,
+                    )),
+                )
+                .filter(within_radius(&self.radius))
+                .filter(is_content_type(&self.content_type))
+                .filter(in_shard(&self.shard, Dialect::of(conn)))
+                .order_by_asc(content::Column::Id)
+                .offset(random_offset)
+                .one(conn)
                 .await
             {
                 Ok(found) => content_key_db_entries.extend(found),
@@ -237,93 +604,270 @@ async fn select_random_content_for_audit(
                 }
             };
         }
-        let item_count = content_key_db_entries.len();
-        debug!(
-            strategy = "random",
-            item_count, "Adding content keys to the audit queue."
-        );
-        add_to_queue(
-            tx.clone(),
-            SelectionStrategy::Random,
-            content_key_db_entries,
-        )
-        .await;
+        Ok(content_key_db_entries)
     }
 }
 
-/// Finds and sends audit tasks for [SelectionStrategy::SelectOldestUnaudited].
+/// Selects content for [SelectionStrategy::SelectOldestUnaudited].
 ///
 /// Strategy achieved by:
 /// 1. Find oldest content
 /// 2. Filter for content with no audits
 /// 3. As audits are sent, gradually select more recent content
-///
-/// At regular intervals the channel capacity is assessed and new tasks are added to reach capacity.
-async fn select_oldest_unaudited_content_for_audit(
-    tx: mpsc::Sender<AuditTask>,
-    conn: DatabaseConnection,
-) {
-    debug!("initializing audit process for 'select oldest unaudited' strategy");
-    let mut interval = interval(Duration::from_secs(10));
+pub struct OldestUnauditedSelector {
+    /// Memory of which audits have been sent using their timestamp.
+    timestamp_too_old_threshold: Mutex<DateTime<Utc>>,
+    radius: Option<ContentRadius>,
+    content_type: ContentType,
+    shard: Option<Shard>,
+}
 
-    // Memory of which audits have been sent using their timestamp.
-    let mut timestamp_too_old_threshold: DateTime<Utc> = match Utc.timestamp_millis_opt(0i64) {
-        chrono::LocalResult::Single(time) => time,
-        _ => {
-            error!("Could not convert starting for timestamp");
-            return;
+impl OldestUnauditedSelector {
+    pub fn new(config: &AuditConfig) -> Self {
+        OldestUnauditedSelector {
+            timestamp_too_old_threshold: Mutex::new(DateTime::<Utc>::from(std::time::UNIX_EPOCH)),
+            radius: config.radius.clone(),
+            content_type: config.content_type.clone(),
+            shard: config.shard,
         }
-    };
+    }
+}
 
-    loop {
-        interval.tick().await;
-        if tx.is_closed() {
-            error!("Channel is closed.");
-            panic!();
-        }
-        let keys_required = tx.capacity();
-        if keys_required == 0 {
-            continue;
-        };
+#[async_trait]
+impl ContentSelector for OldestUnauditedSelector {
+    fn strategy(&self) -> SelectionStrategy {
+        SelectionStrategy::SelectOldestUnaudited
+    }
+
+    async fn select(&self, conn: &DatabaseConnection, limit: u64) -> Result<Vec<Model>> {
+        let timestamp_too_old_threshold = *self.timestamp_too_old_threshold.lock().unwrap();
         let search_result: Vec<(content::Model, Vec<content_audit::Model>)> =
-            match content::Entity::find()
+            content::Entity::find()
                 .filter(content::Column::FirstAvailableAt.gt(timestamp_too_old_threshold))
                 .filter(
                     content::Column::FirstAvailableAt.lt(Utc::now() - chrono::Duration::days(1)),
                 )
+                .filter(within_radius(&self.radius))
+                .filter(is_content_type(&self.content_type))
+                .filter(in_shard(&self.shard, Dialect::of(conn)))
                 .order_by_asc(content::Column::FirstAvailableAt)
                 .find_with_related(entity::content_audit::Entity)
                 .filter(content_audit::Column::CreatedAt.is_null())
-                .limit(keys_required as u64)
-                .all(&conn)
-                .await
-            {
-                Ok(content_key_db_entries) => content_key_db_entries,
-                Err(err) => {
-                    error!(audit.strategy="latest", err=?err, "Could not make audit query");
-                    continue;
-                }
-            };
-        let content_key_db_entries: Vec<content::Model> = search_result
+                .limit(limit)
+                .all(conn)
+                .await?;
+        let mut threshold = self.timestamp_too_old_threshold.lock().unwrap();
+        let content = search_result
             .into_iter()
             .map(|(content, _audits)| {
-                if content.first_available_at > timestamp_too_old_threshold {
-                    timestamp_too_old_threshold = content.first_available_at
+                if content.first_available_at > *threshold {
+                    *threshold = content.first_available_at
                 }
                 content
             })
             .collect();
-        let item_count = content_key_db_entries.len();
-        debug!(
-            strategy = "select oldest unaudited",
-            item_count, "Adding content keys to the audit queue."
-        );
-        add_to_queue(
-            tx.clone(),
-            SelectionStrategy::SelectOldestUnaudited,
-            content_key_db_entries,
+        Ok(content)
+    }
+}
+
+/// Selects content for [SelectionStrategy::Unconfirmed].
+///
+/// Strategy achieved by:
+/// 1. Leaving out content whose latest audit passed within `--skip-if-passed-within`.
+/// 2. Leaving out content audited within the recheck cooldown, so that content that
+///    keeps failing is rechecked at the pace of the recheck failures strategy.
+/// 3. Sorting the rest newest first.
+///
+/// This is the inverse of [FailedSelector]: the audits go to content that is not known
+/// to be available, whether never audited or failing.
+pub struct UnconfirmedSelector {
+    skip_if_passed_within: u64,
+    recheck_cooldown_seconds: u64,
+    radius: Option<ContentRadius>,
+    content_type: ContentType,
+    shard: Option<Shard>,
+}
+
+impl UnconfirmedSelector {
+    pub fn new(config: &AuditConfig) -> Self {
+        UnconfirmedSelector {
+            skip_if_passed_within: config.skip_if_passed_within,
+            recheck_cooldown_seconds: config.recheck_cooldown_seconds,
+            radius: config.radius.clone(),
+            content_type: config.content_type.clone(),
+            shard: config.shard,
+        }
+    }
+}
+
+#[async_trait]
+impl ContentSelector for UnconfirmedSelector {
+    fn strategy(&self) -> SelectionStrategy {
+        SelectionStrategy::Unconfirmed
+    }
+
+    async fn select(&self, conn: &DatabaseConnection, limit: u64) -> Result<Vec<Model>> {
+        let content = content::Entity::find()
+            .filter(
+                content::Column::Id
+                    .not_in_subquery(recently_passed_content(self.skip_if_passed_within)),
+            )
+            .filter(
+                content::Column::Id
+                    .not_in_subquery(recently_audited_content(self.recheck_cooldown_seconds)),
+            )
+            .filter(within_radius(&self.radius))
+            .filter(is_content_type(&self.content_type))
+            .filter(in_shard(&self.shard, Dialect::of(conn)))
+            .order_by_desc(content::Column::FirstAvailableAt)
+            .limit(limit)
+            .all(conn)
+            .await?;
+        Ok(content)
+    }
+}
+
+/// Selects content for [SelectionStrategy::EpochBoundary].
+///
+/// Strategy achieved by:
+/// 1. Joining the content to its block number, leaving out content without one.
+/// 2. Filtering for pre-merge blocks among the first or last `window` blocks of an epoch.
+/// 3. Leaving out content audited within the staleness threshold.
+/// 4. Sorting by distance from the nearest epoch boundary.
+///
+/// Epochs are the runs of [EPOCH_SIZE] blocks summarized by each pre-merge epoch
+/// accumulator, the first starting at the genesis block. Post-merge blocks are not
+/// summarized by these accumulators, so they are not selected.
+pub struct EpochBoundarySelector {
+    window: u64,
+    staleness_threshold_seconds: u64,
+    radius: Option<ContentRadius>,
+    content_type: ContentType,
+    shard: Option<Shard>,
+}
+
+impl EpochBoundarySelector {
+    pub fn new(config: &AuditConfig) -> Self {
+        EpochBoundarySelector {
+            window: config.epoch_boundary_window,
+            staleness_threshold_seconds: config.staleness_threshold_seconds,
+            radius: config.radius.clone(),
+            content_type: config.content_type.clone(),
+            shard: config.shard,
+        }
+    }
+
+    /// Number of blocks between the content's block and the nearest epoch boundary,
+    /// 0 for the first and last block of an epoch.
+    fn distance_from_boundary() -> String {
+        let position = format!("execution_metadata.block_number % {EPOCH_SIZE}");
+        format!(
+            "(CASE WHEN {position} < {half} THEN {position} ELSE {last} - {position} END)",
+            half = EPOCH_SIZE / 2,
+            last = EPOCH_SIZE - 1,
         )
-        .await;
+    }
+}
+
+#[async_trait]
+impl ContentSelector for EpochBoundarySelector {
+    fn strategy(&self) -> SelectionStrategy {
+        SelectionStrategy::EpochBoundary
+    }
+
+    async fn select(&self, conn: &DatabaseConnection, limit: u64) -> Result<Vec<Model>> {
+        let distance = Self::distance_from_boundary();
+        let content = content::Entity::find()
+            .join(
+                JoinType::InnerJoin,
+                entity::content::Relation::ExecutionMetadata.def(),
+            )
+            .filter(execution_metadata::Column::BlockNumber.lt(MERGE_BLOCK_HEIGHT))
+            .filter(Expr::cust(&format!("{distance} < {}", self.window)))
+            .filter(
+                content::Column::Id
+                    .not_in_subquery(recently_audited_content(self.staleness_threshold_seconds)),
+            )
+            .filter(within_radius(&self.radius))
+            .filter(is_content_type(&self.content_type))
+            .filter(in_shard(&self.shard, Dialect::of(conn)))
+            .order_by(Expr::cust(&distance), Order::Asc)
+            .order_by_asc(execution_metadata::Column::BlockNumber)
+            .limit(limit)
+            .all(conn)
+            .await?;
+        Ok(content)
+    }
+}
+
+/// Selects content for [SelectionStrategy::Failed].
+///
+/// Strategy achieved by:
+/// 1. Finding the latest audit of each content key.
+/// 2. Filtering for those that failed longer ago than the recheck cooldown.
+/// 3. Sorting by audit id, so that keys are rechecked in the order they failed.
+/// 4. Remembering the last audit selected, so that each failure is only rechecked once.
+///
+/// A recheck that fails again creates a newer audit, which puts the key at the back of the
+/// line. This way a key that keeps failing does not crowd out other failures.
+pub struct FailedSelector {
+    cooldown: chrono::Duration,
+    /// Id of the latest failed audit that has been selected for a recheck.
+    last_rechecked_audit: AtomicI32,
+    radius: Option<ContentRadius>,
+    content_type: ContentType,
+    shard: Option<Shard>,
+}
+
+impl FailedSelector {
+    pub fn new(config: &AuditConfig) -> Self {
+        FailedSelector {
+            cooldown: chrono::Duration::seconds(config.recheck_cooldown_seconds as i64),
+            last_rechecked_audit: AtomicI32::new(0),
+            radius: config.radius.clone(),
+            content_type: config.content_type.clone(),
+            shard: config.shard,
+        }
+    }
+}
+
+#[async_trait]
+impl ContentSelector for FailedSelector {
+    fn strategy(&self) -> SelectionStrategy {
+        SelectionStrategy::Failed
+    }
+
+    async fn select(&self, conn: &DatabaseConnection, limit: u64) -> Result<Vec<Model>> {
+        let latest_audits = Query::select()
+            .expr(Expr::col(content_audit::Column::Id).max())
+            .from(content_audit::Entity)
+            .group_by_col(content_audit::Column::ContentKey)
+            .to_owned();
+        let failed_audits: Vec<(content_audit::Model, Option<content::Model>)> =
+            content_audit::Entity::find()
+                .filter(content_audit::Column::Id.in_subquery(latest_audits))
+                .filter(
+                    content_audit::Column::Id.gt(self.last_rechecked_audit.load(Ordering::Relaxed)),
+                )
+                .filter(content_audit::Column::Result.eq(AuditResult::Failure))
+                .filter(content_audit::Column::CreatedAt.lt(Utc::now() - self.cooldown))
+                .order_by_asc(content_audit::Column::Id)
+                .limit(limit)
+                .find_also_related(content::Entity)
+                .filter(within_radius(&self.radius))
+                .filter(is_content_type(&self.content_type))
+                .filter(in_shard(&self.shard, Dialect::of(conn)))
+                .all(conn)
+                .await?;
+        let content = failed_audits
+            .into_iter()
+            .filter_map(|(audit, content)| {
+                self.last_rechecked_audit
+                    .fetch_max(audit.id, Ordering::Relaxed);
+                content
+            })
+            .collect();
+        Ok(content)
     }
 }
 
@@ -338,8 +882,12 @@ mod tests {
         content_audit::{self, AuditResult},
         node,
     };
+    use ethereum_types::H256;
     use ethportal_api::types::node_id::NodeId;
-    use ethportal_api::{BlockHeaderKey, HistoryContentKey, OverlayContentKey};
+    use ethportal_api::{
+        BlockBodyKey, BlockHeaderKey, BlockReceiptsKey, EpochAccumulatorKey, HistoryContentKey,
+        OverlayContentKey,
+    };
     use migration::{DbErr, Migrator, MigratorTrait};
     use sea_orm::{
         ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, Database, DbConn, EntityTrait,
@@ -347,6 +895,8 @@ mod tests {
     };
     use tokio::sync::mpsc::channel;
 
+    use crate::radius::Radius;
+
     use super::*;
 
     /// Creates a new in-memory SQLite database for a unit test.
@@ -361,7 +911,7 @@ mod tests {
     /// different strategies.
     ///
     /// Populated in three sections (old, middle, new) of ~15, given
-    /// that audit strategies are performed on blocks of KEYS_PER_PERIOD (=10).
+    /// that the tests select keys in batches of 10 (the channel size).
     ///
     /// Properties:
     /// - 45 total
@@ -399,6 +949,8 @@ mod tests {
                 content_id: Set(content_key.content_id().to_vec()),
                 content_key: Set(content_key.to_bytes()),
                 first_available_at: Set(available_at),
+                audit_count: Set(0),
+                audit_success_count: Set(0),
                 protocol_id: Set(SubProtocol::History),
             };
             let content_key_model = content_key_active_model.insert(&conn).await?;
@@ -434,6 +986,14 @@ mod tests {
                     trace: Set("".to_owned()),
                     client_info: Set(Some(client_info_model.id)),
                     node: Set(Some(node.id)),
+                    duration_ms: Set(None),
+                    outcome: Set(None),
+                    served_by: Set(None),
+                    error: Set(None),
+                    label: Set(None),
+                    closer_peer_responses: Set(None),
+                    content_length: Set(None),
+                    content_id_verified: Set(None),
                 };
                 content_audit_active_model.insert(&conn).await?;
             }
@@ -459,7 +1019,12 @@ mod tests {
         const CHANNEL_SIZE: usize = 20;
         let (tx, mut rx) = channel::<AuditTask>(CHANNEL_SIZE);
         // Start strategy
-        tokio::spawn(select_latest_content_for_audit(tx.clone(), conn.clone()));
+        tokio::spawn(start_audit_selection_task(
+            Box::new(LatestSelector::new(&AuditConfig::default())),
+            tx.clone(),
+            conn.clone(),
+            AuditConfig::default(),
+        ));
         let mut checked_ids: HashSet<i32> = HashSet::new();
         // There are 15 correct values: [31, 32, ... 45], after which the queue should be empty
         let expected_key_ids: Vec<i32> = (31..=45).collect();
@@ -498,9 +1063,11 @@ mod tests {
         const CHANNEL_SIZE: usize = 10;
         let (tx, mut rx) = channel::<AuditTask>(CHANNEL_SIZE);
         // Start strategy
-        tokio::spawn(select_oldest_unaudited_content_for_audit(
+        tokio::spawn(start_audit_selection_task(
+            Box::new(OldestUnauditedSelector::new(&AuditConfig::default())),
             tx.clone(),
             conn.clone(),
+            AuditConfig::default(),
         ));
         let mut checked_ids: HashSet<i32> = HashSet::new();
         // There are 10 correct values: [2, ..., 11]
@@ -533,7 +1100,12 @@ mod tests {
         const CHANNEL_SIZE: usize = 10;
         let (tx, mut rx) = channel::<AuditTask>(CHANNEL_SIZE);
         // Start strategy
-        tokio::spawn(select_latest_content_for_audit(tx.clone(), conn.clone()));
+        tokio::spawn(start_audit_selection_task(
+            Box::new(LatestSelector::new(&AuditConfig::default())),
+            tx.clone(),
+            conn.clone(),
+            AuditConfig::default(),
+        ));
         let mut checked_ids: HashSet<i32> = HashSet::new();
         // There are 45 possible correct values: [1, 2, ... 45]
         let expected_key_ids: Vec<i32> = (1..=45).collect();
@@ -555,4 +1127,473 @@ mod tests {
         // Make sure no key was audited twice by pushing to a hashmap and checking it's length.
         assert_eq!(checked_ids.len(), CHANNEL_SIZE);
     }
+
+    /// Tests that every history content type stored in the database is decoded into
+    /// the matching `HistoryContentKey` variant, and that undecodable records are skipped.
+    #[tokio::test]
+    async fn test_add_to_queue_decodes_all_content_types() {
+        let conn = setup_database().await.unwrap();
+        let block_hash = [7; 32];
+        let content_keys = vec![
+            HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey { block_hash }),
+            HistoryContentKey::BlockBody(BlockBodyKey { block_hash }),
+            HistoryContentKey::BlockReceipts(BlockReceiptsKey { block_hash }),
+            HistoryContentKey::EpochAccumulator(EpochAccumulatorKey {
+                epoch_hash: H256::from(block_hash),
+            }),
+        ];
+        let mut content_key_models = vec![];
+        for content_key in &content_keys {
+            content_key_models.push(content::get_or_create(content_key, &conn).await.unwrap());
+        }
+        // A record with an unknown selector byte can't be decoded.
+        let malformed = content::ActiveModel {
+            id: NotSet,
+            content_id: Set(vec![1; 32]),
+            content_key: Set(vec![0xff; 33]),
+            first_available_at: Set(Utc::now()),
+            audit_count: Set(0),
+            audit_success_count: Set(0),
+            protocol_id: Set(SubProtocol::History),
+        };
+        content_key_models.push(malformed.insert(&conn).await.unwrap());
+
+        let metrics = AuditMetrics::default();
+        let (tx, mut rx) = channel::<AuditTask>(10);
+        add_to_queue(tx, SelectionStrategy::Latest, content_key_models, &metrics).await;
+
+        // The sender has been dropped, so the channel drains and then closes.
+        let mut decoded_keys = vec![];
+        while let Some(task) = rx.recv().await {
+            decoded_keys.push(task.content_key);
+        }
+        assert_eq!(decoded_keys, content_keys);
+        assert_eq!(metrics.malformed_content_keys.load(Ordering::Relaxed), 1);
+    }
+
+    /// Tests that content of other types is dropped when a content type is selected.
+    #[tokio::test]
+    async fn test_retain_content_type() {
+        let conn = setup_database().await.unwrap();
+        let block_hash = [8; 32];
+        let header = HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey { block_hash });
+        let body = HistoryContentKey::BlockBody(BlockBodyKey { block_hash });
+        let receipts = HistoryContentKey::BlockReceipts(BlockReceiptsKey { block_hash });
+        let mut content_key_models = vec![];
+        for content_key in [&header, &body, &receipts] {
+            content_key_models.push(content::get_or_create(content_key, &conn).await.unwrap());
+        }
+
+        let retained = retain_content_type(&ContentType::Body, content_key_models.clone());
+        assert_eq!(retained, vec![content_key_models[1].clone()]);
+        assert_eq!(
+            retain_content_type(&ContentType::All, content_key_models.clone()),
+            content_key_models
+        );
+    }
+
+    /// Tests that selectors only look up content of the configured type.
+    #[tokio::test]
+    async fn test_selector_content_type() {
+        let conn = setup_database().await.unwrap();
+        let block_hash = [9; 32];
+        let body = HistoryContentKey::BlockBody(BlockBodyKey { block_hash });
+        for content_key in [
+            HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey { block_hash }),
+            body.clone(),
+            HistoryContentKey::BlockReceipts(BlockReceiptsKey { block_hash }),
+        ] {
+            content::get_or_create(&content_key, &conn).await.unwrap();
+        }
+
+        let config = AuditConfig {
+            content_type: ContentType::Body,
+            ..Default::default()
+        };
+        let selected = RandomSelector::new(&config)
+            .select(&conn, 10)
+            .await
+            .unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].content_key, body.to_bytes());
+    }
+
+    /// Tests that selectors only look up the content in their shard, as computed by the
+    /// database.
+    #[tokio::test]
+    async fn test_selector_shard() {
+        let conn = setup_database().await.unwrap();
+        let mut in_shard = HashSet::new();
+        let shard = Shard::new(1, 3).unwrap();
+        for n in 0..30u8 {
+            let content_key = HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+                block_hash: [n; 32],
+            });
+            let content = content::get_or_create(&content_key, &conn).await.unwrap();
+            if shard.contains(&content.content_id) {
+                in_shard.insert(content.id);
+            }
+        }
+        assert!(!in_shard.is_empty());
+
+        let config = AuditConfig {
+            shard: Some(shard),
+            ..Default::default()
+        };
+        let selected: HashSet<i32> = UnconfirmedSelector::new(&config)
+            .select(&conn, 100)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|content| content.id)
+            .collect();
+        assert_eq!(selected, in_shard);
+    }
+
+    /// Tests that the unconfirmed strategy skips content whose latest audit passed recently.
+    #[tokio::test]
+    async fn test_unconfirmed_strategy() {
+        let conn = setup_database().await.unwrap();
+        let mut content_ids = vec![];
+        for block_hash in [[10; 32], [11; 32], [12; 32], [13; 32]] {
+            let content_key =
+                HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey { block_hash });
+            content_ids.push(
+                content::get_or_create(&content_key, &conn)
+                    .await
+                    .unwrap()
+                    .id,
+            );
+        }
+        let audit = |content_key, result, hours_ago| content_audit::ActiveModel {
+            id: NotSet,
+            content_key: Set(content_key),
+            created_at: Set(Utc::now() - chrono::Duration::hours(hours_ago)),
+            strategy_used: Set(Some(SelectionStrategy::Latest)),
+            result: Set(result),
+            trace: Set("".to_owned()),
+            client_info: Set(None),
+            node: Set(None),
+            duration_ms: Set(None),
+            outcome: Set(None),
+            served_by: Set(None),
+            error: Set(None),
+            label: Set(None),
+            closer_peer_responses: Set(None),
+            content_length: Set(None),
+            content_id_verified: Set(None),
+        };
+        // Passed recently, and is skipped.
+        audit(content_ids[0], AuditResult::Success, 1)
+            .insert(&conn)
+            .await
+            .unwrap();
+        // Passed, but longer ago than the window.
+        audit(content_ids[1], AuditResult::Success, 48)
+            .insert(&conn)
+            .await
+            .unwrap();
+        // Passed once, then failed.
+        audit(content_ids[2], AuditResult::Success, 2)
+            .insert(&conn)
+            .await
+            .unwrap();
+        audit(content_ids[2], AuditResult::Failure, 1)
+            .insert(&conn)
+            .await
+            .unwrap();
+        // content_ids[3] was never audited.
+
+        let config = AuditConfig {
+            skip_if_passed_within: 24 * 3600,
+            recheck_cooldown_seconds: 0,
+            ..Default::default()
+        };
+        let selected: HashSet<i32> = UnconfirmedSelector::new(&config)
+            .select(&conn, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|content| content.id)
+            .collect();
+        assert_eq!(
+            selected,
+            HashSet::from([content_ids[1], content_ids[2], content_ids[3]])
+        );
+    }
+
+    /// Tests that the epoch boundary strategy selects the pre-merge blocks near the start
+    /// or end of an epoch, nearest to a boundary first.
+    #[tokio::test]
+    async fn test_epoch_boundary_strategy() {
+        let conn = setup_database().await.unwrap();
+        let epoch_start = 3 * EPOCH_SIZE as i32;
+        let mut content_ids = vec![];
+        for (num, block_number) in [
+            epoch_start,
+            epoch_start - 1,
+            epoch_start + 2,
+            epoch_start - 4,
+            // Middle of an epoch.
+            epoch_start + 4096,
+            // Post-merge, though at the start of what would be an epoch.
+            (MERGE_BLOCK_HEIGHT / EPOCH_SIZE as i32 + 1) * EPOCH_SIZE as i32,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let content_key = HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+                block_hash: [num as u8 + 1; 32],
+            });
+            let content = content::get_or_create(&content_key, &conn).await.unwrap();
+            execution_metadata::get_or_create(content.id, block_number, &conn)
+                .await
+                .unwrap();
+            content_ids.push(content.id);
+        }
+
+        let config = AuditConfig {
+            epoch_boundary_window: 3,
+            ..Default::default()
+        };
+        let selected: Vec<i32> = EpochBoundarySelector::new(&config)
+            .select(&conn, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|content| content.id)
+            .collect();
+        // Distances of 0, 0 and 2 from the boundary, while the fourth is 3 away.
+        assert_eq!(
+            selected,
+            vec![content_ids[1], content_ids[0], content_ids[2]]
+        );
+    }
+
+    /// Tests that the `SelectionStrategy::Failed` rechecks each key whose latest audit failed.
+    #[tokio::test]
+    async fn test_failed_strategy() {
+        let conn = get_populated_test_audit_db().await.unwrap();
+        // Key 17 failed, but has passed since.
+        let recovered = content_audit::ActiveModel {
+            id: NotSet,
+            content_key: Set(17),
+            created_at: Set(Utc::now()),
+            strategy_used: Set(Some(SelectionStrategy::Failed)),
+            result: Set(AuditResult::Success),
+            trace: Set("".to_owned()),
+            client_info: Set(None),
+            node: Set(None),
+            duration_ms: Set(None),
+            outcome: Set(None),
+            served_by: Set(None),
+            error: Set(None),
+            label: Set(None),
+            closer_peer_responses: Set(None),
+            content_length: Set(None),
+            content_id_verified: Set(None),
+        };
+        recovered.insert(&conn).await.unwrap();
+
+        const CHANNEL_SIZE: usize = 10;
+        let (tx, mut rx) = channel::<AuditTask>(CHANNEL_SIZE);
+        let config = AuditConfig {
+            audit_period_seconds: 1,
+            recheck_cooldown_seconds: 0,
+            ..Default::default()
+        };
+        tokio::spawn(start_audit_selection_task(
+            Box::new(FailedSelector::new(&config)),
+            tx.clone(),
+            conn.clone(),
+            config,
+        ));
+        // Keys 1 and the odd keys 19-29 failed their latest audit.
+        let expected_key_ids: Vec<i32> = [1].into_iter().chain((19..=29).step_by(2)).collect();
+        let mut checked_ids: Vec<i32> = vec![];
+        while let Some(task) = rx.recv().await {
+            assert_eq!(task.strategy, SelectionStrategy::Failed);
+            let key_model = content::Entity::find()
+                .filter(content::Column::ContentKey.eq(task.content_key.to_bytes()))
+                .one(&conn)
+                .await
+                .unwrap()
+                .unwrap();
+            checked_ids.push(key_model.id);
+            if checked_ids.len() == expected_key_ids.len() {
+                break;
+            }
+        }
+        // Oldest failure first, each key once.
+        assert_eq!(checked_ids, expected_key_ids);
+    }
+
+    /// Tests that the `SelectionStrategy::Failed` waits for the cooldown before rechecking.
+    #[tokio::test]
+    async fn test_failed_strategy_cooldown() {
+        let conn = get_populated_test_audit_db().await.unwrap();
+        let (tx, mut rx) = channel::<AuditTask>(10);
+        let config = AuditConfig {
+            audit_period_seconds: 1,
+            recheck_cooldown_seconds: 3600,
+            ..Default::default()
+        };
+        tokio::spawn(start_audit_selection_task(
+            Box::new(FailedSelector::new(&config)),
+            tx.clone(),
+            conn.clone(),
+            config,
+        ));
+        // All audits in the test database were just made.
+        let received = tokio::time::timeout(Duration::from_secs(2), rx.recv()).await;
+        assert!(received.is_err());
+    }
+
+    /// Tests that a triggered selection cycle runs straight away and reports the keys it enqueued.
+    #[tokio::test]
+    async fn test_audit_trigger() {
+        let conn = setup_database().await.unwrap();
+        let (tx, mut rx) = channel::<AuditTask>(10);
+        let config = AuditConfig {
+            audit_period_seconds: 3600,
+            ..Default::default()
+        };
+        let trigger = config.trigger.clone();
+        tokio::spawn(start_audit_selection_task(
+            Box::new(UnconfirmedSelector::new(&config)),
+            tx,
+            conn.clone(),
+            config,
+        ));
+        // Wait for the task to start listening, nothing is in the database yet.
+        let enqueued = loop {
+            match trigger.trigger().await {
+                Some(enqueued) => break enqueued,
+                None => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        assert_eq!(enqueued, 0);
+
+        for block_hash in [[20; 32], [21; 32]] {
+            let content_key =
+                HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey { block_hash });
+            content::get_or_create(&content_key, &conn).await.unwrap();
+        }
+        assert_eq!(trigger.trigger().await, Some(2));
+        assert!(rx.recv().await.is_some());
+        assert!(rx.recv().await.is_some());
+    }
+
+    /// Tests that the latest selector returns at most `limit` of the newest unaudited keys.
+    #[tokio::test]
+    async fn test_latest_selector_limit() {
+        let conn = get_populated_test_audit_db().await.unwrap();
+        let selected = LatestSelector::new(&AuditConfig::default())
+            .select(&conn, 5)
+            .await
+            .unwrap();
+        assert_eq!(selected.len(), 5);
+        assert!(selected
+            .iter()
+            .all(|content| (31..=45).contains(&content.id)));
+    }
+
+    /// Tests that only content within the radius of the node id is selected.
+    #[tokio::test]
+    async fn test_latest_selector_radius() {
+        let conn = get_populated_test_audit_db().await.unwrap();
+        let target = content::Entity::find_by_id(40)
+            .one(&conn)
+            .await
+            .unwrap()
+            .unwrap();
+        let node_id: [u8; 32] = target.content_id.clone().try_into().unwrap();
+        let config = AuditConfig {
+            radius: Some(ContentRadius::new(
+                node_id,
+                "0x0".parse::<Radius>().unwrap(),
+            )),
+            ..Default::default()
+        };
+        let selected = LatestSelector::new(&config)
+            .select(&conn, 100)
+            .await
+            .unwrap();
+        assert_eq!(selected, vec![target]);
+    }
+
+    /// Tests that the failed selector only selects each failure once.
+    #[tokio::test]
+    async fn test_failed_selector_remembers_rechecks() {
+        let conn = get_populated_test_audit_db().await.unwrap();
+        let config = AuditConfig {
+            recheck_cooldown_seconds: 0,
+            ..Default::default()
+        };
+        let selector = FailedSelector::new(&config);
+        let first = selector.select(&conn, 100).await.unwrap();
+        let first_ids: Vec<i32> = first.iter().map(|content| content.id).collect();
+        let expected_key_ids: Vec<i32> = [1, 17].into_iter().chain((19..=29).step_by(2)).collect();
+        assert_eq!(first_ids, expected_key_ids);
+        assert!(selector.select(&conn, 100).await.unwrap().is_empty());
+    }
+
+    /// Tests that no keys are selected while more than half of the channel is waiting.
+    #[tokio::test]
+    async fn test_keys_to_select_backpressure() {
+        let (tx, _rx) = channel::<AuditTask>(10);
+        let config = AuditConfig {
+            batch_size: 3,
+            ..Default::default()
+        };
+        let task = AuditTask {
+            strategy: SelectionStrategy::Latest,
+            content_key: HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+                block_hash: [1; 32],
+            }),
+            selected_at: Utc::now(),
+        };
+        for _ in 0..5 {
+            tx.send(task.clone()).await.unwrap();
+        }
+        assert_eq!(keys_to_select(&tx, SelectionStrategy::Latest, &config), 3);
+        tx.send(task).await.unwrap();
+        assert_eq!(keys_to_select(&tx, SelectionStrategy::Latest, &config), 0);
+        assert_eq!(config.metrics.backpressure_skips.load(Ordering::Relaxed), 1);
+    }
+
+    /// Tests that the `SelectionStrategy::Random` skips content audited within
+    /// the staleness threshold.
+    #[tokio::test]
+    async fn test_random_strategy_skips_recently_audited() {
+        let conn = get_populated_test_audit_db().await.unwrap();
+        const CHANNEL_SIZE: usize = 10;
+        let (tx, mut rx) = channel::<AuditTask>(CHANNEL_SIZE);
+        let config = AuditConfig {
+            audit_period_seconds: 1,
+            ..Default::default()
+        };
+        tokio::spawn(start_audit_selection_task(
+            Box::new(RandomSelector::new(&config)),
+            tx.clone(),
+            conn.clone(),
+            config,
+        ));
+        let mut checked_ids: HashSet<i32> = HashSet::new();
+        // Keys 1 and 16-30 were just audited.
+        let expected_key_ids: Vec<i32> = (2..=15).chain(31..=45).collect();
+        while let Some(task) = rx.recv().await {
+            let key_model = content::Entity::find()
+                .filter(content::Column::ContentKey.eq(task.content_key.to_bytes()))
+                .one(&conn)
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(expected_key_ids.contains(&key_model.id));
+            checked_ids.insert(key_model.id);
+            if checked_ids.len() == CHANNEL_SIZE {
+                break;
+            }
+        }
+    }
 }
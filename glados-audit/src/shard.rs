@@ -0,0 +1,72 @@
+use glados_core::dialect::Dialect;
+use sea_orm::{sea_query::Expr, Condition};
+
+/// The share of the content audited by one of several instances auditing the same database.
+///
+/// Content belongs to the shard given by the last two bytes of its 32 byte id modulo the number
+/// of shards. Content ids are hashes, so each shard gets an even share, and every
+/// instance must be given the same number of shards for each content id to have
+/// exactly one shard.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Shard {
+    index: u32,
+    count: u32,
+}
+
+impl Shard {
+    pub fn new(index: u32, count: u32) -> Result<Self, String> {
+        if index >= count {
+            return Err(format!(
+                "--shard-index {index} must be less than --shard-count {count}"
+            ));
+        }
+        Ok(Shard { index, count })
+    }
+
+    /// Returns true if the content id belongs to this shard.
+    pub fn contains(&self, content_id: &[u8]) -> bool {
+        if content_id.len() != 32 {
+            return false;
+        }
+        u32::from(u16::from_be_bytes([content_id[30], content_id[31]])) % self.count == self.index
+    }
+
+    /// Matches the content in this shard, computed by the database in the same way
+    /// as [`Shard::contains`].
+    pub fn condition(&self, dialect: Dialect) -> Condition {
+        let tail = dialect.last_two_bytes("content.content_id");
+        Condition::all().add(Expr::cust(&format!(
+            "{tail} % {} = {}",
+            self.count, self.index
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard() {
+        assert!(Shard::new(3, 3).is_err());
+        let shard = Shard::new(1, 3).unwrap();
+        let mut content_id = [0xaa; 32];
+        content_id[30] = 0x00;
+        content_id[31] = 0x04;
+        assert!(shard.contains(&content_id));
+        content_id[31] = 0x05;
+        assert!(!shard.contains(&content_id));
+        // 0x0100 is 256, which is 1 modulo 3.
+        content_id[30] = 0x01;
+        content_id[31] = 0x00;
+        assert!(shard.contains(&content_id));
+        assert!(!shard.contains(&[0x04; 31]));
+
+        let shards: Vec<Shard> = (0..3).map(|index| Shard::new(index, 3).unwrap()).collect();
+        for low in 0..=255 {
+            content_id[31] = low;
+            let owners = shards.iter().filter(|shard| shard.contains(&content_id));
+            assert_eq!(owners.count(), 1);
+        }
+    }
+}
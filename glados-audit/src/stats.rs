@@ -1,29 +1,41 @@
 use chrono::Utc;
 use entity::audit_stats;
 use glados_core::stats::{
-    filter_audits, get_audit_stats, AuditFilters, ContentTypeFilter, Period, StrategyFilter,
-    SuccessFilter,
+    filter_audits, get_audit_stats, has_label, AuditFilters, ContentTypeFilter, Period,
+    StrategyFilter, SuccessFilter,
 };
-use sea_orm::{DatabaseConnection, DbErr};
+use sea_orm::{DatabaseConnection, DbErr, QueryFilter};
 use tokio::time::{interval, Duration};
 use tracing::{debug, error};
 
 /// Loops indefinitely, periodically recording audit stats to the database.
-pub async fn periodically_record_stats(period: Duration, conn: DatabaseConnection) -> ! {
+///
+/// With a label, only the audits made with it are counted and the stats are recorded
+/// under it.
+pub async fn periodically_record_stats(
+    period: Duration,
+    label: Option<String>,
+    conn: DatabaseConnection,
+) -> ! {
     debug!("initializing task for logging audit stats");
     let mut interval = interval(period);
 
     loop {
-        record_current_stats(&conn).await.unwrap_or_else(|e| {
-            error!("failed to record audit stats: {e}");
-        });
+        record_current_stats(label.clone(), &conn)
+            .await
+            .unwrap_or_else(|e| {
+                error!("failed to record audit stats: {e}");
+            });
         interval.tick().await;
     }
 }
 
 /// Records audit stats for the current moment to the database.
 /// Calculates success rate for many combinations of strategy and content type.
-async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
+async fn record_current_stats(
+    label: Option<String>,
+    conn: &DatabaseConnection,
+) -> Result<(), DbErr> {
     // Run audit stat queries in parallel.
     let (
         all,
@@ -49,7 +61,8 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
                 strategy: StrategyFilter::All,
                 content_type: ContentTypeFilter::All,
                 success: SuccessFilter::All
-            }),
+            })
+            .filter(has_label(label.as_deref())),
             Period::Hour,
             conn
         ),
@@ -58,7 +71,8 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
                 strategy: StrategyFilter::Latest,
                 content_type: ContentTypeFilter::All,
                 success: SuccessFilter::All
-            }),
+            })
+            .filter(has_label(label.as_deref())),
             Period::Hour,
             conn
         ),
@@ -67,7 +81,8 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
                 strategy: StrategyFilter::Random,
                 content_type: ContentTypeFilter::All,
                 success: SuccessFilter::All
-            }),
+            })
+            .filter(has_label(label.as_deref())),
             Period::Hour,
             conn
         ),
@@ -76,7 +91,8 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
                 strategy: StrategyFilter::Oldest,
                 content_type: ContentTypeFilter::All,
                 success: SuccessFilter::All
-            }),
+            })
+            .filter(has_label(label.as_deref())),
             Period::Hour,
             conn
         ),
@@ -85,7 +101,8 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
                 strategy: StrategyFilter::FourFours,
                 content_type: ContentTypeFilter::All,
                 success: SuccessFilter::All
-            }),
+            })
+            .filter(has_label(label.as_deref())),
             Period::Hour,
             conn
         ),
@@ -94,7 +111,8 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
                 strategy: StrategyFilter::All,
                 content_type: ContentTypeFilter::Headers,
                 success: SuccessFilter::All
-            }),
+            })
+            .filter(has_label(label.as_deref())),
             Period::Hour,
             conn
         ),
@@ -103,7 +121,8 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
                 strategy: StrategyFilter::All,
                 content_type: ContentTypeFilter::Bodies,
                 success: SuccessFilter::All
-            }),
+            })
+            .filter(has_label(label.as_deref())),
             Period::Hour,
             conn
         ),
@@ -112,7 +131,8 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
                 strategy: StrategyFilter::All,
                 content_type: ContentTypeFilter::Receipts,
                 success: SuccessFilter::All
-            }),
+            })
+            .filter(has_label(label.as_deref())),
             Period::Hour,
             conn
         ),
@@ -121,7 +141,8 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
                 strategy: StrategyFilter::Latest,
                 content_type: ContentTypeFilter::Headers,
                 success: SuccessFilter::All
-            }),
+            })
+            .filter(has_label(label.as_deref())),
             Period::Hour,
             conn
         ),
@@ -130,7 +151,8 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
                 strategy: StrategyFilter::Latest,
                 content_type: ContentTypeFilter::Bodies,
                 success: SuccessFilter::All
-            }),
+            })
+            .filter(has_label(label.as_deref())),
             Period::Hour,
             conn
         ),
@@ -139,7 +161,8 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
                 strategy: StrategyFilter::Latest,
                 content_type: ContentTypeFilter::Receipts,
                 success: SuccessFilter::All
-            }),
+            })
+            .filter(has_label(label.as_deref())),
             Period::Hour,
             conn
         ),
@@ -148,7 +171,8 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
                 strategy: StrategyFilter::Random,
                 content_type: ContentTypeFilter::Headers,
                 success: SuccessFilter::All
-            }),
+            })
+            .filter(has_label(label.as_deref())),
             Period::Hour,
             conn
         ),
@@ -157,7 +181,8 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
                 strategy: StrategyFilter::Random,
                 content_type: ContentTypeFilter::Bodies,
                 success: SuccessFilter::All
-            }),
+            })
+            .filter(has_label(label.as_deref())),
             Period::Hour,
             conn
         ),
@@ -166,7 +191,8 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
                 strategy: StrategyFilter::Random,
                 content_type: ContentTypeFilter::Receipts,
                 success: SuccessFilter::All
-            }),
+            })
+            .filter(has_label(label.as_deref())),
             Period::Hour,
             conn
         ),
@@ -175,7 +201,8 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
                 strategy: StrategyFilter::FourFours,
                 content_type: ContentTypeFilter::Headers,
                 success: SuccessFilter::All
-            }),
+            })
+            .filter(has_label(label.as_deref())),
             Period::Hour,
             conn
         ),
@@ -184,7 +211,8 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
                 strategy: StrategyFilter::FourFours,
                 content_type: ContentTypeFilter::Bodies,
                 success: SuccessFilter::All
-            }),
+            })
+            .filter(has_label(label.as_deref())),
             Period::Hour,
             conn
         ),
@@ -193,7 +221,8 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
                 strategy: StrategyFilter::FourFours,
                 content_type: ContentTypeFilter::Receipts,
                 success: SuccessFilter::All
-            }),
+            })
+            .filter(has_label(label.as_deref())),
             Period::Hour,
             conn
         )
@@ -239,6 +268,7 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
         success_rate_fourfours_headers,
         success_rate_fourfours_bodies,
         success_rate_fourfours_receipts,
+        label,
         conn,
     )
     .await
@@ -248,3 +278,90 @@ async fn record_current_stats(conn: &DatabaseConnection) -> Result<(), DbErr> {
     };
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use entity::{
+        content,
+        content_audit::{self, AuditResult, SelectionStrategy},
+    };
+    use ethportal_api::{BlockBodyKey, BlockHeaderKey, HistoryContentKey};
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{ActiveModelTrait, ActiveValue::NotSet, Database, Set};
+
+    use super::*;
+
+    async fn insert_audit(
+        content_key_id: i32,
+        result: AuditResult,
+        label: Option<&str>,
+        conn: &DatabaseConnection,
+    ) {
+        content_audit::ActiveModel {
+            id: NotSet,
+            content_key: Set(content_key_id),
+            created_at: Set(Utc::now()),
+            strategy_used: Set(Some(SelectionStrategy::Latest)),
+            result: Set(result),
+            trace: Set("".to_owned()),
+            client_info: Set(None),
+            node: Set(None),
+            duration_ms: Set(None),
+            outcome: Set(None),
+            served_by: Set(None),
+            error: Set(None),
+            label: Set(label.map(str::to_owned)),
+            closer_peer_responses: Set(None),
+            content_length: Set(None),
+            content_id_verified: Set(None),
+        }
+        .insert(conn)
+        .await
+        .unwrap();
+    }
+
+    /// Runs the stats queries, which include backend specific SQL, against an
+    /// in-memory SQLite database.
+    #[tokio::test]
+    async fn test_record_current_stats() {
+        let conn = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&conn, None).await.unwrap();
+
+        let header = HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+            block_hash: [1; 32],
+        });
+        let body = HistoryContentKey::BlockBody(BlockBodyKey {
+            block_hash: [1; 32],
+        });
+        let header = content::get_or_create(&header, &conn).await.unwrap();
+        let body = content::get_or_create(&body, &conn).await.unwrap();
+        insert_audit(header.id, AuditResult::Success, None, &conn).await;
+        insert_audit(header.id, AuditResult::Failure, None, &conn).await;
+        insert_audit(body.id, AuditResult::Success, None, &conn).await;
+        insert_audit(body.id, AuditResult::Failure, Some("staging"), &conn).await;
+
+        record_current_stats(None, &conn).await.unwrap();
+        let stats = audit_stats::get_recent_stats(None, &conn).await.unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].success_rate_all_headers, 50.0);
+        assert_eq!(stats[0].success_rate_all_bodies, 50.0);
+
+        // Stats recorded under a label only count its audits, and are kept apart.
+        record_current_stats(Some("staging".to_owned()), &conn)
+            .await
+            .unwrap();
+        let stats = audit_stats::get_recent_stats(Some("staging"), &conn)
+            .await
+            .unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].label.as_deref(), Some("staging"));
+        assert_eq!(stats[0].success_rate_all_bodies, 0.0);
+        assert_eq!(
+            audit_stats::get_recent_stats(None, &conn)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
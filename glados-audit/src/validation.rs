@@ -1,11 +1,26 @@
+use ethereum_types::H256;
+use ethportal_api::types::execution::header::{BlockHeaderProof, Header, HeaderWithProof};
 use ethportal_api::utils::bytes::hex_encode;
-use ethportal_api::{BlockHeaderKey, HistoryContentKey, OverlayContentKey};
+use ethportal_api::{
+    BlockBodyKey, BlockHeaderKey, BlockReceiptsKey, EpochAccumulatorKey, HistoryContentKey,
+    OverlayContentKey,
+};
 use ethportal_api::{ContentValue, HistoryContentValue};
 use tracing::warn;
 
+use crate::proof::{epoch_accumulator_root, PreMergeAccumulator};
+
 /// Checks that content bytes correspond to a correctly formatted
 /// content value.
-pub fn content_is_valid(content_key: &HistoryContentKey, content_bytes: &[u8]) -> bool {
+///
+/// The value must be of the type requested by the key. Headers are checked
+/// against the block hash in the key. Block bodies and receipts are checked
+/// against `header` when one is given, otherwise only their format is checked.
+pub fn content_is_valid(
+    content_key: &HistoryContentKey,
+    content_bytes: &[u8],
+    header: Option<&Header>,
+) -> bool {
     // check deserialization is valid
     let content: HistoryContentValue = match HistoryContentValue::decode(content_bytes) {
         Ok(c) => c,
@@ -15,6 +30,31 @@ pub fn content_is_valid(content_key: &HistoryContentKey, content_bytes: &[u8]) -
         }
     };
 
+    // check the content is the type that was requested
+    let type_matches = matches!(
+        (content_key, &content),
+        (
+            HistoryContentKey::BlockHeaderWithProof(_),
+            HistoryContentValue::BlockHeaderWithProof(_)
+        ) | (
+            HistoryContentKey::BlockBody(_),
+            HistoryContentValue::BlockBody(_)
+        ) | (
+            HistoryContentKey::BlockReceipts(_),
+            HistoryContentValue::Receipts(_)
+        ) | (
+            HistoryContentKey::EpochAccumulator(_),
+            HistoryContentValue::EpochAccumulator(_)
+        )
+    );
+    if !type_matches {
+        warn!(
+            content.key = hex_encode(content_key.to_bytes()),
+            "content type did not match the requested content key"
+        );
+        return false;
+    }
+
     // check nature of content is valid
     match content {
         HistoryContentValue::BlockHeaderWithProof(h) => {
@@ -36,17 +76,42 @@ pub fn content_is_valid(content_key: &HistoryContentKey, content_bytes: &[u8]) -
             }
         }
         HistoryContentValue::BlockBody(b) => {
-            // Reconstruct the key using the block body contents.
-            let _computed_tx_root = b.transactions_root();
-            let _computed_uncles_root = b.uncles_root();
-            warn!("Need to call trusted provider to check block body correctness.");
-            true
+            let Some(header) = header else {
+                warn!("No verified header available to check block body correctness.");
+                return true;
+            };
+            // Reconstruct the header roots using the block body contents.
+            let roots_match = match (b.transactions_root(), b.uncles_root()) {
+                (Ok(tx_root), Ok(uncles_root)) => {
+                    tx_root == header.transactions_root && uncles_root == header.uncles_hash
+                }
+                _ => false,
+            };
+            if !roots_match {
+                warn!(
+                    content.key = hex_encode(content_key.to_bytes()),
+                    "computed block body roots did not match header"
+                );
+            }
+            roots_match
         }
         HistoryContentValue::Receipts(r) => {
-            // Reconstruct the key using the block body contents.
-            let _computed_receipts_root = r.root();
-            warn!("Need to call trusted provider to check receipts correctness.");
-            true
+            let Some(header) = header else {
+                warn!("No verified header available to check receipts correctness.");
+                return true;
+            };
+            // Reconstruct the header root using the receipts contents.
+            let root_matches = match r.root() {
+                Ok(receipts_root) => receipts_root == header.receipts_root,
+                Err(_) => false,
+            };
+            if !root_matches {
+                warn!(
+                    content.key = hex_encode(content_key.to_bytes()),
+                    "computed receipts root did not match header"
+                );
+            }
+            root_matches
         }
         HistoryContentValue::EpochAccumulator(_e) => {
             warn!("Need to check epoch master accumulator for correctness.");
@@ -54,3 +119,194 @@ pub fn content_is_valid(content_key: &HistoryContentKey, content_bytes: &[u8]) -
         }
     }
 }
+
+/// Works out the content id of the content, to compare against the id requested.
+///
+/// A content id is the hash of a content key rather than of the content, so the key is
+/// rebuilt from what the content proves about itself: a header from its own hash, an
+/// epoch accumulator from its SSZ hash tree root, and a block body or receipts from the
+/// hash of `header`. Returns `None` when the content does not identify its key, which
+/// is bodies and receipts without a header.
+///
+/// For a header this repeats the hash check of [content_is_valid]. A body or receipts
+/// is given the key of the header it was fetched for, so with a header the id matches
+/// by construction, and only means something for content that [content_is_valid]
+/// tied to that header.
+pub fn derived_content_id(
+    content_key: &HistoryContentKey,
+    content_bytes: &[u8],
+    header: Option<&Header>,
+) -> Option<[u8; 32]> {
+    let derived_key = match (content_key, header) {
+        (HistoryContentKey::BlockHeaderWithProof(_), _) => {
+            let Ok(HistoryContentValue::BlockHeaderWithProof(h)) =
+                HistoryContentValue::decode(content_bytes)
+            else {
+                return None;
+            };
+            HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+                block_hash: h.header.hash().into(),
+            })
+        }
+        (HistoryContentKey::EpochAccumulator(_), _) => {
+            HistoryContentKey::EpochAccumulator(EpochAccumulatorKey {
+                epoch_hash: H256::from(epoch_accumulator_root(content_bytes)?),
+            })
+        }
+        (HistoryContentKey::BlockBody(_), Some(header)) => {
+            HistoryContentKey::BlockBody(BlockBodyKey {
+                block_hash: header.hash().into(),
+            })
+        }
+        (HistoryContentKey::BlockReceipts(_), Some(header)) => {
+            HistoryContentKey::BlockReceipts(BlockReceiptsKey {
+                block_hash: header.hash().into(),
+            })
+        }
+        _ => return None,
+    };
+    Some(derived_key.content_id())
+}
+
+/// Checks whether the content is that of the requested content id.
+///
+/// Returns `None` when [derived_content_id] cannot tell.
+pub fn content_id_matches(
+    content_key: &HistoryContentKey,
+    content_bytes: &[u8],
+    header: Option<&Header>,
+) -> Option<bool> {
+    derived_content_id(content_key, content_bytes, header).map(|id| id == content_key.content_id())
+}
+
+/// Checks the proof of a header against the pre-merge accumulator, with trin's
+/// header proof verification.
+///
+/// Returns `None` for headers from after the merge, which carry no proof.
+pub fn proof_is_valid(header: &HeaderWithProof, accumulator: &PreMergeAccumulator) -> Option<bool> {
+    if let BlockHeaderProof::None(_) = header.proof {
+        return None;
+    }
+    match accumulator.validate_header_with_proof(header) {
+        Ok(()) => Some(true),
+        Err(e) => {
+            warn!(
+                block.number = header.header.number,
+                err=?e,
+                "header proof did not verify against the pre-merge accumulator"
+            );
+            Some(false)
+        }
+    }
+}
+
+/// Decodes a block header with its proof, returning it only if the header hashes to
+/// the block hash in the key.
+///
+/// Gives the same verdict as [content_is_valid] for a header key, decoding the content once.
+pub fn verified_header_with_proof(
+    header_key: &HistoryContentKey,
+    content_bytes: &[u8],
+) -> Option<HeaderWithProof> {
+    let Ok(HistoryContentValue::BlockHeaderWithProof(h)) =
+        HistoryContentValue::decode(content_bytes)
+    else {
+        warn!(
+            content.key = hex_encode(header_key.to_bytes()),
+            "content was not a block header"
+        );
+        return None;
+    };
+    let computed_key = HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+        block_hash: h.header.hash().into(),
+    });
+    if header_key != &computed_key {
+        warn!(
+            content.key = hex_encode(header_key.to_bytes()),
+            content.value = hex_encode(content_bytes),
+            "computed header hash did not match expected"
+        );
+        return None;
+    }
+    Some(h)
+}
+
+/// Decodes a block header, returning it only if it hashes to the block hash in the key.
+pub fn verified_header(header_key: &HistoryContentKey, content_bytes: &[u8]) -> Option<Header> {
+    verified_header_with_proof(header_key, content_bytes).map(|h| h.header)
+}
+
+#[cfg(test)]
+mod tests {
+    use ethportal_api::utils::bytes::hex_decode;
+
+    use super::*;
+
+    /// Mainnet block 1 as header content, without a proof.
+    fn block_1_header() -> Vec<u8> {
+        hex_decode(include_str!("../testdata/block_1_header.hex").trim()).unwrap()
+    }
+
+    fn block_1_key() -> HistoryContentKey {
+        let block_hash =
+            hex_decode("0x88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6")
+                .unwrap();
+        HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+            block_hash: block_hash.try_into().unwrap(),
+        })
+    }
+
+    /// Tests that a header only matches its content id if it hashes to the block hash.
+    #[test]
+    fn test_header_content_id() {
+        let key = block_1_key();
+        let mut content = block_1_header();
+        assert_eq!(content_id_matches(&key, &content, None), Some(true));
+
+        // Change the last byte of the nonce, just before the empty proof.
+        let nonce_end = content.len() - 2;
+        content[nonce_end] ^= 1;
+        assert_eq!(content_id_matches(&key, &content, None), Some(false));
+    }
+
+    /// Tests that a header without a proof is not checked against the accumulator.
+    #[test]
+    fn test_header_without_proof() {
+        let header = verified_header_with_proof(&block_1_key(), &block_1_header()).unwrap();
+        assert_eq!(header.header.number, 1);
+        assert_eq!(
+            proof_is_valid(&header, &PreMergeAccumulator::default()),
+            None
+        );
+    }
+
+    /// Tests that a block body is matched to its id through the header it was fetched for.
+    #[test]
+    fn test_body_content_id() {
+        let Ok(HistoryContentValue::BlockHeaderWithProof(h)) =
+            HistoryContentValue::decode(&block_1_header())
+        else {
+            panic!("block 1 header did not decode");
+        };
+        let key = HistoryContentKey::BlockBody(BlockBodyKey {
+            block_hash: h.header.hash().into(),
+        });
+        assert_eq!(content_id_matches(&key, &[], Some(&h.header)), Some(true));
+        assert_eq!(content_id_matches(&key, &[], None), None);
+    }
+
+    /// Tests that an epoch accumulator is matched to its id by its hash tree root.
+    #[test]
+    fn test_epoch_accumulator_content_id() {
+        let content = [5; 64];
+        let key = HistoryContentKey::EpochAccumulator(EpochAccumulatorKey {
+            epoch_hash: H256::from(epoch_accumulator_root(&content).unwrap()),
+        });
+        assert_eq!(content_id_matches(&key, &content, None), Some(true));
+        let other = HistoryContentKey::EpochAccumulator(EpochAccumulatorKey {
+            epoch_hash: H256::from([6; 32]),
+        });
+        assert_eq!(content_id_matches(&other, &content, None), Some(false));
+        assert_eq!(content_id_matches(&key, &content[..32], None), None);
+    }
+}
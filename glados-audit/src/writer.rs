@@ -0,0 +1,158 @@
+//! Buffering of audit results so that they are written in batched inserts.
+use std::sync::Arc;
+
+use entity::content_audit;
+use sea_orm::DatabaseConnection;
+use tokio::{
+    sync::Mutex,
+    time::{interval, Duration, MissedTickBehavior},
+};
+use tracing::{debug, error};
+
+use crate::InFlightGuard;
+
+/// An audit and the in flight guard of its task, released once the audit is written.
+type PendingAudit = (content_audit::ActiveModel, Option<Arc<InFlightGuard>>);
+
+/// Audits waiting to be written, shared by all audit workers.
+///
+/// The buffer is written once it holds `batch_size` audits, and by
+/// [`periodically_flush_audits`] every flush interval, whichever comes first. Until
+/// then the content of a buffered audit counts as being audited, so that the workers
+/// do not audit it again.
+#[derive(Debug)]
+pub struct AuditWriter {
+    batch_size: usize,
+    flush_interval: Duration,
+    pending: Mutex<Vec<PendingAudit>>,
+}
+
+impl AuditWriter {
+    pub fn new(batch_size: usize, flush_interval: Duration) -> Self {
+        AuditWriter {
+            batch_size,
+            flush_interval,
+            pending: Mutex::new(Vec::with_capacity(batch_size)),
+        }
+    }
+
+    /// Adds an audit to the buffer, writing the buffer if it is full.
+    pub async fn push(
+        &self,
+        audit: content_audit::ActiveModel,
+        in_flight: Option<Arc<InFlightGuard>>,
+        conn: &DatabaseConnection,
+    ) {
+        let full_batch = {
+            let mut pending = self.pending.lock().await;
+            pending.push((audit, in_flight));
+            if pending.len() < self.batch_size {
+                return;
+            }
+            std::mem::take(&mut *pending)
+        };
+        write_batch(full_batch, conn).await;
+    }
+
+    /// Writes every buffered audit.
+    pub async fn flush(&self, conn: &DatabaseConnection) {
+        let batch = std::mem::take(&mut *self.pending.lock().await);
+        write_batch(batch, conn).await;
+    }
+
+    /// Number of audits waiting to be written.
+    pub async fn pending(&self) -> usize {
+        self.pending.lock().await.len()
+    }
+}
+
+/// Inserts a batch of audits, logging rather than returning a failure as the
+/// audits have already been performed.
+async fn write_batch(batch: Vec<PendingAudit>, conn: &DatabaseConnection) {
+    if batch.is_empty() {
+        return;
+    }
+    let size = batch.len();
+    let (audits, in_flight): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+    match content_audit::create_many(audits, conn).await {
+        Ok(_) => debug!(audits.written = size, "Wrote batch of audits."),
+        Err(e) => error!(audits.lost = size, err=?e, "Could not write batch of audits to db."),
+    }
+    // Only now may the content be audited again.
+    drop(in_flight);
+}
+
+/// Writes the buffered audits every flush interval, so that they are stored
+/// promptly when audits are slow to fill a batch.
+pub async fn periodically_flush_audits(writer: Arc<AuditWriter>, conn: DatabaseConnection) {
+    let mut interval = interval(writer.flush_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        writer.flush(&conn).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::{
+        client_info, content,
+        content_audit::{AuditOutcome, SelectionStrategy},
+        node,
+    };
+    use ethportal_api::{types::node_id::NodeId, BlockHeaderKey, HistoryContentKey};
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{ActiveModelTrait, Database, EntityTrait, PaginatorTrait, Set};
+
+    use super::*;
+
+    async fn sample_audit(conn: &DatabaseConnection) -> content_audit::ActiveModel {
+        let content_key = HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+            block_hash: [1; 32],
+        });
+        let content = content::get_or_create(&content_key, conn).await.unwrap();
+        let client_info = client_info::ActiveModel {
+            id: sea_orm::NotSet,
+            version_info: Set("trin v0.1.0".to_owned()),
+        }
+        .insert(conn)
+        .await
+        .unwrap();
+        let node = node::get_or_create(NodeId::random(), conn).await.unwrap();
+        content_audit::new_audit(
+            content.id,
+            client_info.id,
+            node.id,
+            AuditOutcome::Success,
+            SelectionStrategy::Latest,
+            "".to_owned(),
+            Some(10),
+            None,
+            None,
+            None,
+        )
+    }
+
+    /// Tests that audits are written once a batch fills, and the rest when flushed.
+    #[tokio::test]
+    async fn test_audit_writer() {
+        let conn = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&conn, None).await.unwrap();
+        let audit = sample_audit(&conn).await;
+        let stored = || content_audit::Entity::find().count(&conn);
+
+        let writer = AuditWriter::new(2, Duration::from_secs(60));
+        writer.push(audit.clone(), None, &conn).await;
+        assert_eq!(writer.pending().await, 1);
+        assert_eq!(stored().await.unwrap(), 0);
+
+        writer.push(audit.clone(), None, &conn).await;
+        assert_eq!(writer.pending().await, 0);
+        assert_eq!(stored().await.unwrap(), 2);
+
+        writer.push(audit, None, &conn).await;
+        writer.flush(&conn).await;
+        assert_eq!(writer.pending().await, 0);
+        assert_eq!(stored().await.unwrap(), 3);
+    }
+}
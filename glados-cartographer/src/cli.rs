@@ -21,7 +21,13 @@ pub struct Args {
     pub http_url: Option<Url>,
     #[arg(short, long)]
     pub transport: TransportType,
-    #[arg(short = 'i', long, default_value = DEFAULT_CENSUS_INTERVAL)]
+    /// Seconds between the start of each network census.
+    #[arg(
+        short = 'i',
+        long,
+        default_value = DEFAULT_CENSUS_INTERVAL,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
     pub census_interval: u64,
     #[arg(short, long, default_value = DEFAULT_CONCURRENCY)]
     pub concurrency: usize,
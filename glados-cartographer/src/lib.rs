@@ -8,7 +8,7 @@ use ethportal_api::{generate_random_remote_enr, jsonrpsee::http_client::HttpClie
 use ethportal_api::{Enr, NodeId};
 use primitive_types::U256;
 use sea_orm::DatabaseConnection;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration as StdDuration;
 use tokio::{
@@ -20,13 +20,18 @@ use tokio::{
 };
 use tracing::{debug, error, info, warn};
 
-use entity::{census, census_node, record};
-use glados_core::jsonrpc::TransportConfig;
+use entity::{census, census_node, census_result, node, record, routing_table_snapshot};
+use glados_core::jsonrpc::{
+    PortalApi, TransportConfig, DEFAULT_MAX_RETRIES, DEFAULT_REQUEST_TIMEOUT,
+};
 
 use crate::cli::TransportType;
 
 pub mod cli;
 
+/// ENR key under which Portal clients advertise their software and version.
+const ENR_CLIENT_KEY: &str = "c";
+
 /// Configuration created from CLI arguments.
 #[derive(Clone, Debug)]
 pub struct CartographerConfig {
@@ -83,10 +88,60 @@ async fn orchestrate_dht_census(config: CartographerConfig, conn: DatabaseConnec
     loop {
         interval.tick().await;
 
+        snapshot_routing_table(&config, &conn).await;
         perform_dht_census(config.clone(), conn.clone()).await;
     }
 }
 
+/// Stores how many entries the Portal node holds in each routing table bucket.
+///
+/// Taken once per census, so the size of the node's view of the network can be
+/// followed over time.
+async fn snapshot_routing_table(config: &CartographerConfig, conn: &DatabaseConnection) {
+    let client_url = match &config.transport {
+        TransportConfig::HTTP(http_url) => http_url.to_string(),
+        TransportConfig::IPC(path) => format!("ipc:///{}", path.display()),
+    };
+    let api = PortalApi {
+        client_url,
+        request_timeout: DEFAULT_REQUEST_TIMEOUT,
+        max_retries: DEFAULT_MAX_RETRIES,
+    };
+    let routing_table = match api.get_routing_table_info().await {
+        Ok(routing_table) => routing_table,
+        Err(err) => {
+            error!(err=?err, "Error reading routing table from Portal node");
+            return;
+        }
+    };
+
+    let mut bucket_counts: BTreeMap<u16, u32> = BTreeMap::new();
+    let mut connected_peers = 0;
+    for entry in &routing_table.buckets {
+        *bucket_counts.entry(entry.log_distance).or_default() += 1;
+        if entry.status.eq_ignore_ascii_case("connected") {
+            connected_peers += 1;
+        }
+    }
+
+    match routing_table_snapshot::create(
+        routing_table.localKey.as_bytes().to_vec(),
+        &bucket_counts,
+        connected_peers,
+        conn,
+    )
+    .await
+    {
+        Ok(snapshot) => info!(
+            snapshot.id = snapshot.id,
+            total_peers = snapshot.total_peers,
+            connected_peers = snapshot.connected_peers,
+            "Saved routing table snapshot"
+        ),
+        Err(err) => error!(err=?err, "Error saving routing table snapshot to database"),
+    }
+}
+
 struct DHTCensusRecord {
     enr: Enr,
     record_id: i32,
@@ -438,12 +493,37 @@ async fn do_liveliness_check(
         }
     };
 
+    // Record the client software advertised in the ENR, since peers can't be asked directly
+    if let Some(client_version) = enr.get(ENR_CLIENT_KEY) {
+        let client_version = String::from_utf8_lossy(client_version);
+        if let Err(err) =
+            node::set_client_version(record_model.node_id, &client_version, &conn).await
+        {
+            error!(node_id=?H256::from(enr.node_id().raw()), err=?err, "Error saving client version to database");
+        }
+    }
+
     // Perform liviliness check
     debug!(node_id=?H256::from(enr.node_id().raw()), "Liveliness check");
 
-    match client.ping(enr.to_owned()).await {
+    let ping_start = std::time::Instant::now();
+    let ping_result = client.ping(enr.to_owned()).await;
+    let rtt_ms = ping_result
+        .as_ref()
+        .ok()
+        .map(|_| i32::try_from(ping_start.elapsed().as_millis()).unwrap_or(i32::MAX));
+    if let Err(err) =
+        census_result::create(record_model.node_id, ping_result.is_ok(), rtt_ms, &conn).await
+    {
+        error!(node_id=?H256::from(enr.node_id().raw()), err=?err, "Error saving census result to database");
+    }
+    if let Err(err) = node::record_ping(record_model.node_id, ping_result.is_ok(), &conn).await {
+        error!(node_id=?H256::from(enr.node_id().raw()), err=?err, "Error saving node liveness to database");
+    }
+
+    match ping_result {
         Ok(pong_info) => {
-            debug!(node_id=?H256::from(enr.node_id().raw()), "Liveliness passed");
+            debug!(node_id=?H256::from(enr.node_id().raw()), rtt_ms=?rtt_ms, "Liveliness passed");
 
             // Mark node as known to be alive
             census
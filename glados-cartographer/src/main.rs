@@ -3,6 +3,7 @@ use sea_orm::Database;
 use tracing::{debug, info};
 
 use glados_cartographer::{run_glados_cartographer, CartographerConfig};
+use glados_core::pool::redact_database_url;
 use migration::{Migrator, MigratorTrait};
 
 #[tokio::main]
@@ -22,15 +23,13 @@ async fn main() -> Result<()> {
     //
     // Database Connection
     //
-    debug!(
-        database_url = &config.database_url,
-        "Connecting to database"
-    );
+    let redacted_url = redact_database_url(&config.database_url);
+    debug!(database_url = %redacted_url, "Connecting to database");
 
     let conn = Database::connect(&config.database_url).await?;
 
     info!(
-        database_url = &config.database_url,
+        database_url = %redacted_url,
         "database connection established"
     );
 
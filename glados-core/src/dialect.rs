@@ -0,0 +1,135 @@
+//! SQL fragments that are written differently for SQLite and Postgres.
+//!
+//! Queries are built with sea-query where possible, which takes care of the
+//! backend. The fragments here are the raw SQL that it cannot express, kept in
+//! one place so that each query reads the same on both backends.
+use sea_orm::{ConnectionTrait, DbBackend};
+
+/// Returns true if the database URL opens an in-memory SQLite database.
+///
+/// Each connection to an in-memory database sees its own empty database, so
+/// these must be opened with a single connection.
+pub fn is_in_memory_sqlite(database_url: &str) -> bool {
+    database_url.starts_with("sqlite:") && database_url.contains(":memory:")
+}
+
+/// Raw SQL for the backend of a database connection.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Dialect {
+    backend: DbBackend,
+}
+
+impl Dialect {
+    pub fn new(backend: DbBackend) -> Self {
+        Dialect { backend }
+    }
+
+    pub fn of(conn: &impl ConnectionTrait) -> Self {
+        Dialect::new(conn.get_database_backend())
+    }
+
+    fn is_sqlite(&self) -> bool {
+        self.backend == DbBackend::Sqlite
+    }
+
+    /// The first byte of a binary column, as two lowercase hex digits.
+    pub fn first_byte_hex(&self, column: &str) -> String {
+        if self.is_sqlite() {
+            format!("LOWER(HEX(SUBSTR({column}, 1, 1)))")
+        } else {
+            format!("ENCODE(SUBSTRING({column} FROM 1 FOR 1), 'hex')")
+        }
+    }
+
+    /// The last two bytes of a 32 byte binary column, read as a big-endian number.
+    pub fn last_two_bytes(&self, column: &str) -> String {
+        if self.is_sqlite() {
+            // SQLite cannot read a byte as a number, so each hex digit is looked up instead.
+            let digits: Vec<String> = (1..=4)
+                .map(|n| {
+                    format!("(INSTR('0123456789ABCDEF', SUBSTR(HEX(SUBSTR({column}, 31, 2)), {n}, 1)) - 1)")
+                })
+                .collect();
+            format!(
+                "({} * 4096 + {} * 256 + {} * 16 + {})",
+                digits[0], digits[1], digits[2], digits[3]
+            )
+        } else {
+            format!("(GET_BYTE({column}, 30) * 256 + GET_BYTE({column}, 31))")
+        }
+    }
+
+    /// The start of the `seconds` wide bucket holding a timestamp column, in seconds
+    /// since the unix epoch.
+    pub fn epoch_bucket(&self, column: &str, seconds: i64) -> String {
+        if self.is_sqlite() {
+            format!("(CAST(STRFTIME('%s', {column}) AS INTEGER) / {seconds}) * {seconds}")
+        } else {
+            format!("CAST(FLOOR(EXTRACT(EPOCH FROM {column}) / {seconds}) * {seconds} AS BIGINT)")
+        }
+    }
+
+    /// A timestamp column moved forward by the number of seconds in another column.
+    pub fn add_seconds(&self, timestamp: &str, seconds: &str) -> String {
+        if self.is_sqlite() {
+            format!(
+                "STRFTIME('%Y-%m-%dT%H:%M:%S.%f', DATETIME({timestamp}, '+' || {seconds} || ' seconds'))"
+            )
+        } else {
+            format!("{timestamp} + {seconds} * interval '1 second'")
+        }
+    }
+
+    /// A binary column read as UTF-8 text.
+    pub fn bytes_as_text(&self, column: &str) -> String {
+        if self.is_sqlite() {
+            format!("CAST({column} AS TEXT)")
+        } else {
+            format!("convert_from({column}, 'UTF8')")
+        }
+    }
+
+    /// The `fraction` percentile of a column over each group, interpolating between
+    /// values, or `None` if the backend cannot compute percentiles.
+    pub fn percentile(&self, fraction: f64, column: &str) -> Option<String> {
+        if self.is_sqlite() {
+            None
+        } else {
+            Some(format!(
+                "PERCENTILE_CONT({fraction}) WITHIN GROUP (ORDER BY {column})"
+            ))
+        }
+    }
+
+    /// The placeholder for the `n`th value bound to a raw statement, counting from 1.
+    pub fn placeholder(&self, n: usize) -> String {
+        if self.is_sqlite() {
+            format!("?{n}")
+        } else {
+            format!("${n}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("sqlite::memory:", true)]
+    #[case("sqlite://:memory:", true)]
+    #[case("sqlite:////tmp/glados.sqlite", false)]
+    #[case("postgres://localhost/glados", false)]
+    fn test_is_in_memory_sqlite(#[case] url: &str, #[case] expected: bool) {
+        assert_eq!(is_in_memory_sqlite(url), expected);
+    }
+
+    #[rstest]
+    #[case(DbBackend::Sqlite, "?2")]
+    #[case(DbBackend::Postgres, "$2")]
+    fn test_placeholder(#[case] backend: DbBackend, #[case] expected: &str) {
+        assert_eq!(Dialect::new(backend).placeholder(2), expected);
+    }
+}
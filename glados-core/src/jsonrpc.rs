@@ -13,14 +13,14 @@ use jsonrpsee::{
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{
-    json,
     value::{to_raw_value, RawValue},
     Value,
 };
 
 use ethportal_api::utils::bytes::{hex_decode, hex_encode, ByteUtilsError};
 use thiserror::Error;
-use tracing::error;
+use tokio::time::sleep;
+use tracing::{error, warn};
 use url::Url;
 
 use ethportal_api::types::enr::Enr;
@@ -38,9 +38,19 @@ pub enum Transport {
     IPC(IpcClientManager),
 }
 
+/// Time allowed for a Portal node to answer a request when no timeout is configured.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+/// Times a request that fails at the transport level is retried when no limit is configured.
+pub const DEFAULT_MAX_RETRIES: u8 = 1;
+/// Wait before the first retry of a request to a Portal node, doubled for each retry.
+const INITIAL_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
 #[derive(Clone, Debug)]
 pub struct PortalApi {
     pub client_url: String,
+    pub request_timeout: Duration,
+    /// Times a request that fails at the transport level is retried over a new connection.
+    pub max_retries: u8,
 }
 
 #[derive(Clone, Debug)]
@@ -72,6 +82,9 @@ pub enum JsonRpcError {
     #[error("HTTP client error: {0}")]
     HttpClient(String),
 
+    #[error("transport error: {0}")]
+    Transport(String),
+
     #[cfg(unix)]
     #[error("IPC client error")]
     IpcClient(#[from] IpcError),
@@ -128,6 +141,13 @@ pub enum JsonRpcError {
 
     #[error("Query completed without finding content")]
     ContentNotFound { trace: Option<String> },
+
+    /// The node answered with a JSON-RPC error other than content not found.
+    #[error("JSON-RPC error {code}: {message}")]
+    Rpc { code: i32, message: String },
+
+    #[error("request timed out")]
+    Timeout,
 }
 
 impl From<jsonrpsee::core::error::Error> for JsonRpcError {
@@ -138,6 +158,20 @@ impl From<jsonrpsee::core::error::Error> for JsonRpcError {
                     trace: error.data().map(|data| data.to_string()),
                 };
             }
+            return JsonRpcError::Rpc {
+                code: error.code(),
+                message: error.message().to_string(),
+            };
+        }
+
+        if let jsonrpsee::core::error::Error::RequestTimeout = e {
+            return JsonRpcError::Timeout;
+        }
+
+        if let jsonrpsee::core::error::Error::Transport(_)
+        | jsonrpsee::core::error::Error::RestartNeeded(_) = e
+        {
+            return JsonRpcError::Transport(e.to_string());
         }
 
         // Fallback to the generic HttpClient error variant if no match
@@ -145,6 +179,50 @@ impl From<jsonrpsee::core::error::Error> for JsonRpcError {
     }
 }
 
+impl JsonRpcError {
+    /// Whether the error came from the connection to the node rather than the node's
+    /// response, in which case the request may succeed over a new connection.
+    pub fn is_transport(&self) -> bool {
+        match self {
+            JsonRpcError::Transport(_) | JsonRpcError::IO(_) => true,
+            #[cfg(unix)]
+            JsonRpcError::IpcClient(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Why a request to a Portal node failed, grouped by where it went wrong.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum PortalClientError {
+    /// The node could not be reached, or the connection failed during the request.
+    #[error("transport error: {0}")]
+    Transport(String),
+
+    /// The node did not answer within the request timeout.
+    #[error("request timed out")]
+    Timeout,
+
+    /// The node answered with a JSON-RPC error.
+    #[error("JSON-RPC error {code}: {message}")]
+    Rpc { code: i32, message: String },
+
+    /// The node answered, but the response could not be read.
+    #[error("invalid response: {0}")]
+    InvalidResponse(String),
+}
+
+impl From<JsonRpcError> for PortalClientError {
+    fn from(err: JsonRpcError) -> Self {
+        match err {
+            JsonRpcError::Timeout => PortalClientError::Timeout,
+            JsonRpcError::Rpc { code, message } => PortalClientError::Rpc { code, message },
+            err if err.is_transport() => PortalClientError::Transport(err.to_string()),
+            err => PortalClientError::InvalidResponse(err.to_string()),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct PortalRpcError {
     pub code: Value,
@@ -204,10 +282,30 @@ pub struct Content {
     pub raw: Vec<u8>,
 }
 
+/// The response of a single node to a find content request.
+pub enum FindContentResult {
+    /// The node had the content.
+    Content(Content),
+    /// The node did not have the content and returned nodes closer to it.
+    ClosestNodes(Vec<Enr>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FindContentResultRaw {
+    Content { content: String },
+    Enrs { enrs: Vec<String> },
+}
+
 impl PortalClient {
-    pub async fn from(portal_client_url: String) -> Result<Self, JsonRpcError> {
+    pub async fn from(
+        portal_client_url: String,
+        request_timeout: Duration,
+    ) -> Result<Self, JsonRpcError> {
         let api = PortalApi {
             client_url: portal_client_url.clone(),
+            request_timeout,
+            max_retries: DEFAULT_MAX_RETRIES,
         };
 
         let client_info = api.get_client_version().await?;
@@ -234,12 +332,47 @@ impl PortalClient {
 }
 
 impl PortalApi {
+    /// Sends a request to the Portal node.
+    ///
+    /// A new connection is made for each request. If the connection fails, for
+    /// example because the node restarted, the request is sent again over a fresh
+    /// connection, up to `max_retries` times with exponential backoff. Errors in
+    /// the node's response are returned without retrying.
     pub async fn make_request(
         &self,
         method: &str,
         params: Option<Vec<Box<RawValue>>>,
     ) -> Result<String, JsonRpcError> {
-        let transport = PortalApi::parse_client_url(self.client_url.clone()).await?;
+        let mut backoff = INITIAL_RETRY_BACKOFF;
+        let mut attempt = 0;
+        loop {
+            match self.send_request(method, params.clone()).await {
+                Err(err) if err.is_transport() && attempt < self.max_retries => {
+                    attempt += 1;
+                    warn!(
+                        client.url = self.client_url,
+                        method,
+                        attempt,
+                        max_retries = self.max_retries,
+                        backoff_ms = backoff.as_millis() as u64,
+                        err=?err,
+                        "Transport error from Portal node, reconnecting."
+                    );
+                    sleep(backoff).await;
+                    backoff *= 2;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn send_request(
+        &self,
+        method: &str,
+        params: Option<Vec<Box<RawValue>>>,
+    ) -> Result<String, JsonRpcError> {
+        let transport =
+            PortalApi::parse_client_url(self.client_url.clone(), self.request_timeout).await?;
         // jsonrpsee requires the conversion of `Option<Vec<Box<RawValue>>>` to `ArrayParams`
         let array_params: ArrayParams = match params {
             Some(json_params) => {
@@ -315,13 +448,7 @@ impl PortalApi {
             input: key.to_string(),
         })?;
         match self.make_request(method, Some(vec![param])).await {
-            Ok(response) => {
-                let query_result = serde_json::from_value::<QueryResult>(json!(response))
-                    .map_err(JsonRpcError::Malformed)?;
-
-                let content_raw = hex_decode(&query_result.content)?;
-                Ok(Some(Content { raw: content_raw }))
-            }
+            Ok(response) => Ok(Some(parse_query_response(&response)?)),
             Err(err) => match err {
                 JsonRpcError::ContentNotFound { trace: _ } => Ok(None),
                 _ => Err(err),
@@ -355,19 +482,66 @@ impl PortalApi {
         }
     }
 
-    pub async fn parse_client_url(client_url: String) -> Result<Transport, JsonRpcError> {
-        let http_prefix = "http://";
+    /// Asks a single node for content, without a recursive lookup.
+    ///
+    /// Distinguishes the node having the content from it returning closer nodes,
+    /// which shows how far a lookup has to go.
+    pub async fn find_content<T: OverlayContentKey>(
+        self,
+        enr: &Enr,
+        content_key: &T,
+    ) -> Result<FindContentResult, JsonRpcError> {
+        let params = Some(vec![
+            to_raw_value(&enr.to_base64())?,
+            to_raw_value(&hex_encode(content_key.to_bytes()))?,
+        ]);
+        let response = self
+            .make_request("portal_historyFindContent", params)
+            .await?;
+        parse_find_content_response(&response)
+    }
+
+    /// Looks up the ENR the node has stored for the given node id.
+    pub async fn get_enr(self, node_id: &H256) -> Result<Enr, JsonRpcError> {
+        let params = Some(vec![to_raw_value(&hex_encode(node_id.as_bytes()))?]);
+        let response = self.make_request("portal_historyGetEnr", params).await?;
+        parse_enr_response(&response)
+    }
+
+    /// Adds an ENR to the node's routing table, returning whether it was added.
+    pub async fn add_enr(self, enr: &Enr) -> Result<bool, JsonRpcError> {
+        let params = Some(vec![to_raw_value(&enr.to_base64())?]);
+        let response = self.make_request("portal_historyAddEnr", params).await?;
+        serde_json::from_str(&response).map_err(|e| JsonRpcError::InvalidJson {
+            source: e,
+            input: response.to_string(),
+        })
+    }
+
+    /// Selects the transport from the URL scheme: `http://` or `https://` for
+    /// JSON-RPC over HTTP, `ipc:///` followed by a socket path for IPC.
+    pub async fn parse_client_url(
+        client_url: String,
+        request_timeout: Duration,
+    ) -> Result<Transport, JsonRpcError> {
+        let http_prefixes = ["http://", "https://"];
         let ipc_prefix = "ipc:///";
-        if client_url.strip_prefix(http_prefix).is_some() {
+        if http_prefixes
+            .iter()
+            .any(|prefix| client_url.starts_with(prefix))
+        {
             Ok(Transport::HTTP(HttpClientManager {
                 client: HttpClientBuilder::default()
-                    .request_timeout(Duration::from_secs(120))
+                    .request_timeout(request_timeout)
                     .build(client_url)?,
             }))
         } else if let Some(ipc_path) = client_url.strip_prefix(ipc_prefix) {
             #[cfg(unix)]
             return Ok(Transport::IPC(IpcClientManager {
-                client: IpcClientBuilder::default().build(ipc_path).await?,
+                client: IpcClientBuilder::default()
+                    .request_timeout(request_timeout)
+                    .build(ipc_path)
+                    .await?,
             }));
             #[cfg(windows)]
             panic!("Reth doesn't support Unix Domain Sockets IPC for windows, use http")
@@ -377,6 +551,51 @@ impl PortalApi {
     }
 }
 
+fn parse_query_response(response: &str) -> Result<Content, JsonRpcError> {
+    let query_result: QueryResult =
+        serde_json::from_str(response).map_err(JsonRpcError::Malformed)?;
+    Ok(Content {
+        raw: hex_decode(&query_result.content)?,
+    })
+}
+
+fn parse_find_content_response(response: &str) -> Result<FindContentResult, JsonRpcError> {
+    let result_raw: FindContentResultRaw =
+        serde_json::from_str(response).map_err(|e| JsonRpcError::InvalidJson {
+            source: e,
+            input: response.to_string(),
+        })?;
+    match result_raw {
+        FindContentResultRaw::Content { content } => Ok(FindContentResult::Content(Content {
+            raw: hex_decode(&content)?,
+        })),
+        FindContentResultRaw::Enrs { enrs } => {
+            let enrs: Result<Vec<Enr>, JsonRpcError> = enrs
+                .iter()
+                .map(|enr_string| {
+                    Enr::from_str(enr_string).map_err(|e| JsonRpcError::InvalidEnr {
+                        error: e,
+                        enr_string: enr_string.to_string(),
+                    })
+                })
+                .collect();
+            Ok(FindContentResult::ClosestNodes(enrs?))
+        }
+    }
+}
+
+fn parse_enr_response(response: &str) -> Result<Enr, JsonRpcError> {
+    let enr_string: String =
+        serde_json::from_str(response).map_err(|e| JsonRpcError::InvalidJson {
+            source: e,
+            input: response.to_string(),
+        })?;
+    Enr::from_str(&enr_string).map_err(|e| JsonRpcError::InvalidEnr {
+        error: e,
+        enr_string,
+    })
+}
+
 fn parse_routing_table_entry(
     local_node_id: &H256,
     raw_node_id: &str,
@@ -419,7 +638,8 @@ fn distance_log2(distance: U256) -> Result<u16, JsonRpcError> {
     }
 }
 
-fn strip_quotes(client_info: String) -> String {
+/// Removes the JSON quotes around a client version string.
+pub fn strip_quotes(client_info: String) -> String {
     if client_info.starts_with('"') && client_info.ends_with('"') {
         let mut chars = client_info.chars();
         chars.next();
@@ -433,8 +653,65 @@ fn strip_quotes(client_info: String) -> String {
 #[cfg(test)]
 mod tests {
 
-    use super::strip_quotes;
+    use super::{
+        parse_enr_response, parse_find_content_response, parse_query_response, strip_quotes,
+        FindContentResult, JsonRpcError, PortalApi, PortalClientError,
+    };
+    use ethportal_api::{generate_random_remote_enr, BlockHeaderKey, HistoryContentKey};
+    use jsonrpsee::types::ErrorObjectOwned;
     use rstest::rstest;
+    use serde_json::{json, Value};
+    use std::time::Duration;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// Starts a Portal node that answers one JSON-RPC request over HTTP with `result`.
+    async fn mock_node(result: Value) -> PortalApi {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            let body = loop {
+                let read = stream.read(&mut buf).await.unwrap();
+                if read == 0 {
+                    return;
+                }
+                request.extend_from_slice(&buf[..read]);
+                let text = String::from_utf8_lossy(&request);
+                let Some(header_end) = text.find("\r\n\r\n") else {
+                    continue;
+                };
+                let content_length: usize = text[..header_end]
+                    .lines()
+                    .find_map(|line| {
+                        let (name, value) = line.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse().unwrap())
+                    })
+                    .unwrap();
+                if request.len() >= header_end + 4 + content_length {
+                    break request[header_end + 4..header_end + 4 + content_length].to_vec();
+                }
+            };
+            let request: Value = serde_json::from_slice(&body).unwrap();
+            let response =
+                json!({"jsonrpc": "2.0", "id": request["id"], "result": result}).to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{response}",
+                response.len()
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+        PortalApi {
+            client_url,
+            request_timeout: Duration::from_secs(5),
+            max_retries: 0,
+        }
+    }
 
     #[rstest]
     #[case("\"test\"", "test")]
@@ -442,4 +719,92 @@ mod tests {
     fn test_strip_quotes(#[case] original: String, #[case] expected: String) {
         assert_eq!(strip_quotes(original), expected);
     }
+
+    #[test]
+    fn test_query_response() {
+        // The JSON text of the result, as returned by `make_request`.
+        let response = json!({"content": "0x0102", "utpTransfer": false}).to_string();
+        assert_eq!(parse_query_response(&response).unwrap().raw, vec![1, 2]);
+        assert!(matches!(
+            parse_query_response("\"0x0102\""),
+            Err(JsonRpcError::Malformed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_content() {
+        let api = mock_node(json!({"content": "0x0102", "utpTransfer": false})).await;
+        let content_key = HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+            block_hash: [1; 32],
+        });
+        let content = api.get_content(&content_key).await.unwrap().unwrap();
+        assert_eq!(content.raw, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_find_content_response_with_content() {
+        let response = json!({"content": "0x0102", "utpTransfer": false}).to_string();
+        match parse_find_content_response(&response).unwrap() {
+            FindContentResult::Content(content) => assert_eq!(content.raw, vec![1, 2]),
+            FindContentResult::ClosestNodes(_) => panic!("expected content"),
+        }
+    }
+
+    #[test]
+    fn test_find_content_response_with_enrs() {
+        let enr = generate_random_remote_enr().1;
+        let response = json!({"enrs": [enr.to_base64()]}).to_string();
+        match parse_find_content_response(&response).unwrap() {
+            FindContentResult::Content(_) => panic!("expected closer nodes"),
+            FindContentResult::ClosestNodes(enrs) => assert_eq!(enrs, vec![enr]),
+        }
+    }
+
+    #[test]
+    fn test_find_content_response_malformed() {
+        let response = json!({"enrs": ["not an enr"]}).to_string();
+        assert!(parse_find_content_response(&response).is_err());
+        assert!(parse_find_content_response("true").is_err());
+    }
+
+    #[test]
+    fn test_get_enr_response() {
+        let enr = generate_random_remote_enr().1;
+        let response = json!(enr.to_base64()).to_string();
+        assert_eq!(parse_enr_response(&response).unwrap(), enr);
+    }
+
+    #[test]
+    fn test_portal_client_error_classification() {
+        let call = |code: i32, message: &str| {
+            jsonrpsee::core::error::Error::Call(ErrorObjectOwned::owned(code, message, None::<()>))
+        };
+
+        let not_found = JsonRpcError::from(call(-39001, "content not found"));
+        assert!(matches!(not_found, JsonRpcError::ContentNotFound { .. }));
+
+        let rpc = PortalClientError::from(JsonRpcError::from(call(-32601, "Method not found")));
+        assert_eq!(
+            rpc,
+            PortalClientError::Rpc {
+                code: -32601,
+                message: "Method not found".to_string()
+            }
+        );
+        assert_eq!(rpc.to_string(), "JSON-RPC error -32601: Method not found");
+
+        let timeout = JsonRpcError::from(jsonrpsee::core::error::Error::RequestTimeout);
+        assert_eq!(PortalClientError::from(timeout), PortalClientError::Timeout);
+
+        let transport = JsonRpcError::Transport("connection refused".to_string());
+        assert!(matches!(
+            PortalClientError::from(transport),
+            PortalClientError::Transport(_)
+        ));
+
+        assert!(matches!(
+            PortalClientError::from(JsonRpcError::Empty),
+            PortalClientError::InvalidResponse(_)
+        ));
+    }
 }
@@ -1,3 +1,8 @@
 pub mod db;
+pub mod dialect;
 pub mod jsonrpc;
+pub mod logging;
+pub mod metrics;
+pub mod pool;
 pub mod stats;
+pub mod trigger;
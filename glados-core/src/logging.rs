@@ -0,0 +1,48 @@
+use anyhow::{anyhow, Result};
+use clap::{Args, ValueEnum};
+use tracing_subscriber::EnvFilter;
+
+/// Filter used when neither `--log-level` nor `RUST_LOG` is given.
+const DEFAULT_LOG_LEVEL: &str = "info";
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, ValueEnum)]
+#[clap(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human readable lines.
+    #[default]
+    Pretty,
+    /// One JSON object per line, including span fields, for log ingestion.
+    Json,
+}
+
+/// Log output settings shared by the glados binaries.
+#[derive(Args, Clone, Debug, Default, Eq, PartialEq)]
+pub struct LogArgs {
+    #[arg(long, value_enum, default_value = "pretty", help = "log output format")]
+    pub log_format: LogFormat,
+    #[arg(
+        long,
+        help = "log filter, eg info or glados_audit=debug (defaults to RUST_LOG, then info)"
+    )]
+    pub log_level: Option<String>,
+}
+
+/// Installs the global tracing subscriber.
+pub fn init_logging(args: &LogArgs) -> Result<()> {
+    let filter = match &args.log_level {
+        Some(level) => EnvFilter::try_new(level)?,
+        None => {
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_LEVEL))
+        }
+    };
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match args.log_format {
+        LogFormat::Pretty => builder.try_init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .try_init(),
+    }
+    .map_err(|e| anyhow!("Could not install the log subscriber: {e}"))
+}
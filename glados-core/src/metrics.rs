@@ -0,0 +1,71 @@
+use chrono::{DateTime, TimeZone, Utc};
+use std::{
+    sync::atomic::{AtomicI64, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+/// Live state of the audit process that is not recorded in the database.
+///
+/// Shared behind an `Arc` so that it can be reported by glados-web when both
+/// run in the same process.
+#[derive(Debug, Default)]
+pub struct AuditMetrics {
+    /// Number of collated audit tasks waiting for a worker.
+    pub queue_depth: AtomicUsize,
+    /// Number of audit workers started.
+    pub workers: AtomicUsize,
+    /// Number of audit workers busy with an audit task.
+    pub active_workers: AtomicUsize,
+    /// Number of content keys from the database that could not be decoded and were skipped.
+    pub malformed_content_keys: AtomicUsize,
+    /// Number of selection periods skipped because the audit workers were behind.
+    pub backpressure_skips: AtomicUsize,
+    /// When the task most recently taken by a worker was selected, in milliseconds since the epoch.
+    pub last_taken_selected_at_ms: AtomicI64,
+}
+
+impl AuditMetrics {
+    /// Records that a worker took a task that was selected at the given time.
+    pub fn record_task_taken(&self, selected_at: DateTime<Utc>) {
+        self.last_taken_selected_at_ms
+            .store(selected_at.timestamp_millis(), Ordering::Relaxed);
+    }
+
+    /// Approximate age of the oldest audit task waiting for a worker.
+    ///
+    /// Workers take tasks in about the order they were selected, so the next task
+    /// was selected around when the task taken last was. Zero if nothing is waiting.
+    pub fn queue_lag(&self, now: DateTime<Utc>) -> Duration {
+        if self.queue_depth.load(Ordering::Relaxed) == 0 {
+            return Duration::ZERO;
+        }
+        let selected_at_ms = self.last_taken_selected_at_ms.load(Ordering::Relaxed);
+        match Utc.timestamp_millis_opt(selected_at_ms).single() {
+            Some(selected_at) if selected_at_ms > 0 => {
+                (now - selected_at).to_std().unwrap_or_default()
+            }
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_lag() {
+        let metrics = AuditMetrics::default();
+        let now = Utc::now();
+        // No task taken yet.
+        metrics.queue_depth.store(3, Ordering::Relaxed);
+        assert_eq!(metrics.queue_lag(now), Duration::ZERO);
+
+        metrics.record_task_taken(now - chrono::Duration::seconds(30));
+        assert_eq!(metrics.queue_lag(now), Duration::from_secs(30));
+
+        // Nothing waiting, so nothing is behind.
+        metrics.queue_depth.store(0, Ordering::Relaxed);
+        assert_eq!(metrics.queue_lag(now), Duration::ZERO);
+    }
+}
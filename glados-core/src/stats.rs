@@ -1,17 +1,22 @@
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display};
 
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use clap::ValueEnum;
 
 use entity::{
+    census_node,
     content::{self, SubProtocol},
-    content_audit::{self, AuditResult, SelectionStrategy},
+    content_audit::{self, AuditOutcome, AuditResult, SelectionStrategy},
+    record,
 };
 use sea_orm::{
-    sea_query::{Expr, IntoCondition},
-    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, JoinType, PaginatorTrait, QueryFilter,
-    QuerySelect, RelationTrait, Select,
+    sea_query::{Alias, DynIden, Expr, IntoCondition, Order, Query, SimpleExpr},
+    ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    FromQueryResult, JoinType, PaginatorTrait, QueryFilter, QuerySelect, RelationTrait, Select,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+use crate::dialect::Dialect;
 
 /// Generates a SeaORM select query for audits based on the provided filters.
 /// User can decide whether to retrieve or only count results.
@@ -53,7 +58,7 @@ pub fn filter_audits(filters: AuditFilters) -> Select<content_audit::Entity> {
             content_audit::Relation::Content
                 .def()
                 .on_condition(|_left, right| {
-                    Expr::cust("get_byte(content.content_key, 0) = 0x00")
+                    has_selector(right.clone(), 0x00)
                         .and(
                             Expr::col((right, content::Column::ProtocolId))
                                 .eq(SubProtocol::History),
@@ -66,7 +71,7 @@ pub fn filter_audits(filters: AuditFilters) -> Select<content_audit::Entity> {
             content_audit::Relation::Content
                 .def()
                 .on_condition(|_left, right| {
-                    Expr::cust("get_byte(content.content_key, 0) = 0x01")
+                    has_selector(right.clone(), 0x01)
                         .and(
                             Expr::col((right, content::Column::ProtocolId))
                                 .eq(SubProtocol::History),
@@ -79,7 +84,7 @@ pub fn filter_audits(filters: AuditFilters) -> Select<content_audit::Entity> {
             content_audit::Relation::Content
                 .def()
                 .on_condition(|_left, right| {
-                    Expr::cust("get_byte(content.content_key, 0) = 0x02")
+                    has_selector(right.clone(), 0x02)
                         .and(
                             Expr::col((right, content::Column::ProtocolId))
                                 .eq(SubProtocol::History),
@@ -90,13 +95,31 @@ pub fn filter_audits(filters: AuditFilters) -> Select<content_audit::Entity> {
     }
 }
 
+/// Matches content keys in the given table that start with the selector byte.
+///
+/// Compares against the range of keys with that first byte, which reads the
+/// same on every database backend.
+fn has_selector(table: DynIden, selector: u8) -> SimpleExpr {
+    let key = || Expr::col((table.clone(), content::Column::ContentKey));
+    let lower = key().gte(vec![selector]);
+    match selector.checked_add(1) {
+        Some(next) => lower.and(key().lt(vec![next])),
+        None => lower,
+    }
+}
+
 /// Calculates stats for the given set of audits over the given period.
+///
+/// Audits where the request to the node failed, with a transport error, timeout
+/// or JSON-RPC error, are left out, as they say nothing about whether the content
+/// is available.
 pub async fn get_audit_stats(
     filtered: Select<content_audit::Entity>,
     period: Period,
     conn: &DatabaseConnection,
 ) -> Result<AuditStats, DbErr> {
     let cutoff = period.cutoff_time();
+    let filtered = filtered.filter(excludes_request_failures());
 
     let new_content = content::Entity::find()
         .filter(content::Column::FirstAvailableAt.gt(cutoff))
@@ -143,6 +166,774 @@ pub async fn get_audit_stats(
     })
 }
 
+/// Counts all content and audits ever recorded.
+///
+/// Unlike [`get_audit_stats`], transport errors are counted as audits that did not pass.
+pub async fn get_totals(conn: &DatabaseConnection) -> Result<Totals, DbErr> {
+    let content = content::Entity::find().count(conn).await?;
+    let audits = content_audit::Entity::find().count(conn).await?;
+    let passes = content_audit::Entity::find()
+        .filter(content_audit::Column::Result.eq(AuditResult::Success))
+        .count(conn)
+        .await?;
+    Ok(Totals {
+        content,
+        audits,
+        passes,
+    })
+}
+
+/// Counts failed audits over the given period by their outcome, optionally only for
+/// the audits with a label.
+pub async fn get_failure_causes(
+    period: Period,
+    label: Option<&str>,
+    conn: &DatabaseConnection,
+) -> Result<FailureCauses, DbErr> {
+    let cutoff = period.cutoff_time();
+    let count_outcome = |outcome: AuditOutcome| {
+        content_audit::Entity::find()
+            .filter(content_audit::Column::CreatedAt.gt(cutoff))
+            .filter(content_audit::Column::Outcome.eq(outcome))
+            .filter(has_label(label))
+            .count(conn)
+    };
+    let not_found = count_outcome(AuditOutcome::NotFound).await? as u32;
+    let invalid_content = count_outcome(AuditOutcome::InvalidContent).await? as u32;
+    let invalid_proof = count_outcome(AuditOutcome::InvalidProof).await? as u32;
+    let transport_error = count_outcome(AuditOutcome::TransportError).await? as u32;
+    let timeout = count_outcome(AuditOutcome::Timeout).await? as u32;
+    let rpc_error = count_outcome(AuditOutcome::RpcError).await? as u32;
+
+    Ok(FailureCauses {
+        period,
+        not_found,
+        invalid_content,
+        invalid_proof,
+        transport_error,
+        timeout,
+        rpc_error,
+    })
+}
+
+#[derive(FromQueryResult)]
+struct ContentTypeRow {
+    selector: String,
+    total: i64,
+    passed: i64,
+}
+
+/// Counts audits and passes over the given period by the type of content audited,
+/// optionally only for the audits with a label.
+///
+/// The type is the selector, the first byte of the content key. Transport errors
+/// are left out, as in [`get_audit_stats`].
+pub async fn get_content_type_breakdown(
+    period: Period,
+    label: Option<&str>,
+    conn: &DatabaseConnection,
+) -> Result<ContentTypeBreakdown, DbErr> {
+    let builder = conn.get_database_backend();
+    let selector = Dialect::new(builder).first_byte_hex("content.content_key");
+    let passed = passed_audits_expr();
+    let query = Query::select()
+        .expr_as(Expr::cust(&selector), Alias::new("selector"))
+        .expr_as(
+            Expr::count(Expr::col((
+                content_audit::Entity,
+                content_audit::Column::Id,
+            ))),
+            Alias::new("total"),
+        )
+        .expr_as(Expr::cust(&passed), Alias::new("passed"))
+        .from(content_audit::Entity)
+        .inner_join(
+            content::Entity,
+            Expr::col((content_audit::Entity, content_audit::Column::ContentKey))
+                .equals((content::Entity, content::Column::Id)),
+        )
+        .and_where(content_audit::Column::CreatedAt.gt(period.cutoff_time()))
+        .cond_where(excludes_request_failures())
+        .cond_where(has_label(label))
+        .group_by_col(Alias::new("selector"))
+        .order_by(Alias::new("selector"), Order::Asc)
+        .to_owned();
+
+    let rows = ContentTypeRow::find_by_statement(builder.build(&query))
+        .all(conn)
+        .await?;
+    let types = rows
+        .into_iter()
+        .map(|row| ContentTypeStats {
+            content_type: content_type_name(&row.selector),
+            total: row.total,
+            passed: row.passed,
+        })
+        .collect();
+    Ok(ContentTypeBreakdown { period, types })
+}
+
+#[derive(FromQueryResult)]
+pub struct FailingContent {
+    pub content_key: Vec<u8>,
+    pub audits: i64,
+}
+
+/// Finds content that failed every audit over the given period, with at least
+/// `min_audits` audits.
+///
+/// Transport errors are left out, as in [`get_content_type_breakdown`]. The most
+/// audited content comes first.
+pub async fn get_failing_content(
+    period: Period,
+    min_audits: u64,
+    conn: &DatabaseConnection,
+) -> Result<Vec<FailingContent>, DbErr> {
+    let builder = conn.get_database_backend();
+    let audit_count = Expr::count(Expr::col((
+        content_audit::Entity,
+        content_audit::Column::Id,
+    )));
+    let query = Query::select()
+        .expr_as(
+            Expr::col((content::Entity, content::Column::ContentKey)),
+            Alias::new("content_key"),
+        )
+        .expr_as(audit_count.clone(), Alias::new("audits"))
+        .from(content_audit::Entity)
+        .inner_join(
+            content::Entity,
+            Expr::col((content_audit::Entity, content_audit::Column::ContentKey))
+                .equals((content::Entity, content::Column::Id)),
+        )
+        .and_where(content_audit::Column::CreatedAt.gt(period.cutoff_time()))
+        .cond_where(excludes_request_failures())
+        .group_by_col((content::Entity, content::Column::ContentKey))
+        .and_having(Expr::expr(Expr::cust(passed_audits_expr())).eq(0))
+        .and_having(Expr::expr(audit_count).gte(min_audits))
+        .order_by(Alias::new("audits"), Order::Desc)
+        .to_owned();
+
+    FailingContent::find_by_statement(builder.build(&query))
+        .all(conn)
+        .await
+}
+
+#[derive(FromQueryResult)]
+pub struct WorstContent {
+    pub content_key: Vec<u8>,
+    pub audits: i64,
+    pub failures: i64,
+}
+
+/// Finds the content with the highest share of failed audits since `cutoff`,
+/// optionally counting only the audits with a label.
+///
+/// Transport errors are left out, as in [`get_content_type_breakdown`]. Ties
+/// are broken by the number of failures, so content that failed more often
+/// comes first. Without a cutoff or label the audit counts kept on each content
+/// key are ranked, rather than counting the audits.
+pub async fn get_worst_content(
+    cutoff: Option<DateTime<Utc>>,
+    limit: u64,
+    label: Option<&str>,
+    conn: &DatabaseConnection,
+) -> Result<Vec<WorstContent>, DbErr> {
+    let builder = conn.get_database_backend();
+    let mut query = if cutoff.is_none() && label.is_none() {
+        let failed_audits = "(content.audit_count - content.audit_success_count)";
+        Query::select()
+            .expr_as(
+                Expr::col((content::Entity, content::Column::ContentKey)),
+                Alias::new("content_key"),
+            )
+            .expr_as(
+                Expr::cust("CAST(content.audit_count AS BIGINT)"),
+                Alias::new("audits"),
+            )
+            .expr_as(
+                Expr::cust(format!("CAST({failed_audits} AS BIGINT)")),
+                Alias::new("failures"),
+            )
+            .expr_as(
+                Expr::cust(format!(
+                    "CAST({failed_audits} AS DOUBLE PRECISION) / content.audit_count"
+                )),
+                Alias::new("failure_rate"),
+            )
+            .from(content::Entity)
+            .and_where(content::Column::AuditCount.gt(0))
+            .to_owned()
+    } else {
+        let failed_audits = format!("(COUNT(content_audit.id) - {})", passed_audits_expr());
+        let mut query = Query::select()
+            .expr_as(
+                Expr::col((content::Entity, content::Column::ContentKey)),
+                Alias::new("content_key"),
+            )
+            .expr_as(
+                Expr::count(Expr::col((
+                    content_audit::Entity,
+                    content_audit::Column::Id,
+                ))),
+                Alias::new("audits"),
+            )
+            .expr_as(Expr::cust(&failed_audits), Alias::new("failures"))
+            .expr_as(
+                Expr::cust(format!(
+                    "CAST({failed_audits} AS DOUBLE PRECISION) / COUNT(content_audit.id)"
+                )),
+                Alias::new("failure_rate"),
+            )
+            .from(content_audit::Entity)
+            .inner_join(
+                content::Entity,
+                Expr::col((content_audit::Entity, content_audit::Column::ContentKey))
+                    .equals((content::Entity, content::Column::Id)),
+            )
+            .cond_where(excludes_request_failures())
+            .cond_where(has_label(label))
+            .group_by_col((content::Entity, content::Column::Id))
+            .group_by_col((content::Entity, content::Column::ContentKey))
+            .to_owned();
+        if let Some(cutoff) = cutoff {
+            query.and_where(content_audit::Column::CreatedAt.gt(cutoff));
+        }
+        query
+    };
+    query
+        .order_by(Alias::new("failure_rate"), Order::Desc)
+        .order_by(Alias::new("failures"), Order::Desc)
+        .limit(limit);
+
+    WorstContent::find_by_statement(builder.build(&query))
+        .all(conn)
+        .await
+}
+
+/// Matches audits made with the given label, or every audit if there is none.
+pub fn has_label(label: Option<&str>) -> Condition {
+    match label {
+        Some(label) => Condition::all().add(content_audit::Column::Label.eq(label)),
+        None => Condition::all(),
+    }
+}
+
+/// Sums the audits in a group that passed.
+fn passed_audits_expr() -> String {
+    format!(
+        "CAST(SUM(CASE WHEN content_audit.result = {} THEN 1 ELSE 0 END) AS BIGINT)",
+        AuditResult::Success as i32
+    )
+}
+
+/// Matches audits where the node answered the content request, whatever it said.
+pub fn excludes_request_failures() -> Condition {
+    Condition::any()
+        .add(content_audit::Column::Outcome.is_null())
+        .add(content_audit::Column::Outcome.is_not_in(AuditOutcome::request_failures()))
+}
+
+/// Names the type of history content with the given hex encoded selector.
+fn content_type_name(selector: &str) -> String {
+    match u8::from_str_radix(selector, 16) {
+        Ok(0x00) => "Block headers".to_string(),
+        Ok(0x01) => "Block bodies".to_string(),
+        Ok(0x02) => "Receipts".to_string(),
+        Ok(0x03) => "Epoch accumulators".to_string(),
+        _ => format!("Unknown (0x{})", selector.to_lowercase()),
+    }
+}
+
+/// Width of the time buckets used for success rate history.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BucketInterval {
+    Minute,
+    #[default]
+    Hour,
+    Day,
+}
+
+impl BucketInterval {
+    fn total_seconds(&self) -> i64 {
+        match self {
+            BucketInterval::Minute => 60,
+            BucketInterval::Hour => 3600,
+            BucketInterval::Day => 86400,
+        }
+    }
+}
+
+/// Audit counts for a single time bucket.
+#[derive(Debug, Serialize)]
+pub struct SuccessRateBucket {
+    pub bucket_start: DateTime<Utc>,
+    pub total: i64,
+    pub passed: i64,
+}
+
+#[derive(FromQueryResult)]
+struct SuccessRateRow {
+    bucket_start: i64,
+    total: i64,
+    passed: i64,
+}
+
+/// Counts audits and passes created after `since`, grouped into time buckets,
+/// optionally counting only the audits with a label.
+///
+/// The grouping is done by the database, as the audit table is too large to load.
+/// Transport errors are left out, as in [`get_audit_stats`]. Buckets without
+/// audits are absent.
+pub async fn get_success_rate_history(
+    interval: BucketInterval,
+    since: DateTime<Utc>,
+    label: Option<&str>,
+    conn: &DatabaseConnection,
+) -> Result<Vec<SuccessRateBucket>, DbErr> {
+    let seconds = interval.total_seconds();
+    let builder = conn.get_database_backend();
+    let bucket_start = Dialect::new(builder).epoch_bucket("created_at", seconds);
+    let passed = format!(
+        "CAST(SUM(CASE WHEN result = {} THEN 1 ELSE 0 END) AS BIGINT)",
+        AuditResult::Success as i32
+    );
+    let query = Query::select()
+        .expr_as(Expr::cust(&bucket_start), Alias::new("bucket_start"))
+        .expr_as(
+            Expr::count(Expr::col(content_audit::Column::Id)),
+            Alias::new("total"),
+        )
+        .expr_as(Expr::cust(&passed), Alias::new("passed"))
+        .from(content_audit::Entity)
+        .and_where(content_audit::Column::CreatedAt.gt(since))
+        .cond_where(excludes_request_failures())
+        .cond_where(has_label(label))
+        .group_by_col(Alias::new("bucket_start"))
+        .order_by(Alias::new("bucket_start"), Order::Asc)
+        .to_owned();
+
+    let rows = SuccessRateRow::find_by_statement(builder.build(&query))
+        .all(conn)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            Some(SuccessRateBucket {
+                bucket_start: Utc.timestamp_opt(row.bucket_start, 0).single()?,
+                total: row.total,
+                passed: row.passed,
+            })
+        })
+        .collect())
+}
+
+/// Distinct nodes surveyed by the census over a window, in total and for each day.
+#[derive(Debug, Serialize)]
+pub struct NodeCounts {
+    pub total: i64,
+    /// Oldest day first. Days without a census are absent.
+    pub daily: Vec<NodeCountBucket>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NodeCountBucket {
+    pub bucket_start: DateTime<Utc>,
+    pub nodes: i64,
+}
+
+#[derive(FromQueryResult)]
+struct NodeCountRow {
+    bucket_start: i64,
+    nodes: i64,
+}
+
+#[derive(FromQueryResult)]
+struct NodeTotalRow {
+    nodes: i64,
+}
+
+/// Counts the distinct node ids surveyed by a census after `since`.
+///
+/// A node may be surveyed under several records as its ENR changes, so the records
+/// are counted by the node they belong to.
+///
+/// Censuses are not labelled, so with a label the nodes that served content to the
+/// audits with that label are counted instead.
+pub async fn get_distinct_node_counts(
+    since: DateTime<Utc>,
+    label: Option<&str>,
+    conn: &DatabaseConnection,
+) -> Result<NodeCounts, DbErr> {
+    let builder = conn.get_database_backend();
+    let dialect = Dialect::new(builder);
+    let (surveyed_nodes, bucket_start) = match label {
+        Some(label) => (
+            Query::select()
+                .expr_as(
+                    Expr::cust("COUNT(DISTINCT content_audit.served_by)"),
+                    Alias::new("nodes"),
+                )
+                .from(content_audit::Entity)
+                .and_where(content_audit::Column::ServedBy.is_not_null())
+                .and_where(content_audit::Column::CreatedAt.gt(since))
+                .and_where(content_audit::Column::Label.eq(label))
+                .to_owned(),
+            dialect.epoch_bucket(
+                "content_audit.created_at",
+                BucketInterval::Day.total_seconds(),
+            ),
+        ),
+        None => (
+            Query::select()
+                .expr_as(
+                    Expr::cust("COUNT(DISTINCT record.node_id)"),
+                    Alias::new("nodes"),
+                )
+                .from(census_node::Entity)
+                .inner_join(
+                    record::Entity,
+                    Expr::col((record::Entity, record::Column::Id))
+                        .equals((census_node::Entity, census_node::Column::RecordId)),
+                )
+                .and_where(
+                    Expr::col((census_node::Entity, census_node::Column::SurveyedAt)).gt(since),
+                )
+                .to_owned(),
+            dialect.epoch_bucket(
+                "census_node.surveyed_at",
+                BucketInterval::Day.total_seconds(),
+            ),
+        ),
+    };
+    let daily_query = surveyed_nodes
+        .clone()
+        .expr_as(Expr::cust(&bucket_start), Alias::new("bucket_start"))
+        .group_by_col(Alias::new("bucket_start"))
+        .order_by(Alias::new("bucket_start"), Order::Asc)
+        .to_owned();
+
+    let total = NodeTotalRow::find_by_statement(builder.build(&surveyed_nodes))
+        .one(conn)
+        .await?
+        .map_or(0, |row| row.nodes);
+    let daily = NodeCountRow::find_by_statement(builder.build(&daily_query))
+        .all(conn)
+        .await?
+        .into_iter()
+        .filter_map(|row| {
+            Some(NodeCountBucket {
+                bucket_start: Utc.timestamp_opt(row.bucket_start, 0).single()?,
+                nodes: row.nodes,
+            })
+        })
+        .collect();
+    Ok(NodeCounts { total, daily })
+}
+
+/// Number of recent audits sampled for latency percentiles when the database
+/// cannot compute them.
+const LATENCY_SAMPLE_SIZE: u64 = 10_000;
+
+/// Audit latency over a window, for all content or for each type of content.
+#[derive(Debug, Serialize)]
+pub struct AuditLatency {
+    /// Whether the percentiles were taken from a sample of the most recent audits.
+    pub sampled: bool,
+    pub summaries: Vec<LatencySummary>,
+}
+
+/// Latency of the audits of one type of content, or of all content.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct LatencySummary {
+    /// Absent when audits of every type are summarized together.
+    pub content_type: Option<String>,
+    pub audits: i64,
+    pub average_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+}
+
+#[derive(FromQueryResult)]
+struct LatencyRow {
+    selector: Option<String>,
+    audits: i64,
+    average_ms: Option<f64>,
+    p50_ms: Option<f64>,
+    p95_ms: Option<f64>,
+}
+
+#[derive(FromQueryResult)]
+struct LatencySample {
+    selector: Option<String>,
+    duration_ms: i32,
+}
+
+/// Summarizes how long audits created after `since` took, optionally for each type of
+/// content, and optionally only for the audits with a label.
+///
+/// Only audits with a recorded duration are counted, which leaves out requests that
+/// failed. Percentiles are computed by the database where it supports them. Otherwise
+/// they are computed here from the most recent audits, up to [`LATENCY_SAMPLE_SIZE`].
+pub async fn get_audit_latency(
+    since: DateTime<Utc>,
+    by_content_type: bool,
+    label: Option<&str>,
+    conn: &DatabaseConnection,
+) -> Result<AuditLatency, DbErr> {
+    let builder = conn.get_database_backend();
+    let dialect = Dialect::new(builder);
+    let selector = match by_content_type {
+        true => dialect.first_byte_hex("content.content_key"),
+        false => "NULL".to_owned(),
+    };
+    let duration = "content_audit.duration_ms";
+    let mut query = Query::select()
+        .expr_as(Expr::cust(&selector), Alias::new("selector"))
+        .from(content_audit::Entity)
+        .inner_join(
+            content::Entity,
+            Expr::col((content_audit::Entity, content_audit::Column::ContentKey))
+                .equals((content::Entity, content::Column::Id)),
+        )
+        .and_where(content_audit::Column::CreatedAt.gt(since))
+        .and_where(content_audit::Column::DurationMs.is_not_null())
+        .cond_where(has_label(label))
+        .to_owned();
+
+    let (Some(p50), Some(p95)) = (
+        dialect.percentile(0.5, duration),
+        dialect.percentile(0.95, duration),
+    ) else {
+        query
+            .expr_as(Expr::cust(duration), Alias::new("duration_ms"))
+            .order_by(
+                (content_audit::Entity, content_audit::Column::Id),
+                Order::Desc,
+            )
+            .limit(LATENCY_SAMPLE_SIZE);
+        let samples = LatencySample::find_by_statement(builder.build(&query))
+            .all(conn)
+            .await?;
+        let mut groups: BTreeMap<Option<String>, Vec<i32>> = BTreeMap::new();
+        for sample in samples {
+            groups
+                .entry(sample.selector)
+                .or_default()
+                .push(sample.duration_ms);
+        }
+        let summaries = groups
+            .into_iter()
+            .filter_map(|(selector, durations)| {
+                summarize_latency(selector.as_deref().map(content_type_name), durations)
+            })
+            .collect();
+        return Ok(AuditLatency {
+            sampled: true,
+            summaries,
+        });
+    };
+
+    query
+        .expr_as(
+            Expr::cust(format!("COUNT({duration})")),
+            Alias::new("audits"),
+        )
+        .expr_as(
+            Expr::cust(format!("CAST(AVG({duration}) AS DOUBLE PRECISION)")),
+            Alias::new("average_ms"),
+        )
+        .expr_as(Expr::cust(p50), Alias::new("p50_ms"))
+        .expr_as(Expr::cust(p95), Alias::new("p95_ms"));
+    if by_content_type {
+        query
+            .group_by_col(Alias::new("selector"))
+            .order_by(Alias::new("selector"), Order::Asc);
+    }
+    let rows = LatencyRow::find_by_statement(builder.build(&query))
+        .all(conn)
+        .await?;
+    let summaries = rows
+        .into_iter()
+        .filter(|row| row.audits > 0)
+        .map(|row| LatencySummary {
+            content_type: row.selector.as_deref().map(content_type_name),
+            audits: row.audits,
+            average_ms: row.average_ms.unwrap_or_default(),
+            p50_ms: row.p50_ms.unwrap_or_default(),
+            p95_ms: row.p95_ms.unwrap_or_default(),
+        })
+        .collect();
+    Ok(AuditLatency {
+        sampled: false,
+        summaries,
+    })
+}
+
+/// Largest power of two that content lengths are bucketed by, 16 MiB. Longer content
+/// is counted in this bucket.
+const MAX_CONTENT_LENGTH_BUCKET_POWER: u32 = 24;
+
+/// Audits of one type of content whose returned content was at least `min_bytes` long,
+/// and shorter than the next bucket.
+#[derive(Debug, Serialize)]
+pub struct ContentLengthBucket {
+    pub content_type: String,
+    pub min_bytes: i64,
+    pub audits: i64,
+}
+
+#[derive(FromQueryResult)]
+struct ContentLengthRow {
+    selector: String,
+    min_bytes: i64,
+    audits: i64,
+}
+
+/// The lower bound of the histogram bucket holding a content length column: zero, or
+/// the largest power of two no greater than the length.
+fn content_length_bucket(column: &str) -> String {
+    let cases: String = (0..=MAX_CONTENT_LENGTH_BUCKET_POWER)
+        .map(|power| {
+            let lower = match power {
+                0 => 0,
+                power => 1_i64 << (power - 1),
+            };
+            format!("WHEN {column} < {} THEN {lower} ", 1_i64 << power)
+        })
+        .collect();
+    format!(
+        "CAST(CASE {cases}ELSE {} END AS BIGINT)",
+        1_i64 << MAX_CONTENT_LENGTH_BUCKET_POWER
+    )
+}
+
+/// Counts the audits created after `since` by the length of the content returned,
+/// for each type of content, in buckets doubling in size.
+///
+/// Only audits that got content back are counted, whether or not it was valid.
+/// Buckets without audits are absent.
+pub async fn get_content_length_histogram(
+    since: DateTime<Utc>,
+    conn: &DatabaseConnection,
+) -> Result<Vec<ContentLengthBucket>, DbErr> {
+    let builder = conn.get_database_backend();
+    let selector = Dialect::new(builder).first_byte_hex("content.content_key");
+    let min_bytes = content_length_bucket("content_audit.content_length");
+    let query = Query::select()
+        .expr_as(Expr::cust(&selector), Alias::new("selector"))
+        .expr_as(Expr::cust(&min_bytes), Alias::new("min_bytes"))
+        .expr_as(
+            Expr::count(Expr::col((
+                content_audit::Entity,
+                content_audit::Column::Id,
+            ))),
+            Alias::new("audits"),
+        )
+        .from(content_audit::Entity)
+        .inner_join(
+            content::Entity,
+            Expr::col((content_audit::Entity, content_audit::Column::ContentKey))
+                .equals((content::Entity, content::Column::Id)),
+        )
+        .and_where(content_audit::Column::CreatedAt.gt(since))
+        .and_where(content_audit::Column::ContentLength.is_not_null())
+        .group_by_col(Alias::new("selector"))
+        .group_by_col(Alias::new("min_bytes"))
+        .order_by(Alias::new("selector"), Order::Asc)
+        .order_by(Alias::new("min_bytes"), Order::Asc)
+        .to_owned();
+    let rows = ContentLengthRow::find_by_statement(builder.build(&query))
+        .all(conn)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| ContentLengthBucket {
+            content_type: content_type_name(&row.selector),
+            min_bytes: row.min_bytes,
+            audits: row.audits,
+        })
+        .collect())
+}
+
+/// Summarizes a set of audit durations, or returns `None` if there are none.
+///
+/// Percentiles interpolate between the nearest durations, as Postgres does.
+fn summarize_latency(
+    content_type: Option<String>,
+    mut durations: Vec<i32>,
+) -> Option<LatencySummary> {
+    if durations.is_empty() {
+        return None;
+    }
+    durations.sort_unstable();
+    let percentile = |fraction: f64| {
+        let rank = fraction * (durations.len() - 1) as f64;
+        let lower = durations[rank.floor() as usize] as f64;
+        let upper = durations[rank.ceil() as usize] as f64;
+        lower + (upper - lower) * rank.fract()
+    };
+    let total: i64 = durations.iter().map(|&duration| duration as i64).sum();
+    Some(LatencySummary {
+        content_type,
+        audits: durations.len() as i64,
+        average_ms: total as f64 / durations.len() as f64,
+        p50_ms: percentile(0.5),
+        p95_ms: percentile(0.95),
+    })
+}
+
+pub struct Totals {
+    pub content: u64,
+    pub audits: u64,
+    pub passes: u64,
+}
+
+impl Totals {
+    pub fn pass_percent(&self) -> f32 {
+        match self.audits {
+            0 => 0.0,
+            audits => (self.passes as f32 / audits as f32) * 100.0,
+        }
+    }
+}
+
+pub struct FailureCauses {
+    pub period: Period,
+    pub not_found: u32,
+    pub invalid_content: u32,
+    pub invalid_proof: u32,
+    pub transport_error: u32,
+    pub timeout: u32,
+    pub rpc_error: u32,
+}
+
+pub struct ContentTypeBreakdown {
+    pub period: Period,
+    pub types: Vec<ContentTypeStats>,
+}
+
+/// Audit counts for a single type of content.
+pub struct ContentTypeStats {
+    pub content_type: String,
+    pub total: i64,
+    pub passed: i64,
+}
+
+impl ContentTypeStats {
+    pub fn failed(&self) -> i64 {
+        self.total - self.passed
+    }
+
+    pub fn pass_percent(&self) -> f32 {
+        match self.total {
+            0 => 0.0,
+            total => (self.passed as f32 / total as f32) * 100.0,
+        }
+    }
+}
+
 pub struct AuditStats {
     pub period: Period,
     pub new_content: u32,
@@ -154,6 +945,7 @@ pub struct AuditStats {
     pub audits_per_minute: u32,
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
 pub enum Period {
     Hour,
     Day,
@@ -220,3 +1012,382 @@ pub enum ContentTypeFilter {
     Bodies,
     Receipts,
 }
+
+#[cfg(test)]
+mod tests {
+    use entity::{census, node};
+    use ethportal_api::types::node_id::NodeId;
+    use ethportal_api::{BlockBodyKey, BlockHeaderKey, HistoryContentKey};
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{ActiveModelTrait, ActiveValue::NotSet, Database, Set};
+
+    use super::*;
+
+    async fn setup_database() -> DatabaseConnection {
+        let conn = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&conn, None).await.unwrap();
+        conn
+    }
+
+    async fn insert_audit(
+        content_key_id: i32,
+        result: AuditResult,
+        outcome: Option<AuditOutcome>,
+        label: Option<&str>,
+        conn: &DatabaseConnection,
+    ) -> content_audit::Model {
+        content_audit::ActiveModel {
+            id: NotSet,
+            content_key: Set(content_key_id),
+            created_at: Set(Utc::now()),
+            strategy_used: Set(Some(SelectionStrategy::Latest)),
+            result: Set(result),
+            trace: Set("".to_owned()),
+            client_info: Set(None),
+            node: Set(None),
+            duration_ms: Set(None),
+            outcome: Set(outcome),
+            served_by: Set(None),
+            error: Set(None),
+            label: Set(label.map(str::to_owned)),
+            closer_peer_responses: Set(None),
+            content_length: Set(None),
+            content_id_verified: Set(None),
+        }
+        .insert(conn)
+        .await
+        .unwrap()
+    }
+
+    /// Adds a header and a body with two audits of the header, one of the body and one
+    /// of the body labelled "staging", returning the header and body.
+    async fn insert_audited_content(conn: &DatabaseConnection) -> (content::Model, content::Model) {
+        let header = HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+            block_hash: [1; 32],
+        });
+        let body = HistoryContentKey::BlockBody(BlockBodyKey {
+            block_hash: [1; 32],
+        });
+        let header = content::get_or_create(&header, conn).await.unwrap();
+        let body = content::get_or_create(&body, conn).await.unwrap();
+        insert_audit(header.id, AuditResult::Success, None, None, conn).await;
+        insert_audit(
+            header.id,
+            AuditResult::Failure,
+            Some(AuditOutcome::NotFound),
+            None,
+            conn,
+        )
+        .await;
+        insert_audit(body.id, AuditResult::Success, None, None, conn).await;
+        insert_audit(
+            body.id,
+            AuditResult::Failure,
+            Some(AuditOutcome::InvalidContent),
+            Some("staging"),
+            conn,
+        )
+        .await;
+        (header, body)
+    }
+
+    async fn insert_record(node_id: i32, sequence_number: i32, conn: &DatabaseConnection) -> i32 {
+        record::ActiveModel {
+            id: NotSet,
+            node_id: Set(node_id),
+            raw: Set(format!("enr-{node_id}-{sequence_number}")),
+            sequence_number: Set(sequence_number),
+        }
+        .insert(conn)
+        .await
+        .unwrap()
+        .id
+    }
+
+    /// Tests that nodes are counted once however many of their records were surveyed.
+    #[tokio::test]
+    async fn test_get_distinct_node_counts() {
+        let conn = setup_database().await;
+        let at = |day, hour| Utc.with_ymd_and_hms(2024, 1, day, hour, 0, 0).unwrap();
+        let census = census::create(at(1, 0), 60, &conn).await.unwrap();
+        let mut nodes = vec![];
+        for _ in 0..3 {
+            nodes.push(
+                node::get_or_create(NodeId::random(), &conn)
+                    .await
+                    .unwrap()
+                    .id,
+            );
+        }
+        let first_record = insert_record(nodes[0], 1, &conn).await;
+        let second_record = insert_record(nodes[0], 2, &conn).await;
+        let other_node_record = insert_record(nodes[1], 1, &conn).await;
+        let old_record = insert_record(nodes[2], 1, &conn).await;
+        for (record_id, surveyed_at) in [
+            (first_record, at(2, 6)),
+            (second_record, at(2, 8)),
+            (second_record, at(3, 6)),
+            (other_node_record, at(3, 7)),
+            // Before the window.
+            (old_record, at(1, 6)),
+        ] {
+            census_node::create(census.id, record_id, 1u64.into(), surveyed_at, &conn)
+                .await
+                .unwrap();
+        }
+
+        let counts = get_distinct_node_counts(at(2, 0), None, &conn)
+            .await
+            .unwrap();
+        assert_eq!(counts.total, 2);
+        let daily: Vec<(DateTime<Utc>, i64)> = counts
+            .daily
+            .iter()
+            .map(|bucket| (bucket.bucket_start, bucket.nodes))
+            .collect();
+        assert_eq!(daily, vec![(at(2, 0), 1), (at(3, 0), 2)]);
+    }
+
+    /// Tests that audits served by a node are counted by their label, as censuses have none.
+    #[tokio::test]
+    async fn test_get_distinct_node_counts_with_label() {
+        let conn = setup_database().await;
+        let body = HistoryContentKey::BlockBody(BlockBodyKey {
+            block_hash: [1; 32],
+        });
+        let body = content::get_or_create(&body, &conn).await.unwrap();
+        let mut nodes = vec![];
+        for _ in 0..2 {
+            nodes.push(
+                node::get_or_create(NodeId::random(), &conn)
+                    .await
+                    .unwrap()
+                    .id,
+            );
+        }
+        for (served_by, label) in [
+            (Some(nodes[0]), Some("staging")),
+            (Some(nodes[0]), Some("staging")),
+            (Some(nodes[1]), Some("staging")),
+            (None, Some("staging")),
+            (Some(nodes[1]), None),
+        ] {
+            let mut audit: content_audit::ActiveModel =
+                insert_audit(body.id, AuditResult::Success, None, label, &conn)
+                    .await
+                    .into();
+            audit.served_by = Set(served_by);
+            audit.update(&conn).await.unwrap();
+        }
+
+        let since = Utc::now() - Duration::hours(1);
+        let counts = get_distinct_node_counts(since, Some("staging"), &conn)
+            .await
+            .unwrap();
+        assert_eq!(counts.total, 2);
+        assert_eq!(
+            counts.daily.iter().map(|bucket| bucket.nodes).sum::<i64>(),
+            2
+        );
+        let counts = get_distinct_node_counts(since, Some("production"), &conn)
+            .await
+            .unwrap();
+        assert_eq!(counts.total, 0);
+        assert!(counts.daily.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_content_type_breakdown() {
+        let conn = setup_database().await;
+        insert_audited_content(&conn).await;
+
+        let types = |breakdown: ContentTypeBreakdown| -> Vec<(String, i64, i64)> {
+            breakdown
+                .types
+                .into_iter()
+                .map(|t| (t.content_type, t.total, t.passed))
+                .collect()
+        };
+        let breakdown = get_content_type_breakdown(Period::Hour, None, &conn)
+            .await
+            .unwrap();
+        assert_eq!(
+            types(breakdown),
+            vec![
+                ("Block headers".to_owned(), 2, 1),
+                ("Block bodies".to_owned(), 2, 1)
+            ]
+        );
+        let breakdown = get_content_type_breakdown(Period::Hour, Some("staging"), &conn)
+            .await
+            .unwrap();
+        assert_eq!(types(breakdown), vec![("Block bodies".to_owned(), 1, 0)]);
+    }
+
+    #[tokio::test]
+    async fn test_get_failure_causes() {
+        let conn = setup_database().await;
+        insert_audited_content(&conn).await;
+
+        let causes = get_failure_causes(Period::Hour, None, &conn).await.unwrap();
+        assert_eq!((causes.not_found, causes.invalid_content), (1, 1));
+        let causes = get_failure_causes(Period::Hour, Some("staging"), &conn)
+            .await
+            .unwrap();
+        assert_eq!((causes.not_found, causes.invalid_content), (0, 1));
+    }
+
+    #[tokio::test]
+    async fn test_get_success_rate_history() {
+        let conn = setup_database().await;
+        insert_audited_content(&conn).await;
+
+        let since = Utc::now() - Duration::hours(2);
+        let history = get_success_rate_history(BucketInterval::Hour, since, None, &conn)
+            .await
+            .unwrap();
+        assert_eq!(history.iter().map(|bucket| bucket.total).sum::<i64>(), 4);
+        assert_eq!(history.iter().map(|bucket| bucket.passed).sum::<i64>(), 2);
+        let history =
+            get_success_rate_history(BucketInterval::Hour, since, Some("production"), &conn)
+                .await
+                .unwrap();
+        assert!(history.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_worst_content() {
+        let conn = setup_database().await;
+        let (header, body) = insert_audited_content(&conn).await;
+        insert_audit(header.id, AuditResult::Failure, None, None, &conn).await;
+
+        let cutoff = Some(Utc::now() - Duration::hours(1));
+        let worst = get_worst_content(cutoff, 10, None, &conn).await.unwrap();
+        assert_eq!(worst.len(), 2);
+        assert_eq!(worst[0].content_key, header.content_key);
+        assert_eq!((worst[0].audits, worst[0].failures), (3, 2));
+        assert_eq!((worst[1].audits, worst[1].failures), (2, 1));
+
+        // Only the labelled audit is counted when asking for its label.
+        let worst = get_worst_content(cutoff, 10, Some("staging"), &conn)
+            .await
+            .unwrap();
+        assert_eq!(worst.len(), 1);
+        assert_eq!(worst[0].content_key, body.content_key);
+        assert_eq!((worst[0].audits, worst[0].failures), (1, 1));
+    }
+
+    /// Tests that without a cutoff or label the audit counts on each content key are ranked.
+    #[tokio::test]
+    async fn test_get_worst_content_from_counts() {
+        let conn = setup_database().await;
+        let mut ids = vec![];
+        for (key, audits, successes) in [
+            (
+                HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+                    block_hash: [1; 32],
+                }),
+                4,
+                1,
+            ),
+            (
+                HistoryContentKey::BlockBody(BlockBodyKey {
+                    block_hash: [1; 32],
+                }),
+                2,
+                2,
+            ),
+            // Never audited, so left out.
+            (
+                HistoryContentKey::BlockBody(BlockBodyKey {
+                    block_hash: [2; 32],
+                }),
+                0,
+                0,
+            ),
+        ] {
+            let model = content::get_or_create(&key, &conn).await.unwrap();
+            content::add_audit_counts(model.id, audits, successes, &conn)
+                .await
+                .unwrap();
+            ids.push(model.content_key);
+        }
+
+        let worst = get_worst_content(None, 10, None, &conn).await.unwrap();
+        let ranked: Vec<(&[u8], i64, i64)> = worst
+            .iter()
+            .map(|content| {
+                (
+                    content.content_key.as_slice(),
+                    content.audits,
+                    content.failures,
+                )
+            })
+            .collect();
+        assert_eq!(
+            ranked,
+            vec![(ids[0].as_slice(), 4, 3), (ids[1].as_slice(), 2, 0)]
+        );
+    }
+
+    /// Tests that audits are counted by the length of the content they got back.
+    #[tokio::test]
+    async fn test_content_length_histogram() {
+        let conn = setup_database().await;
+        let body = HistoryContentKey::BlockBody(BlockBodyKey {
+            block_hash: [1; 32],
+        });
+        let body = content::get_or_create(&body, &conn).await.unwrap();
+        for content_length in [
+            None,
+            Some(0),
+            Some(2),
+            Some(3),
+            Some(1000),
+            Some(100_000_000),
+        ] {
+            let mut audit: content_audit::ActiveModel =
+                insert_audit(body.id, AuditResult::Success, None, None, &conn)
+                    .await
+                    .into();
+            audit.content_length = Set(content_length);
+            audit.update(&conn).await.unwrap();
+        }
+
+        let histogram = get_content_length_histogram(Utc::now() - Duration::hours(1), &conn)
+            .await
+            .unwrap();
+        let buckets: Vec<(&str, i64, i64)> = histogram
+            .iter()
+            .map(|bucket| {
+                (
+                    bucket.content_type.as_str(),
+                    bucket.min_bytes,
+                    bucket.audits,
+                )
+            })
+            .collect();
+        assert_eq!(
+            buckets,
+            vec![
+                ("Block bodies", 0, 1),
+                ("Block bodies", 2, 2),
+                ("Block bodies", 512, 1),
+                ("Block bodies", 1 << 24, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summarize_latency() {
+        assert_eq!(summarize_latency(None, vec![]), None);
+
+        let durations = (1..=20).rev().map(|n| n * 10).collect();
+        let summary = summarize_latency(Some("Block headers".to_string()), durations).unwrap();
+        assert_eq!(summary.content_type.as_deref(), Some("Block headers"));
+        assert_eq!(summary.audits, 20);
+        assert_eq!(summary.average_ms, 105.0);
+        assert_eq!(summary.p50_ms, 105.0);
+        assert!((summary.p95_ms - 190.5).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,105 @@
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{mpsc, oneshot};
+
+/// Selection cycles that can be waiting for each selection task at once.
+const PENDING_TRIGGERS: usize = 16;
+
+/// Asks the selection tasks of the audit process to select content now, on top of
+/// their periods.
+///
+/// Clones share the same selection tasks, so one can be handed to glados-web when
+/// both run in the same process.
+#[derive(Clone, Debug, Default)]
+pub struct AuditTrigger {
+    listeners: Arc<Mutex<Vec<mpsc::Sender<oneshot::Sender<usize>>>>>,
+}
+
+impl AuditTrigger {
+    /// Receives the requests for a selection cycle, each with a sender for the number
+    /// of content keys that the cycle enqueued.
+    ///
+    /// Each selection task subscribes once, and stops being asked when it drops the
+    /// receiver.
+    pub fn subscribe(&self) -> mpsc::Receiver<oneshot::Sender<usize>> {
+        let (listener, requests) = mpsc::channel(PENDING_TRIGGERS);
+        self.lock_listeners().push(listener);
+        requests
+    }
+
+    /// Runs a selection cycle in every selection task, returning the number of content
+    /// keys they enqueued between them.
+    ///
+    /// A task that already has [`PENDING_TRIGGERS`] cycles waiting is not asked again,
+    /// and a task that stops before it replies counts as having enqueued nothing.
+    /// Returns `None` if no selection task is running.
+    pub async fn trigger(&self) -> Option<usize> {
+        let replies: Vec<oneshot::Receiver<usize>> = {
+            let mut listeners = self.lock_listeners();
+            listeners.retain(|listener| !listener.is_closed());
+            if listeners.is_empty() {
+                return None;
+            }
+            listeners
+                .iter()
+                .filter_map(|listener| {
+                    let (reply, replies) = oneshot::channel();
+                    listener.try_send(reply).ok().map(|()| replies)
+                })
+                .collect()
+        };
+        let mut enqueued = 0;
+        for reply in replies {
+            if let Ok(keys) = reply.await {
+                enqueued += keys;
+            }
+        }
+        Some(enqueued)
+    }
+
+    fn lock_listeners(
+        &self,
+    ) -> std::sync::MutexGuard<'_, Vec<mpsc::Sender<oneshot::Sender<usize>>>> {
+        // The list is only pushed to and filtered, so a panic while holding the lock
+        // cannot leave it inconsistent.
+        self.listeners
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_trigger_without_tasks() {
+        let trigger = AuditTrigger::default();
+        assert_eq!(trigger.trigger().await, None);
+
+        // A task that has stopped is not waited for.
+        drop(trigger.subscribe());
+        assert_eq!(trigger.trigger().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_trigger_sums_replies() {
+        let trigger = AuditTrigger::default();
+        for keys in [3, 4] {
+            let mut requests = trigger.subscribe();
+            tokio::spawn(async move {
+                while let Some(reply) = requests.recv().await {
+                    let _ = reply.send(keys);
+                }
+            });
+        }
+        // Stops before replying.
+        let mut stopped = trigger.subscribe();
+        tokio::spawn(async move {
+            let _ = stopped.recv().await;
+        });
+
+        assert_eq!(trigger.trigger().await, Some(7));
+        assert_eq!(trigger.trigger().await, Some(7));
+    }
+}
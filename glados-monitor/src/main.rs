@@ -4,6 +4,7 @@ use sea_orm::{Database, DatabaseConnection};
 use tokio::{signal, task};
 use tracing::{debug, info};
 
+use glados_core::pool::redact_database_url;
 use glados_monitor::{
     bulk_download_block_data,
     cli::{Cli, Commands},
@@ -27,14 +28,15 @@ async fn main() -> Result<()> {
     //
     // Database Connection
     //
-    debug!(DATABASE_URL = &cli.database_url, "Connecting to database");
+    let redacted_url = redact_database_url(&cli.database_url);
+    debug!(DATABASE_URL = %redacted_url, "Connecting to database");
 
     let conn = Database::connect(&cli.database_url)
         .await
         .expect("Database connection failed");
 
     info!(
-        DATABASE_URL = &cli.database_url,
+        DATABASE_URL = %redacted_url,
         "database connection established"
     );
 
@@ -0,0 +1,58 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+use crate::templates::{ErrorTemplate, HtmlTemplate};
+
+/// An error from a route, rendered as an error page.
+///
+/// Details are logged where the error occurs, the message is shown to the user.
+#[derive(Debug)]
+pub enum AppError {
+    /// The request could not be understood, such as a malformed hex string.
+    BadRequest(String),
+    /// The requested item is not in the database.
+    NotFound(String),
+    /// Something went wrong while handling a valid request.
+    Internal(String),
+    /// A status code from a helper that does not provide a message.
+    Status(StatusCode),
+}
+
+impl AppError {
+    pub(crate) fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Status(status) => *status,
+        }
+    }
+}
+
+impl From<StatusCode> for AppError {
+    fn from(status: StatusCode) -> Self {
+        AppError::Status(status)
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        let message = match self {
+            AppError::BadRequest(message)
+            | AppError::NotFound(message)
+            | AppError::Internal(message) => message,
+            AppError::Status(status) => status
+                .canonical_reason()
+                .unwrap_or("Something went wrong...")
+                .to_string(),
+        };
+        let template = ErrorTemplate {
+            status: status.as_u16(),
+            message,
+        };
+        (status, HtmlTemplate(template)).into_response()
+    }
+}
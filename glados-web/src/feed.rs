@@ -0,0 +1,67 @@
+use entity::{content, content_audit};
+use ethportal_api::utils::bytes::hex_encode;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use tokio::{
+    sync::broadcast,
+    time::{interval, Duration},
+};
+use tracing::{debug, error};
+
+use crate::routes::AuditJson;
+
+/// Number of audits kept for subscribers that fall behind before they start missing audits.
+pub const AUDIT_FEED_CAPACITY: usize = 256;
+/// Time between database checks for new audits.
+const AUDIT_FEED_PERIOD: Duration = Duration::from_secs(1);
+/// Largest number of new audits read from the database per check.
+const AUDIT_FEED_BATCH_SIZE: u64 = 100;
+
+/// Loops indefinitely, publishing audits created after startup to live feed subscribers.
+///
+/// The audit process usually runs separately from the web server, so new audits are
+/// found by polling the database for ids above the last one published.
+pub async fn publish_new_audits(conn: DatabaseConnection, tx: broadcast::Sender<AuditJson>) -> ! {
+    let mut last_id = match content_audit::Entity::find()
+        .order_by_desc(content_audit::Column::Id)
+        .one(&conn)
+        .await
+    {
+        Ok(latest) => latest.map(|audit| audit.id).unwrap_or_default(),
+        Err(e) => {
+            error!(err=?e, "Could not look up latest audit for the live feed");
+            0
+        }
+    };
+    debug!(audit.id = last_id, "Starting live audit feed");
+    let mut interval = interval(AUDIT_FEED_PERIOD);
+
+    loop {
+        interval.tick().await;
+        let audits = match content_audit::Entity::find()
+            .filter(content_audit::Column::Id.gt(last_id))
+            .order_by_asc(content_audit::Column::Id)
+            .limit(AUDIT_FEED_BATCH_SIZE)
+            .find_also_related(content::Entity)
+            .all(&conn)
+            .await
+        {
+            Ok(audits) => audits,
+            Err(e) => {
+                error!(err=?e, "Could not look up new audits for the live feed");
+                continue;
+            }
+        };
+        for (audit, content) in audits {
+            last_id = audit.id;
+            let Some(content) = content else {
+                continue;
+            };
+            // Sending only fails when nobody is subscribed, in which case the audit is dropped.
+            let _ = tx.send(AuditJson {
+                content_key: hex_encode(content.content_key),
+                passed: audit.is_success(),
+                created_at: audit.created_at,
+            });
+        }
+    }
+}
@@ -1,31 +1,58 @@
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 use std::{net::SocketAddr, path::Path};
 
 use anyhow::{bail, Result};
-use axum::http::StatusCode;
+use axum::http::{header, HeaderValue, Request, StatusCode};
 use axum::{
-    extract::Extension,
-    routing::{get, get_service},
+    body::Body,
+    extract::{DefaultBodyLimit, Extension},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, get_service, post},
     Router,
 };
-use tower_http::services::ServeDir;
-use tracing::info;
+use tokio_rustls::rustls::ServerConfig;
+use tower_http::{
+    services::ServeDir,
+    trace::{DefaultOnResponse, TraceLayer},
+    LatencyUnit,
+};
+use tracing::{info, info_span, warn, Level, Span};
 
 use ethereum_types::U256;
 use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
 
 pub mod cli;
+pub mod error;
+pub mod feed;
 pub mod routes;
 pub mod state;
 pub mod templates;
+pub mod tls;
+pub mod utils;
 
-use crate::state::State;
-
-const SOCKET: &str = "0.0.0.0:3001";
+use crate::state::{QueryPermit, State};
 
 const ASSET_PATH_ENV_VAR: &str = "GLADOS_WEB_ASSETS_PATH";
 
-pub async fn run_glados_web(config: Arc<State>) -> Result<()> {
+/// Header holding a request id set by a proxy in front of glados-web.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest request id taken from a request, so that a client cannot fill the logs.
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+/// Id given to the next request that does not come with one.
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Serves the dashboard and API, over HTTPS when given a TLS config and plain HTTP otherwise.
+pub async fn run_glados_web(
+    config: Arc<State>,
+    socket: SocketAddr,
+    tls_config: Option<Arc<ServerConfig>>,
+) -> Result<()> {
     let assets_path = match std::env::var(ASSET_PATH_ENV_VAR) {
         Ok(path) => Path::new(&path).to_path_buf(),
         Err(_) => {
@@ -60,19 +87,26 @@ pub async fn run_glados_web(config: Arc<State>) -> Result<()> {
         info!(row.id=?updated.id, old=?previous_value, new=?updated.node_id_high, "Setting high bits");
     }
 
+    tokio::spawn(feed::publish_new_audits(
+        config.database_connection.clone(),
+        config.audit_feed.clone(),
+    ));
+
     // setup router
-    let app = Router::new()
+    let database_routes = Router::new()
         .route("/", get(routes::root))
         .route("/census/census-list/", get(routes::census_explorer_list))
         .route("/census/", get(routes::single_census_view))
         .route("/census/explorer", get(routes::census_explorer))
         .route("/network/", get(routes::network_dashboard))
+        .route("/network/nodes/", get(routes::node_list))
         .route("/network/node/:node_id_hex/", get(routes::node_detail))
         .route(
             "/network/node/:node_id_hex/enr/:enr_seq/",
             get(routes::enr_detail),
         )
         .route("/content/", get(routes::content_dashboard))
+        .route("/content/search/", get(routes::content_search))
         .route("/content/id/", get(routes::contentid_list))
         .route(
             "/content/id/:content_id_hex/",
@@ -86,34 +120,209 @@ pub async fn run_glados_web(config: Arc<State>) -> Result<()> {
         .route("/audit/id/:audit_id", get(routes::contentaudit_detail))
         .route("/audits/", get(routes::contentaudit_dashboard))
         .route("/audits/filter/", get(routes::contentaudit_filter))
-        .route(
-            "/api/hourly-success-rate/",
-            get(routes::hourly_success_rate),
-        )
+        .route("/audits/compare/", get(routes::audit_comparison))
+        .route("/api/hourly-success-rate", get(routes::hourly_success_rate))
         .route(
             "/api/is-content-in-deadzone/:content_key",
             get(routes::is_content_in_deadzone),
         )
         .route("/api/stat-history/", get(routes::get_audit_stats_handler))
+        .route("/api/audits", get(routes::recent_audits_api))
+        .route("/api/clients", get(routes::client_counts))
+        .route("/api/content/:content_id_hex", get(routes::contentid_api))
+        .route("/api/content/:content_id_hex/raw", get(routes::raw_content))
+        .route("/api/content-keys", post(routes::insert_content_keys))
+        .route("/api/audit/trigger", post(routes::trigger_audit))
+        .route("/api/search", get(routes::search_api))
+        .route("/api/worst-content", get(routes::worst_content))
+        .route("/api/failure-distances", get(routes::failure_distances))
+        .route(
+            "/api/routing-table/history",
+            get(routes::routing_table_history),
+        )
+        .route("/api/audits.csv", get(routes::audits_csv))
+        .route("/api/stats/success-rate", get(routes::success_rate_history))
+        .route("/api/stats/latency", get(routes::latency_stats))
+        .route("/api/stats/nodes", get(routes::node_stats))
+        .route(
+            "/api/stats/content-length",
+            get(routes::content_length_histogram),
+        )
         .route(
             "/census/census-node-timeseries-data/",
             get(routes::census_timeseries),
         )
+        .route_layer(middleware::from_fn(limit_concurrent_queries));
+
+    // Probes, metrics and the live feed stay reachable when the query limit is reached.
+    let app = Router::new()
+        .merge(database_routes)
+        .route("/metrics", get(routes::metrics))
+        .route("/healthz", get(routes::healthz))
+        .route("/readyz", get(routes::readyz))
+        .route("/api/version", get(routes::version))
+        .route("/ws/audits", get(routes::audit_feed))
         .nest_service("/static/", serve_dir.clone())
         .fallback_service(serve_dir)
+        // Oversized bodies are rejected with 413 by the extractors reading them.
+        .layer(DefaultBodyLimit::max(config.max_request_body_bytes))
         .layer(Extension(config));
 
-    let app = app.fallback(handler_404);
+    // Every log line recorded while handling a request carries its id, and the
+    // status and latency are logged once the response is ready.
+    let app = app
+        .fallback(handler_404)
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(|request: &Request<Body>| request_span(request))
+                .on_response(
+                    DefaultOnResponse::new()
+                        .level(Level::INFO)
+                        .latency_unit(LatencyUnit::Millis),
+                ),
+        )
+        .layer(middleware::from_fn(assign_request_id));
 
-    let socket: SocketAddr = SOCKET.parse()?;
+    if let Some(tls_config) = tls_config {
+        info!("Serving glados-web over HTTPS at {}", socket);
+        return tls::serve_tls(app, socket, tls_config).await;
+    }
     info!("Serving glados-web at {}", socket);
     Ok(axum::Server::bind(&socket)
         .serve(app.into_make_service())
         .await?)
 }
 
+/// Sets the `x-request-id` header of a request, and of its response so that the client
+/// can quote it.
+///
+/// The id set by a proxy is kept, so that its logs can be matched up too, as long as it
+/// is at most [`MAX_REQUEST_ID_LEN`] letters, digits, `-`, `_`, `.` or `:`. Otherwise
+/// one is assigned.
+async fn assign_request_id<B>(mut request: Request<B>, next: Next<B>) -> Response {
+    let request_id = match request.headers().get(REQUEST_ID_HEADER) {
+        Some(request_id) if is_valid_request_id(request_id) => request_id.clone(),
+        _ => HeaderValue::from(NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)),
+    };
+    request
+        .headers_mut()
+        .insert(REQUEST_ID_HEADER, request_id.clone());
+    let mut response = next.run(request).await;
+    response.headers_mut().insert(REQUEST_ID_HEADER, request_id);
+    response
+}
+
+fn is_valid_request_id(request_id: &HeaderValue) -> bool {
+    let request_id = request_id.as_bytes();
+    !request_id.is_empty()
+        && request_id.len() <= MAX_REQUEST_ID_LEN
+        && request_id
+            .iter()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"-_.:".contains(byte))
+}
+
+/// Opens the span that the log lines of a request are recorded in, carrying the id
+/// set by [`assign_request_id`].
+fn request_span<B>(request: &Request<B>) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or_default();
+    info_span!(
+        "request",
+        request.id = request_id,
+        request.method = %request.method(),
+        request.path = request.uri().path(),
+    )
+}
+
+/// Seconds a client is asked to wait when the query limit is reached.
+const QUERY_LIMIT_RETRY_AFTER_SECONDS: &str = "1";
+
+/// Responds `503` with `Retry-After` when `--web-max-concurrent-queries` requests are
+/// already being handled, rather than queueing for a database connection.
+///
+/// The permit is passed to the handler as a [`QueryPermit`] extension, so that a
+/// streamed response can hold it until the stream ends.
+pub(crate) async fn limit_concurrent_queries<B>(
+    Extension(state): Extension<Arc<State>>,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let Some(query_limiter) = &state.query_limiter else {
+        return next.run(request).await;
+    };
+    match query_limiter.clone().try_acquire_owned() {
+        Ok(permit) => {
+            request.extensions_mut().insert(QueryPermit::new(permit));
+            next.run(request).await
+        }
+        Err(_) => {
+            warn!("Query limit reached, turning away request.");
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                [(header::RETRY_AFTER, QUERY_LIMIT_RETRY_AFTER_SECONDS)],
+            )
+                .into_response()
+        }
+    }
+}
+
 /// Global routing error handler to prevent panics.
 async fn handler_404() -> StatusCode {
     tracing::error!("404: Non-existent page visited");
     StatusCode::NOT_FOUND
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderMap;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    /// Sends a request with the given `x-request-id`, returning the id seen by the
+    /// handler and the one in the response.
+    async fn request_ids(request_id: Option<&str>) -> (String, String) {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|headers: HeaderMap| async move {
+                    headers[REQUEST_ID_HEADER].to_str().unwrap().to_owned()
+                }),
+            )
+            .layer(middleware::from_fn(assign_request_id));
+        let mut request = Request::builder().uri("/");
+        if let Some(request_id) = request_id {
+            request = request.header(REQUEST_ID_HEADER, request_id);
+        }
+        let response = app
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let echoed = response.headers()[REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (String::from_utf8(body.to_vec()).unwrap(), echoed)
+    }
+
+    #[tokio::test]
+    async fn test_request_id() {
+        let (seen, echoed) = request_ids(Some("proxy-1f2e.3")).await;
+        assert_eq!(
+            (seen.as_str(), echoed.as_str()),
+            ("proxy-1f2e.3", "proxy-1f2e.3")
+        );
+
+        // Missing, overlong and unprintable ids are replaced by an assigned one.
+        let too_long = "a".repeat(MAX_REQUEST_ID_LEN + 1);
+        for request_id in [None, Some(too_long.as_str()), Some("a b"), Some("")] {
+            let (seen, echoed) = request_ids(request_id).await;
+            assert_eq!(seen, echoed);
+            assert!(seen.parse::<u64>().is_ok(), "{seen}");
+        }
+    }
+}
@@ -1,27 +1,68 @@
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
-use sea_orm::Database;
+use tokio::{
+    sync::{broadcast, Semaphore},
+    time::Duration,
+};
 
-use glados_web::{cli::Args, run_glados_web, state::State};
+use glados_core::{
+    jsonrpc::{PortalApi, DEFAULT_MAX_RETRIES},
+    logging::init_logging,
+    pool::{connect, redact_database_url},
+};
+use glados_web::{
+    cli::Args,
+    feed::AUDIT_FEED_CAPACITY,
+    run_glados_web,
+    state::State,
+    utils::{Cached, ProbeLimiter, NODE_VERSION_CACHE_TTL},
+};
 use migration::{Migrator, MigratorTrait};
 
+/// Time allowed for the Portal node to answer a readiness check.
+const READINESS_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    env_logger::init();
     // parse command line arguments
     let args = Args::parse();
+    init_logging(&args.log)?;
+    let tls_config = args.web.tls.load()?;
 
-    let conn = Database::connect(args.database_url)
+    let conn = connect(&args.database_url, &args.pool)
         .await
-        .expect("Database connection failed");
+        .with_context(|| {
+            format!(
+                "Could not connect to the database at {}",
+                redact_database_url(&args.database_url)
+            )
+        })?;
     Migrator::up(&conn, None).await?;
 
+    let (audit_feed, _) = broadcast::channel(AUDIT_FEED_CAPACITY);
     let config = Arc::new(State {
         database_connection: conn,
+        audit_metrics: None,
+        audit_trigger: None,
+        portal_api: args.portal_client.map(|client_url| PortalApi {
+            client_url,
+            request_timeout: READINESS_REQUEST_TIMEOUT,
+            max_retries: DEFAULT_MAX_RETRIES,
+        }),
+        api_token: args.web.api_token,
+        raw_content_limiter: ProbeLimiter::per_minute(args.web.raw_content_per_minute),
+        node_version: Cached::new(NODE_VERSION_CACHE_TTL),
+        audit_feed,
+        page_limits: args.web.page_limits,
+        max_request_body_bytes: args.web.max_request_body_bytes,
+        query_limiter: args
+            .web
+            .web_max_concurrent_queries
+            .map(|limit| Arc::new(Semaphore::new(limit as usize))),
     });
 
-    run_glados_web(config).await?;
+    run_glados_web(config, args.web.socket_addr(), tls_config).await?;
     Ok(())
 }
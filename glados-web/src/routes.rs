@@ -1,50 +1,76 @@
 use axum::{
-    extract::{Extension, Path, Query as HttpQuery},
-    http::StatusCode,
-    response::IntoResponse,
+    body::StreamBody,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Extension, Path, Query as HttpQuery,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
     Json,
 };
 use chrono::{DateTime, Utc};
-use entity::{audit_stats, census, census_node, client_info};
+use entity::{
+    audit_stats, census, census_node, census_result, client_info, routing_table_snapshot,
+};
 use entity::{
     content,
-    content_audit::{self, AuditResult},
+    content_audit::{self, AuditOutcome, AuditResult, Availability, SelectionStrategy},
     execution_metadata, key_value, node, record,
 };
 use ethportal_api::jsonrpsee::core::__reexports::serde_json;
 use ethportal_api::types::distance::{Distance, Metric, XorMetric};
-use ethportal_api::utils::bytes::{hex_decode, hex_encode};
-use ethportal_api::{HistoryContentKey, OverlayContentKey};
-use glados_core::stats::{filter_audits, get_audit_stats, AuditFilters, Period};
+use ethportal_api::utils::bytes::hex_encode;
+use ethportal_api::{BlockHeaderKey, HistoryContentKey, OverlayContentKey};
+use futures::{stream, StreamExt};
+use glados_audit::validation::content_id_matches;
+use glados_core::dialect::Dialect;
+use glados_core::jsonrpc::{strip_quotes, PortalApi};
+use glados_core::metrics::AuditMetrics;
+use glados_core::stats::{
+    excludes_request_failures, filter_audits, get_audit_latency, get_audit_stats,
+    get_content_length_histogram, get_content_type_breakdown, get_distinct_node_counts,
+    get_failure_causes, get_success_rate_history, get_totals, get_worst_content, has_label,
+    AuditFilters, AuditLatency, BucketInterval, ContentLengthBucket, ContentTypeFilter, NodeCounts,
+    Period, StrategyFilter, SuccessFilter, SuccessRateBucket, WorstContent,
+};
 use migration::{Alias, JoinType, Order};
 use sea_orm::sea_query::{Expr, Query, SeaRc};
 use sea_orm::{sea_query::SimpleExpr, Statement};
 use sea_orm::{
-    ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, DynIden, EntityTrait,
-    FromQueryResult, LoaderTrait, ModelTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DynIden, EntityTrait, FromQueryResult,
+    LoaderTrait, ModelTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, RelationTrait,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use std::fmt::Formatter;
-use std::sync::Arc;
+use std::fmt::{Formatter, Write};
+use std::sync::{atomic::Ordering, Arc};
 use std::{fmt::Display, io};
-use tracing::error;
-use tracing::info;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, error, info, warn};
 
 use crate::templates::{
-    AuditDashboardTemplate, AuditTableTemplate, CensusExplorerTemplate, ContentAuditDetailTemplate,
-    ContentDashboardTemplate, ContentIdDetailTemplate, ContentIdListTemplate,
-    ContentKeyDetailTemplate, ContentKeyListTemplate, EnrDetailTemplate, HtmlTemplate,
-    IndexTemplate, NetworkDashboardTemplate, NodeDetailTemplate, PaginatedCensusListTemplate,
-    SingleCensusViewTemplate,
+    AuditComparisonTemplate, AuditDashboardTemplate, AuditTableTemplate, CensusExplorerTemplate,
+    ContentAuditDetailTemplate, ContentDashboardTemplate, ContentIdDetailTemplate,
+    ContentIdListTemplate, ContentKeyDetailTemplate, ContentKeyListTemplate, ContentSearchTemplate,
+    EnrDetailTemplate, HtmlTemplate, IndexTemplate, NetworkDashboardTemplate, NodeDetailTemplate,
+    NodeListTemplate, PaginatedCensusListTemplate, SingleCensusViewTemplate,
+};
+use crate::{
+    error::AppError,
+    state::{QueryPermit, State},
+    templates::AuditTuple,
+    utils::{
+        decode_header, decode_hex, parse_window, tokens_match, DecodedHeader,
+        NODE_VERSION_FAILURE_CACHE_TTL,
+    },
 };
-use crate::{state::State, templates::AuditTuple};
 
 //
 // Routes
 //
-pub async fn handle_error(_err: io::Error) -> impl IntoResponse {
-    (StatusCode::INTERNAL_SERVER_ERROR, "Something went wrong...")
+pub async fn handle_error(err: io::Error) -> impl IntoResponse {
+    error!(err=?err, "Could not serve static file");
+    AppError::Internal("Something went wrong...".to_string())
 }
 
 #[derive(FromQueryResult, Debug, Clone, Copy)]
@@ -100,9 +126,12 @@ impl Display for ClientDiversityResult {
     }
 }
 
-async fn generate_radius_graph_data(state: &Arc<State>) -> Vec<CalculatedRadiusChartData> {
+async fn generate_radius_graph_data(
+    state: &Arc<State>,
+) -> Result<Vec<CalculatedRadiusChartData>, AppError> {
     let builder = state.database_connection.get_database_backend();
     let mut radius_density = Query::select();
+    #[rustfmt::skip]
     radius_density
         .expr(Expr::col((
             census_node::Entity,
@@ -136,11 +165,10 @@ async fn generate_radius_graph_data(state: &Arc<State>) -> Vec<CalculatedRadiusC
         )
         .and_where(
             Expr::col((census_node::Entity, census_node::Column::SurveyedAt))
-                .lt(Expr::cust(if builder == DbBackend::Sqlite {
-                    "STRFTIME('%Y-%m-%dT%H:%M:%S.%f', DATETIME(latest_census.started_at, '+' || latest_census.duration || ' seconds'))"
-                } else {
-                    "latest_census.started_at + latest_census.duration * interval '1 second'"
-                })),
+                .lt(Expr::cust(Dialect::new(builder).add_seconds(
+                    "latest_census.started_at",
+                    "latest_census.duration",
+                ))),
         )
         .and_where(
             Expr::col((census_node::Entity, census_node::Column::RecordId))
@@ -154,7 +182,10 @@ async fn generate_radius_graph_data(state: &Arc<State>) -> Vec<CalculatedRadiusC
     let radius_chart_data = RadiusChartData::find_by_statement(builder.build(&radius_density))
         .all(&state.database_connection)
         .await
-        .unwrap();
+        .map_err(|e| {
+            error!(err=?e, "Could not look up node radii");
+            AppError::Internal("Could not look up node radii".to_string())
+        })?;
 
     let mut radius_percentages: Vec<CalculatedRadiusChartData> = vec![];
     for i in radius_chart_data {
@@ -177,7 +208,6 @@ async fn generate_radius_graph_data(state: &Arc<State>) -> Vec<CalculatedRadiusC
         ];
 
         let percentage = (radius_int as f64 / u32::MAX as f64) * 100.0;
-        let formatted_percentage = format!("{:.2}", percentage);
 
         let mut node_id_bytes: [u8; 32] = [0; 32];
         if i.node_id.len() == 32 {
@@ -186,14 +216,15 @@ async fn generate_radius_graph_data(state: &Arc<State>) -> Vec<CalculatedRadiusC
 
         let node_id_string = hex_encode(node_id_bytes);
         radius_percentages.push(CalculatedRadiusChartData {
-            data_radius: formatted_percentage.parse().unwrap(),
+            // Rounded to two decimal places for display.
+            data_radius: (percentage * 100.0).round() / 100.0,
             node_id: u64::from_be_bytes(node_id_high_bytes),
             node_id_string,
             raw_enr: i.raw,
         });
     }
 
-    radius_percentages
+    Ok(radius_percentages)
 }
 
 async fn get_max_census_id(state: &Arc<State>) -> Option<MaxCensusId> {
@@ -214,7 +245,10 @@ async fn get_max_census_id(state: &Arc<State>) -> Option<MaxCensusId> {
     }
 }
 
-async fn get_created_data_from_census_id(state: &Arc<State>, census_id: i32) -> DateTime<Utc> {
+async fn get_created_data_from_census_id(
+    state: &Arc<State>,
+    census_id: i32,
+) -> Result<DateTime<Utc>, AppError> {
     let builder = state.database_connection.get_database_backend();
     // we need to bounds check the requested census_id and return None if it doesn't exist
     let created_data = Query::select()
@@ -228,15 +262,19 @@ async fn get_created_data_from_census_id(state: &Arc<State>, census_id: i32) ->
     let created_data = CensusCreatedAt::find_by_statement(builder.build(&created_data))
         .one(&state.database_connection)
         .await
-        .unwrap();
-    created_data.unwrap().created_at
+        .map_err(|e| {
+            error!(err=?e, census_id, "Could not look up census start time");
+            AppError::Internal("Could not look up census start time".to_string())
+        })?
+        .ok_or_else(|| AppError::NotFound(format!("No census found with id {census_id}")))?;
+    Ok(created_data.created_at)
 }
 
 async fn generate_client_diversity_data(
     state: &Arc<State>,
     census_id: Option<i32>,
     max_census_id: MaxCensusId,
-) -> Option<Vec<ClientDiversityResult>> {
+) -> Result<Option<Vec<ClientDiversityResult>>, AppError> {
     let builder = state.database_connection.get_database_backend();
     let census_selection_query = match census_id {
         Some(census_id) => {
@@ -248,7 +286,7 @@ async fn generate_client_diversity_data(
                     .limit(1)
                     .take()
             } else {
-                return None;
+                return Ok(None);
             }
         }
         None => Query::select()
@@ -296,7 +334,7 @@ async fn generate_client_diversity_data(
                 .column(key_value::Column::RecordId)
                 .column(key_value::Column::Value)
                 .and_where(
-                    Expr::cust("convert_from(key, 'UTF8')").eq("c"),
+                    Expr::cust(Dialect::new(builder).bytes_as_text("key")).eq("c"),
                 )
                 .take(),
             right_table.clone(),
@@ -305,19 +343,22 @@ async fn generate_client_diversity_data(
         )
         .add_group_by([Expr::cust("substr(substr(value, 1, 2), length(substr(value, 1, 2)), 1)")]);
 
-    Some(
+    let client_diversity_data =
         ClientDiversityResult::find_by_statement(builder.build(&client_count))
             .all(&state.database_connection)
             .await
-            .unwrap(),
-    )
+            .map_err(|e| {
+                error!(err=?e, "Could not look up client diversity");
+                AppError::Internal("Could not look up client diversity".to_string())
+            })?;
+    Ok(Some(client_diversity_data))
 }
 
 async fn generate_enr_list_from_census_id(
     state: &Arc<State>,
     census_id: Option<i32>,
     max_census_id: MaxCensusId,
-) -> Option<Vec<RawEnr>> {
+) -> Result<Option<Vec<RawEnr>>, AppError> {
     let census_selection_query = match census_id {
         Some(census_id) => {
             if census_id >= 1 && census_id <= max_census_id.id {
@@ -328,7 +369,7 @@ async fn generate_enr_list_from_census_id(
                     .limit(1)
                     .take()
             } else {
-                return None;
+                return Ok(None);
             }
         }
         None => Query::select()
@@ -357,26 +398,30 @@ async fn generate_enr_list_from_census_id(
                 .eq(Expr::col((record::Entity, record::Column::Id))),
         );
 
-    Some(
-        RawEnr::find_by_statement(builder.build(&enrs_from_census))
-            .all(&state.database_connection)
-            .await
-            .unwrap(),
-    )
+    let enr_list = RawEnr::find_by_statement(builder.build(&enrs_from_census))
+        .all(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(err=?e, "Could not look up census ENRs");
+            AppError::Internal("Could not look up census ENRs".to_string())
+        })?;
+    Ok(Some(enr_list))
 }
 
-pub async fn root(Extension(state): Extension<Arc<State>>) -> impl IntoResponse {
+pub async fn root(
+    Extension(state): Extension<Arc<State>>,
+) -> Result<HtmlTemplate<IndexTemplate>, AppError> {
     let client_diversity_data = match get_max_census_id(&state).await {
         None => vec![],
         Some(max_census_id) => generate_client_diversity_data(&state, None, max_census_id)
-            .await
-            .unwrap(),
+            .await?
+            .unwrap_or_default(),
     };
 
-    let radius_percentages = generate_radius_graph_data(&state).await;
+    let radius_percentages = generate_radius_graph_data(&state).await?;
     let open_filter = content_audit::Entity::find();
     // Run queries for content dashboard data concurrently
-    let (hour_stats, day_stats, week_stats) = tokio::join!(
+    let (hour_stats, day_stats, week_stats, totals) = tokio::join!(
         get_audit_stats(
             open_filter.clone(),
             Period::Hour,
@@ -388,33 +433,48 @@ pub async fn root(Extension(state): Extension<Arc<State>>) -> impl IntoResponse
             Period::Week,
             &state.database_connection
         ),
+        get_totals(&state.database_connection),
     );
 
     // Get results from queries
-    let hour_stats = hour_stats.unwrap();
-    let day_stats = day_stats.unwrap();
-    let week_stats = week_stats.unwrap();
+    let hour_stats = hour_stats.map_err(|e| {
+        error!(err=?e, "Could not look up audit stats");
+        AppError::Internal("Could not look up audit stats".to_string())
+    })?;
+    let day_stats = day_stats.map_err(|e| {
+        error!(err=?e, "Could not look up audit stats");
+        AppError::Internal("Could not look up audit stats".to_string())
+    })?;
+    let week_stats = week_stats.map_err(|e| {
+        error!(err=?e, "Could not look up audit stats");
+        AppError::Internal("Could not look up audit stats".to_string())
+    })?;
+    let totals = totals.map_err(|e| {
+        error!(err=?e, "Could not look up totals");
+        AppError::Internal("Could not look up totals".to_string())
+    })?;
 
     let template = IndexTemplate {
         client_diversity_data,
         average_radius_chart: radius_percentages,
         stats: [hour_stats, day_stats, week_stats],
+        totals,
     };
-    HtmlTemplate(template)
+    Ok(HtmlTemplate(template))
 }
 
 pub async fn network_dashboard(
     Extension(state): Extension<Arc<State>>,
 ) -> Result<HtmlTemplate<NetworkDashboardTemplate>, StatusCode> {
-    const KEY_COUNT: u64 = 20;
+    let key_count = state.page_limits.dashboard_rows;
 
     let recent_node_list = node::Entity::find()
         .order_by_desc(node::Column::Id)
-        .limit(KEY_COUNT)
+        .limit(key_count)
         .all(&state.database_connection)
         .await
         .map_err(|e| {
-            error!(key.count=KEY_COUNT, err=?e, "Could not look up recent nodes");
+            error!(key.count=key_count, err=?e, "Could not look up recent nodes");
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
     let total_node_count = node::Entity::find()
@@ -428,14 +488,13 @@ pub async fn network_dashboard(
     let recent_enr_list: Vec<(record::Model, node::Model)> = record::Entity::find()
         .order_by_desc(record::Column::Id)
         .find_also_related(node::Entity)
-        .limit(KEY_COUNT)
+        .limit(key_count)
         .all(&state.database_connection)
         .await
         .map_err(|e| {
-            error!(key.count=KEY_COUNT, err=?e, "Could not look up recent ENR records");
+            error!(key.count=key_count, err=?e, "Could not look up recent ENR records");
             StatusCode::INTERNAL_SERVER_ERROR
-        })
-        .unwrap()
+        })?
         .iter()
         .filter_map(|(r, n)| {
             n.as_ref()
@@ -460,24 +519,144 @@ pub async fn network_dashboard(
     Ok(HtmlTemplate(template))
 }
 
-pub async fn node_detail(
-    Path(node_id_hex): Path<String>,
+/// Largest page size accepted by the node list.
+const MAX_NODE_PAGE_SIZE: u64 = 500;
+
+#[derive(Deserialize, Debug)]
+pub struct NodeListParams {
+    pub page: Option<u64>,
+    pub page_size: Option<u64>,
+    /// `node_id` (the default), `last_seen` or `alive`.
+    pub sort: Option<String>,
+    /// Only nodes that did, or did not, answer their latest census ping.
+    pub alive: Option<bool>,
+}
+
+/// Order of the node list.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NodeSort {
+    NodeId,
+    /// Most recently seen first.
+    LastSeen,
+    /// Alive nodes first, each group most recently seen first.
+    Alive,
+}
+
+impl NodeSort {
+    /// Reads `?sort=`, falling back to ordering by node id for values that are not understood.
+    fn from_param(sort: Option<&str>) -> Self {
+        match sort {
+            None | Some("node_id") => NodeSort::NodeId,
+            Some("last_seen") => NodeSort::LastSeen,
+            Some("alive") => NodeSort::Alive,
+            Some(sort) => {
+                warn!(sort, "Ignoring unknown node list sort key");
+                NodeSort::NodeId
+            }
+        }
+    }
+
+    pub fn as_param(&self) -> &'static str {
+        match self {
+            NodeSort::NodeId => "node_id",
+            NodeSort::LastSeen => "last_seen",
+            NodeSort::Alive => "alive",
+        }
+    }
+
+    fn apply(&self, nodes: sea_orm::Select<node::Entity>) -> sea_orm::Select<node::Entity> {
+        // Nodes never seen are listed last, which backends otherwise disagree on.
+        let by_last_seen = |nodes: sea_orm::Select<node::Entity>| {
+            nodes
+                .order_by_asc(Expr::col(node::Column::LastSeen).is_null())
+                .order_by_desc(node::Column::LastSeen)
+        };
+        let nodes = match self {
+            NodeSort::NodeId => nodes,
+            NodeSort::LastSeen => by_last_seen(nodes),
+            NodeSort::Alive => by_last_seen(nodes.order_by_desc(node::Column::Alive)),
+        };
+        nodes.order_by_asc(node::Column::NodeId)
+    }
+}
+
+pub async fn node_list(
+    params: HttpQuery<NodeListParams>,
     Extension(state): Extension<Arc<State>>,
-) -> Result<HtmlTemplate<NodeDetailTemplate>, StatusCode> {
-    let node_id = hex_decode(&node_id_hex).map_err(|e| {
+) -> Result<HtmlTemplate<NodeListTemplate>, StatusCode> {
+    let page = params.page.unwrap_or(1).max(1);
+    let page_size = params
+        .page_size
+        .unwrap_or(state.page_limits.node_page_size)
+        .clamp(1, MAX_NODE_PAGE_SIZE);
+
+    let sort = NodeSort::from_param(params.sort.as_deref());
+    let filtered_nodes = match params.alive {
+        Some(alive) => node::Entity::find().filter(node::Column::Alive.eq(alive)),
+        None => node::Entity::find(),
+    };
+
+    let nodes = sort
+        .apply(filtered_nodes.clone())
+        .offset((page - 1).saturating_mul(page_size))
+        .limit(page_size)
+        .all(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(page, page_size, err=?e, "Could not look up nodes");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let total_node_count = filtered_nodes
+        .count(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(err=?e, "Error looking up total Node count");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let template = NodeListTemplate {
+        nodes,
+        page,
+        page_size,
+        sort: sort.as_param(),
+        alive_query: match params.alive {
+            Some(alive) => format!("&alive={alive}"),
+            None => "".to_string(),
+        },
+        alive_filter: params.alive,
+        total_node_count,
+        has_next: page.saturating_mul(page_size) < total_node_count,
+    };
+    Ok(HtmlTemplate(template))
+}
+
+/// Looks up a node by its hex node id.
+async fn find_node(node_id_hex: &str, conn: &DatabaseConnection) -> Result<node::Model, AppError> {
+    let node_id = decode_hex(node_id_hex).map_err(|e| {
         error!(node_id=node_id_hex, err=?e, "Could not decode proved node_id");
-        StatusCode::INTERNAL_SERVER_ERROR
+        AppError::BadRequest(format!("Invalid node id: {node_id_hex}"))
     })?;
-    let node_model = node::Entity::find()
+    node::Entity::find()
         .filter(node::Column::NodeId.eq(node_id))
-        .one(&state.database_connection)
+        .one(conn)
         .await
         .map_err(|e| {
-            error!(node_id=node_id_hex, err=?e, "No record found for node_id");
-            StatusCode::NOT_FOUND
+            error!(node_id=node_id_hex, err=?e, "Could not look up node_id");
+            AppError::Internal("Could not look up node".to_string())
+        })?
+        .ok_or_else(|| {
+            error!(node_id = node_id_hex, "No record found for node_id");
+            AppError::NotFound(format!("No node found with id {node_id_hex}"))
         })
-        .unwrap()
-        .unwrap();
+}
+
+pub async fn node_detail(
+    Path(node_id_hex): Path<String>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<HtmlTemplate<NodeDetailTemplate>, AppError> {
+    const NODE_HISTORY_COUNT: u64 = 20;
+
+    let node_model = find_node(&node_id_hex, &state.database_connection).await?;
     let enr_list = record::Entity::find()
         .filter(record::Column::NodeId.eq(node_model.id))
         .order_by_desc(record::Column::SequenceNumber)
@@ -485,11 +664,37 @@ pub async fn node_detail(
         .await
         .map_err(|e| {
             error!(node.node_id=node_id_hex, node.db_id=node_model.id, err=?e, "Error looking up ENRs");
-            StatusCode::NOT_FOUND
+            AppError::Internal("Could not look up ENRs for node".to_string())
         })?;
     let closest_node_list = node::closest_xor(node_model.get_node_id(), &state.database_connection)
         .await
-        .unwrap();
+        .map_err(|e| {
+            error!(node.node_id=node_id_hex, err=?e, "Error looking up closest nodes");
+            AppError::Internal("Could not look up closest nodes".to_string())
+        })?;
+
+    let census_results = node_model
+        .find_related(census_result::Entity)
+        .order_by_desc(census_result::Column::CreatedAt)
+        .limit(NODE_HISTORY_COUNT)
+        .all(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(node.node_id=node_id_hex, err=?e, "Error looking up census results");
+            AppError::Internal("Could not look up census results for node".to_string())
+        })?;
+    let audits = content_audit::Entity::find()
+        .filter(content_audit::Column::Node.eq(node_model.id))
+        .order_by_desc(content_audit::Column::CreatedAt)
+        .limit(NODE_HISTORY_COUNT)
+        .all(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(node.node_id=node_id_hex, err=?e, "Error looking up audits by node");
+            AppError::Internal("Could not look up audits for node".to_string())
+        })?;
+    let recent_audits =
+        get_audit_tuples_from_audit_models(audits, &state.database_connection).await?;
 
     let latest_enr = enr_list.first().cloned();
 
@@ -502,7 +707,7 @@ pub async fn node_detail(
                 .await
                 .map_err(|e| {
                     error!(enr.id=enr.id, err=?e, "Error looking up key_value pairs");
-                    StatusCode::INTERNAL_SERVER_ERROR
+                    AppError::Internal("Could not look up ENR key value pairs".to_string())
                 })?,
         ),
         None => None,
@@ -513,6 +718,8 @@ pub async fn node_detail(
         latest_enr_key_value_list,
         enr_list,
         closest_node_list,
+        census_results,
+        recent_audits,
     };
     Ok(HtmlTemplate(template))
 }
@@ -520,39 +727,32 @@ pub async fn node_detail(
 pub async fn enr_detail(
     Path((node_id_hex, enr_seq)): Path<(String, u64)>,
     Extension(state): Extension<Arc<State>>,
-) -> Result<HtmlTemplate<EnrDetailTemplate>, StatusCode> {
-    let node_id = hex_decode(&node_id_hex).map_err(|e| {
-        error!(node_id=node_id_hex, err=?e, "Could not decode proved node_id");
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    let node_model = node::Entity::find()
-        .filter(node::Column::NodeId.eq(node_id))
-        .one(&state.database_connection)
-        .await
-        .map_err(|e| {
-            error!(node_id=node_id_hex, err=?e, "No record found for node_id");
-            StatusCode::NOT_FOUND
-        })
-        .unwrap()
-        .unwrap();
+) -> Result<HtmlTemplate<EnrDetailTemplate>, AppError> {
+    let node_model = find_node(&node_id_hex, &state.database_connection).await?;
     let enr = record::Entity::find()
         .filter(record::Column::NodeId.eq(node_model.id.to_owned()))
         .filter(record::Column::SequenceNumber.eq(enr_seq))
         .one(&state.database_connection)
         .await
         .map_err(|e| {
-            error!(enr.node_id=node_id_hex, enr.seq=enr_seq, err=?e, "No record found for node_id and sequence_number");
-            StatusCode::NOT_FOUND
-        })
-        .unwrap()
-        .unwrap();
+            error!(enr.node_id=node_id_hex, enr.seq=enr_seq, err=?e, "Could not look up ENR");
+            AppError::Internal("Could not look up ENR".to_string())
+        })?
+        .ok_or_else(|| {
+            error!(
+                enr.node_id = node_id_hex,
+                enr.seq = enr_seq,
+                "No record found for node_id and sequence_number"
+            );
+            AppError::NotFound(format!("No ENR found with sequence number {enr_seq}"))
+        })?;
     let key_value_list = key_value::Entity::find()
         .filter(key_value::Column::RecordId.eq(enr.id))
         .all(&state.database_connection)
         .await
         .map_err(|e| {
             error!(enr.id=enr.id, enr.node_id=node_id_hex, err=?e, "Error looking up key_value pairs");
-            StatusCode::NOT_FOUND
+            AppError::Internal("Could not look up ENR key value pairs".to_string())
         })?;
 
     let template = EnrDetailTemplate {
@@ -563,21 +763,260 @@ pub async fn enr_detail(
     Ok(HtmlTemplate(template))
 }
 
+/// Whether the audit workers are keeping up, shown when the audit process shares its metrics.
+#[derive(Debug)]
+pub struct PipelineHealth {
+    pub queue_depth: usize,
+    pub workers: usize,
+    pub active_workers: usize,
+    /// Approximate age of the oldest audit task waiting for a worker.
+    pub queue_lag_secs: u64,
+}
+
+impl PipelineHealth {
+    fn from_metrics(metrics: &AuditMetrics) -> Self {
+        PipelineHealth {
+            queue_depth: metrics.queue_depth.load(Ordering::Relaxed),
+            workers: metrics.workers.load(Ordering::Relaxed),
+            active_workers: metrics.active_workers.load(Ordering::Relaxed),
+            queue_lag_secs: metrics.queue_lag(Utc::now()).as_secs(),
+        }
+    }
+}
+
+/// Filters for the recent audits on the content dashboard.
+///
+/// Holds the canonical query parameter values, so the template can show the active filters.
+#[derive(Debug, Default)]
+pub struct DashboardFilters {
+    /// `pass` or `fail`.
+    pub result: Option<&'static str>,
+    /// `header`, `body` or `receipts`.
+    pub content_type: Option<&'static str>,
+    /// The `--run-label` of the audits, which also narrows the stats on the dashboard.
+    pub label: Option<String>,
+}
+
+impl DashboardFilters {
+    /// Reads `?result=`, `?type=` and `?label=`, ignoring values that are not understood.
+    fn from_params(params: &HashMap<String, String>) -> Self {
+        let result = params
+            .get("result")
+            .and_then(|value| match value.to_lowercase().as_str() {
+                "pass" | "success" => Some("pass"),
+                "fail" | "failure" => Some("fail"),
+                _ => {
+                    warn!(
+                        filter.result = value,
+                        "Ignoring unknown audit result filter"
+                    );
+                    None
+                }
+            });
+        let content_type =
+            params
+                .get("type")
+                .and_then(|value| match value.to_lowercase().as_str() {
+                    "header" | "headers" => Some("header"),
+                    "body" | "bodies" => Some("body"),
+                    "receipts" => Some("receipts"),
+                    _ => {
+                        warn!(
+                            filter.content_type = value,
+                            "Ignoring unknown content type filter"
+                        );
+                        None
+                    }
+                });
+        DashboardFilters {
+            result,
+            content_type,
+            label: params.get("label").cloned(),
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.result.is_some() || self.content_type.is_some() || self.label.is_some()
+    }
+
+    fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    fn audit_filters(&self) -> AuditFilters {
+        AuditFilters {
+            strategy: StrategyFilter::All,
+            success: match self.result {
+                Some("pass") => SuccessFilter::Success,
+                Some(_) => SuccessFilter::Failure,
+                None => SuccessFilter::All,
+            },
+            content_type: match self.content_type {
+                Some("header") => ContentTypeFilter::Headers,
+                Some("body") => ContentTypeFilter::Bodies,
+                Some(_) => ContentTypeFilter::Receipts,
+                None => ContentTypeFilter::All,
+            },
+        }
+    }
+}
+
+/// Order of the content and audit lists on the content dashboard.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum DashboardSort {
+    /// Most recently available content, or most recent audits, first.
+    #[default]
+    Newest,
+    Oldest,
+    /// Ascending content id, or audit id.
+    ById,
+}
+
+impl DashboardSort {
+    /// Reads `?content_sort=` or `?audit_sort=`, falling back to newest first for values
+    /// that are not understood.
+    fn from_param(sort: Option<&String>) -> Self {
+        match sort.map(String::as_str) {
+            None | Some("newest") => DashboardSort::Newest,
+            Some("oldest") => DashboardSort::Oldest,
+            Some("by-id") => DashboardSort::ById,
+            Some(sort) => {
+                warn!(sort, "Ignoring unknown dashboard sort key");
+                DashboardSort::Newest
+            }
+        }
+    }
+
+    pub fn as_param(&self) -> &'static str {
+        match self {
+            DashboardSort::Newest => "newest",
+            DashboardSort::Oldest => "oldest",
+            DashboardSort::ById => "by-id",
+        }
+    }
+
+    fn apply_to_content(
+        &self,
+        content: sea_orm::Select<content::Entity>,
+    ) -> sea_orm::Select<content::Entity> {
+        match self {
+            DashboardSort::Newest => content.order_by_desc(content::Column::FirstAvailableAt),
+            DashboardSort::Oldest => content.order_by_asc(content::Column::FirstAvailableAt),
+            DashboardSort::ById => content.order_by_asc(content::Column::ContentId),
+        }
+    }
+
+    fn apply_to_audits(
+        &self,
+        audits: sea_orm::Select<content_audit::Entity>,
+    ) -> sea_orm::Select<content_audit::Entity> {
+        match self {
+            DashboardSort::Newest => audits.order_by_desc(content_audit::Column::CreatedAt),
+            DashboardSort::Oldest => audits.order_by_asc(content_audit::Column::CreatedAt),
+            DashboardSort::ById => audits.order_by_asc(content_audit::Column::Id),
+        }
+    }
+}
+
+/// The filters and sorts of a content dashboard request, so that each link on the page
+/// changes one of them and keeps the rest.
+#[derive(Debug, Default)]
+pub struct DashboardQuery {
+    pub filters: DashboardFilters,
+    pub content_sort: DashboardSort,
+    pub audit_sort: DashboardSort,
+}
+
+impl DashboardQuery {
+    fn from_params(params: &HashMap<String, String>) -> Self {
+        DashboardQuery {
+            filters: DashboardFilters::from_params(params),
+            content_sort: DashboardSort::from_param(params.get("content_sort")),
+            audit_sort: DashboardSort::from_param(params.get("audit_sort")),
+        }
+    }
+
+    /// The query string with one parameter set to a value, keeping the others.
+    pub fn with(&self, name: &str, value: &str) -> String {
+        self.to_query_string(Some((name, value)), true)
+    }
+
+    /// The query string without the result and type filters, keeping the sorts and
+    /// the label, which scopes the whole dashboard.
+    pub fn without_filters(&self) -> String {
+        self.to_query_string(None, false)
+    }
+
+    fn to_query_string(&self, set: Option<(&str, &str)>, keep_filters: bool) -> String {
+        let mut params = vec![
+            ("content_sort", Some(self.content_sort.as_param())),
+            ("audit_sort", Some(self.audit_sort.as_param())),
+        ];
+        if keep_filters {
+            params.push(("result", self.filters.result));
+            params.push(("type", self.filters.content_type));
+        }
+        params.push(("label", self.filters.label()));
+        if let Some((name, value)) = set {
+            match params.iter_mut().find(|(param, _)| *param == name) {
+                Some(param) => param.1 = Some(value),
+                None => params.push((name, Some(value))),
+            }
+        }
+        let pairs: Vec<(&str, &str)> = params
+            .into_iter()
+            .filter_map(|(name, value)| value.map(|value| (name, value)))
+            .collect();
+        // Labels are free text, so the values are encoded.
+        format!(
+            "?{}",
+            serde_urlencoded::to_string(pairs).unwrap_or_default()
+        )
+    }
+}
+
+/// The audits that match the filters, in the order given.
+async fn get_filtered_recent_audits(
+    filters: &DashboardFilters,
+    sort: DashboardSort,
+    num_audits: u64,
+    conn: &DatabaseConnection,
+) -> Result<Vec<AuditTuple>, StatusCode> {
+    if !filters.is_active() && sort == DashboardSort::Newest {
+        return get_recent_audits(num_audits, conn).await;
+    }
+    let recent_audits = sort
+        .apply_to_audits(filter_audits(filters.audit_filters()).filter(has_label(filters.label())))
+        .limit(num_audits)
+        .all(conn)
+        .await
+        .map_err(|e| {
+            error!(key.count=num_audits, filters=?filters, err=?e, "Could not look up filtered recent audits");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    get_audit_tuples_from_audit_models(recent_audits, conn).await
+}
+
 pub async fn content_dashboard(
+    params: HttpQuery<HashMap<String, String>>,
     Extension(state): Extension<Arc<State>>,
 ) -> Result<HtmlTemplate<ContentDashboardTemplate>, StatusCode> {
-    const KEY_COUNT: u64 = 20;
-    let contentid_list = content::Entity::find()
-        .order_by_desc(content::Column::FirstAvailableAt)
-        .limit(KEY_COUNT)
+    let key_count = state.page_limits.dashboard_rows;
+    let query = DashboardQuery::from_params(&params);
+    let contentid_list = query
+        .content_sort
+        .apply_to_content(content::Entity::find())
+        .limit(key_count)
         .all(&state.database_connection)
         .await
         .map_err(|e| {
-            error!(key.count=KEY_COUNT, err=?e, "Could not look up latest keys");
+            error!(key.count=key_count, err=?e, "Could not look up latest keys");
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-    let open_filter = content_audit::Entity::find();
+    let label = query.filters.label();
+    let open_filter = content_audit::Entity::find().filter(has_label(label));
     // Run queries for content dashboard data concurrently
     let (
         audits_of_recent_content,
@@ -587,11 +1026,19 @@ pub async fn content_dashboard(
         hour_stats,
         day_stats,
         week_stats,
+        failure_causes,
+        content_types,
+        latency,
     ) = tokio::join!(
-        get_audits_for_recent_content(KEY_COUNT, &state.database_connection),
-        get_recent_audits(KEY_COUNT, &state.database_connection),
-        get_recent_audit_successes(KEY_COUNT, &state.database_connection),
-        get_recent_audit_failures(KEY_COUNT, &state.database_connection),
+        get_audits_for_recent_content(key_count, &state.database_connection),
+        get_filtered_recent_audits(
+            &query.filters,
+            query.audit_sort,
+            key_count,
+            &state.database_connection
+        ),
+        get_recent_audit_successes(key_count, label, &state.database_connection),
+        get_recent_audit_failures(key_count, label, &state.database_connection),
         get_audit_stats(
             open_filter.clone(),
             Period::Hour,
@@ -603,6 +1050,14 @@ pub async fn content_dashboard(
             Period::Week,
             &state.database_connection
         ),
+        get_failure_causes(Period::Day, label, &state.database_connection),
+        get_content_type_breakdown(Period::Day, label, &state.database_connection),
+        get_audit_latency(
+            Utc::now() - chrono::Duration::days(1),
+            true,
+            label,
+            &state.database_connection
+        ),
     );
 
     // Get results from queries
@@ -622,14 +1077,34 @@ pub async fn content_dashboard(
         error!(err=?e, "Could not look up recent audits");
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
+    let failure_causes = failure_causes.map_err(|e| {
+        error!(err=?e, "Could not look up audit failure causes");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let content_types = content_types.map_err(|e| {
+        error!(err=?e, "Could not look up audits by content type");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let latency = latency.map_err(|e| {
+        error!(err=?e, "Could not look up audit latency");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
     let template = ContentDashboardTemplate {
         stats: [hour_stats, day_stats, week_stats],
+        failure_causes,
+        content_types,
+        latency,
         contentid_list,
         audits_of_recent_content,
         recent_audits,
+        query,
         recent_audit_successes,
         recent_audit_failures,
+        pipeline: state
+            .audit_metrics
+            .as_deref()
+            .map(PipelineHealth::from_metrics),
     };
     Ok(HtmlTemplate(template))
 }
@@ -696,11 +1171,13 @@ pub async fn get_audits_for_recent_content(
 
 pub async fn get_recent_audit_successes(
     num_audits: u64,
+    label: Option<&str>,
     conn: &DatabaseConnection,
 ) -> Result<Vec<AuditTuple>, StatusCode> {
     let recent_audits: Vec<content_audit::Model> = content_audit::Entity::find()
         .order_by_desc(content_audit::Column::CreatedAt)
         .filter(content_audit::Column::Result.eq(AuditResult::Success))
+        .filter(has_label(label))
         .limit(num_audits)
         .all(conn)
         .await
@@ -714,11 +1191,13 @@ pub async fn get_recent_audit_successes(
 
 pub async fn get_recent_audit_failures(
     num_audits: u64,
+    label: Option<&str>,
     conn: &DatabaseConnection,
 ) -> Result<Vec<AuditTuple>, StatusCode> {
     let recent_audits: Vec<content_audit::Model> = content_audit::Entity::find()
         .order_by_desc(content_audit::Column::CreatedAt)
         .filter(content_audit::Column::Result.eq(AuditResult::Failure))
+        .filter(has_label(label))
         .limit(num_audits)
         .all(conn)
         .await
@@ -753,7 +1232,7 @@ pub async fn get_audit_tuples_from_audit_models(
     // Zip up the audits with their corresponding content and client info.
     // Filter out the (ideally zero) audits that do not have content or client info.
     let audit_tuples: Vec<AuditTuple> = itertools::izip!(audits, content, client_info)
-        .filter_map(|(audit, content, info)| content.map(|c| (audit, c, info.unwrap())))
+        .filter_map(|(audit, content, info)| Some((audit, content?, info?)))
         .collect();
 
     Ok(audit_tuples)
@@ -762,89 +1241,655 @@ pub async fn get_audit_tuples_from_audit_models(
 pub async fn contentid_list(
     Extension(state): Extension<Arc<State>>,
 ) -> Result<HtmlTemplate<ContentIdListTemplate>, StatusCode> {
-    const KEY_COUNT: u64 = 50;
+    let key_count = state.page_limits.list_rows;
     let contentid_list: Vec<content::Model> = content::Entity::find()
         .order_by_asc(content::Column::ContentId)
-        .limit(KEY_COUNT)
+        .limit(key_count)
         .all(&state.database_connection)
         .await
         .map_err(|e| {
-            error!(key.count=KEY_COUNT, err=?e, "Could not look up ids");
+            error!(key.count=key_count, err=?e, "Could not look up ids");
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
     let template = ContentIdListTemplate { contentid_list };
     Ok(HtmlTemplate(template))
 }
 
-pub async fn contentid_detail(
-    Path(content_id_hex): Path<String>,
-    Extension(state): Extension<Arc<State>>,
-) -> Result<HtmlTemplate<ContentIdDetailTemplate>, StatusCode> {
-    let content_id_raw = hex_decode(&content_id_hex).map_err(|e| {
+/// A content id with each of its content keys and their audits, newest first.
+type ContentIdAudits = (
+    content::Model,
+    Vec<(content::Model, Vec<content_audit::Model>)>,
+);
+
+/// Looks up a content id, its content keys and their audit history.
+async fn find_content_id_audits(
+    content_id_hex: &str,
+    conn: &DatabaseConnection,
+) -> Result<ContentIdAudits, AppError> {
+    let content_id_raw = decode_hex(content_id_hex).map_err(|e| {
         error!(content.id=content_id_hex, err=?e, "Could not decode up id bytes");
-        StatusCode::INTERNAL_SERVER_ERROR
+        AppError::BadRequest(format!("Invalid content id: {content_id_hex}"))
     })?;
     let content_id = content::Entity::find()
         .filter(content::Column::ContentId.eq(content_id_raw.clone()))
-        .one(&state.database_connection)
+        .one(conn)
         .await
         .map_err(|e| {
             error!(content.id=content_id_hex, err=?e, "Could not look up id");
-            StatusCode::INTERNAL_SERVER_ERROR
+            AppError::Internal("Could not look up content id".to_string())
         })?
         .ok_or_else(|| {
             error!(content.id = content_id_hex, "No data for id");
-            StatusCode::NOT_FOUND
+            AppError::NotFound(format!("No content found with id {content_id_hex}"))
         })?;
 
     let contentkey_list = content::Entity::find()
         .filter(content::Column::ContentId.eq(content_id_raw))
-        .all(&state.database_connection)
+        .all(conn)
         .await
         .map_err(|e| {
             error!(content.id=content_id_hex, err=?e, "Could not content keys for id");
-            StatusCode::INTERNAL_SERVER_ERROR
+            AppError::Internal("Could not look up content keys for id".to_string())
+        })?;
+
+    let audit_lists = contentkey_list
+        .load_many(
+            content_audit::Entity::find().order_by_desc(content_audit::Column::CreatedAt),
+            conn,
+        )
+        .await
+        .map_err(|e| {
+            error!(content.id=content_id_hex, err=?e, "Could not look up audits for id");
+            AppError::Internal("Could not look up audits for content id".to_string())
         })?;
 
+    Ok((
+        content_id,
+        contentkey_list.into_iter().zip(audit_lists).collect(),
+    ))
+}
+
+/// Fetches header content from the Portal node and decodes it, for display.
+///
+/// Only header keys are fetched, and only when a Portal node is configured and the
+/// `--raw-content-per-minute` limit, shared with [`raw_content`], allows it.
+async fn fetch_decoded_header(
+    content_key: &HistoryContentKey,
+    state: &State,
+) -> Option<DecodedHeader> {
+    if !matches!(content_key, HistoryContentKey::BlockHeaderWithProof(_)) {
+        return None;
+    }
+    let portal_api = state.portal_api.clone()?;
+    if !state.raw_content_limiter.try_acquire() {
+        debug!(
+            content.key = hex_encode(content_key.to_bytes()),
+            "Raw content limit reached, not decoding header."
+        );
+        return None;
+    }
+    match portal_api.get_content(content_key).await {
+        Ok(Some(content)) => decode_header(content_key, &content.raw),
+        Ok(None) => None,
+        Err(e) => {
+            warn!(content.key = hex_encode(content_key.to_bytes()), err=?e, "Could not fetch header to decode.");
+            None
+        }
+    }
+}
+
+pub async fn contentid_detail(
+    Path(content_id_hex): Path<String>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<HtmlTemplate<ContentIdDetailTemplate>, AppError> {
+    let (content_id, contentkey_list) =
+        find_content_id_audits(&content_id_hex, &state.database_connection).await?;
+    let header = match contentkey_list
+        .iter()
+        .find_map(|(content, _)| HistoryContentKey::try_from(content.content_key.clone()).ok())
+    {
+        Some(content_key) => fetch_decoded_header(&content_key, &state).await,
+        None => None,
+    };
     let template = ContentIdDetailTemplate {
         content_id,
-        contentkey_list,
+        header,
+        contentkey_list: contentkey_list
+            .into_iter()
+            .map(|(content, audits)| {
+                let availability = Availability::from_audits(&audits);
+                (content, audits, availability)
+            })
+            .collect(),
     };
     Ok(HtmlTemplate(template))
 }
 
-pub async fn contentkey_list(
-    Extension(state): Extension<Arc<State>>,
-) -> Result<HtmlTemplate<ContentKeyListTemplate>, StatusCode> {
-    const KEY_COUNT: u64 = 50;
-    let contentkey_list: Vec<content::Model> = content::Entity::find()
-        .order_by_desc(content::Column::Id)
-        .limit(KEY_COUNT)
-        .all(&state.database_connection)
-        .await
-        .map_err(|e| {
-            error!(key.count=KEY_COUNT, err=?e, "Could not look up keys");
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    let template = ContentKeyListTemplate { contentkey_list };
-    Ok(HtmlTemplate(template))
+#[derive(Serialize, Debug)]
+pub struct ContentIdJson {
+    pub content_id: String,
+    pub content_keys: Vec<ContentKeyAuditsJson>,
 }
 
-pub async fn contentaudit_dashboard() -> Result<HtmlTemplate<AuditDashboardTemplate>, StatusCode> {
-    let template = AuditDashboardTemplate {};
-    Ok(HtmlTemplate(template))
+#[derive(Serialize, Debug)]
+pub struct ContentKeyAuditsJson {
+    pub content_key: String,
+    pub first_available_at: DateTime<Utc>,
+    /// Number of the block that the content belongs to, if known.
+    pub block_number: Option<i32>,
+    /// Whether the latest audit passed, absent if the key has not been audited.
+    pub available: Option<bool>,
+    /// When the content was last retrieved, if its latest audits failed after an earlier success.
+    pub missing_since: Option<DateTime<Utc>>,
+    /// Audits recorded of the key, including any since deleted by retention.
+    pub audit_count: i32,
+    /// Those audits that passed.
+    pub audit_success_count: i32,
+    /// Audits of the key, newest first.
+    pub audits: Vec<ContentAuditJson>,
 }
 
-pub async fn census_explorer() -> Result<HtmlTemplate<CensusExplorerTemplate>, StatusCode> {
-    let template = CensusExplorerTemplate {};
-    Ok(HtmlTemplate(template))
+#[derive(Serialize, Debug)]
+pub struct ContentAuditJson {
+    pub id: i32,
+    pub created_at: DateTime<Utc>,
+    pub passed: bool,
+    /// Why the audit passed or failed, absent for early audits.
+    pub outcome: Option<String>,
+    pub strategy: Option<String>,
+    pub duration_ms: Option<i32>,
+    /// Why the request to the Portal node failed, if it did.
+    pub error: Option<String>,
+}
+
+impl From<content_audit::Model> for ContentAuditJson {
+    fn from(audit: content_audit::Model) -> Self {
+        ContentAuditJson {
+            id: audit.id,
+            created_at: audit.created_at,
+            passed: audit.is_success(),
+            outcome: audit.outcome.as_ref().map(AuditOutcome::as_text),
+            strategy: audit.strategy_used.as_ref().map(SelectionStrategy::as_text),
+            duration_ms: audit.duration_ms,
+            error: audit.error,
+        }
+    }
+}
+
+/// The JSON counterpart of [`contentid_detail`], with the full audit history of a content id.
+pub async fn contentid_api(
+    Path(content_id_hex): Path<String>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Json<ContentIdJson>, AppError> {
+    let (content_id, contentkey_list) =
+        find_content_id_audits(&content_id_hex, &state.database_connection).await?;
+    let block_numbers: HashMap<i32, i32> = execution_metadata::Entity::find()
+        .filter(
+            execution_metadata::Column::Content
+                .is_in(contentkey_list.iter().map(|(content, _)| content.id)),
+        )
+        .all(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(err=?e, "Could not look up block numbers of content keys");
+            AppError::Internal("Could not look up block numbers".to_string())
+        })?
+        .into_iter()
+        .map(|metadata| (metadata.content, metadata.block_number))
+        .collect();
+    Ok(Json(ContentIdJson {
+        content_id: content_id.id_as_hex(),
+        content_keys: contentkey_list
+            .into_iter()
+            .map(|(content, audits)| {
+                let availability = Availability::from_audits(&audits);
+                ContentKeyAuditsJson {
+                    content_key: content.key_as_hex(),
+                    first_available_at: content.first_available_at,
+                    block_number: block_numbers.get(&content.id).copied(),
+                    available: match availability {
+                        Availability::Unknown => None,
+                        Availability::Available => Some(true),
+                        _ => Some(false),
+                    },
+                    missing_since: availability.missing_since(),
+                    audit_count: content.audit_count,
+                    audit_success_count: content.audit_success_count,
+                    audits: audits.into_iter().map(ContentAuditJson::from).collect(),
+                }
+            })
+            .collect(),
+    }))
+}
+
+/// Checks that a request carries `Authorization: Bearer <token>` matching `--api-token`.
+///
+/// Responds `404` when no token is configured, as the endpoint is then disabled, and
+/// `401` when the request's token is missing or wrong.
+fn require_api_token(headers: &HeaderMap, state: &State) -> Result<(), StatusCode> {
+    let Some(api_token) = &state.api_token else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let authorized = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| tokens_match(token, api_token));
+    match authorized {
+        true => Ok(()),
+        false => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+/// Time allowed for the Portal node to find content for [`raw_content`].
+const RAW_CONTENT_REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+#[derive(Serialize, Debug)]
+pub struct RawContentJson {
+    pub content_key: String,
+    pub content_id: String,
+    /// The content as the Portal node returned it, hex encoded.
+    pub raw: String,
+    /// Whether the content the node returned is that of the content id, or `None` if
+    /// the content cannot show it, as for block bodies and receipts without their header.
+    pub content_id_verified: Option<bool>,
+}
+
+/// Asks the Portal node for a content id's content and returns it undecoded.
+///
+/// This is a live probe of the node, not a database read. It needs the same bearer
+/// token as [`insert_content_keys`] and is limited to `--raw-content-per-minute`
+/// requests, with `429` for the rest. The first stored content key of the content id
+/// is requested.
+///
+/// Responds `404` if the content id is unknown or the node does not have the content,
+/// and `502` if the request to the node fails.
+pub async fn raw_content(
+    Path(content_id_hex): Path<String>,
+    headers: HeaderMap,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Json<RawContentJson>, StatusCode> {
+    require_api_token(&headers, &state)?;
+    let Some(portal_api) = &state.portal_api else {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    };
+    let content_id = decode_hex(&content_id_hex).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let content = content::Entity::find()
+        .filter(content::Column::ContentId.eq(content_id))
+        .order_by_asc(content::Column::Id)
+        .one(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(content.id = content_id_hex, err=?e, "Could not look up content id");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let content_key = HistoryContentKey::try_from(content.content_key.clone()).map_err(|e| {
+        error!(content.id = content_id_hex, err=?e, "Could not decode stored content key");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if !state.raw_content_limiter.try_acquire() {
+        return Err(StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    let portal_api = PortalApi {
+        request_timeout: RAW_CONTENT_REQUEST_TIMEOUT,
+        ..portal_api.clone()
+    };
+    let client_url = portal_api.client_url.clone();
+    let found = portal_api.get_content(&content_key).await.map_err(|e| {
+        warn!(content.key = content.key_as_hex(), client.url = client_url, err=?e, "Could not fetch raw content from the Portal node");
+        StatusCode::BAD_GATEWAY
+    })?;
+    let Some(found) = found else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    Ok(Json(RawContentJson {
+        content_key: content.key_as_hex(),
+        content_id: content.id_as_hex(),
+        content_id_verified: content_id_matches(&content_key, &found.raw, None),
+        raw: hex_encode(found.raw),
+    }))
+}
+
+/// Most content keys accepted by one request to [`insert_content_keys`].
+const MAX_INSERTED_CONTENT_KEYS: usize = 1000;
+
+/// What happened to one of the content keys given to [`insert_content_keys`].
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentKeyInsertionStatus {
+    /// The key was new and is now available for auditing.
+    Inserted,
+    /// The key was already in the database.
+    Duplicate,
+    /// The key was not a hex encoded history network content key, or its content id
+    /// was not hex encoded.
+    Invalid,
+    /// The content id given with the key was not the one derived from it, so the key
+    /// was not stored.
+    Mismatch,
+}
+
+/// A content key given to [`insert_content_keys`], on its own or with the content id
+/// the caller derived for it.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum ContentKeyInput {
+    Key(String),
+    WithId {
+        content_key: String,
+        content_id: String,
+    },
+}
+
+#[derive(Serialize, Debug)]
+pub struct ContentKeyInsertion {
+    pub content_key: String,
+    pub status: ContentKeyInsertionStatus,
+}
+
+/// Inserts a JSON array of hex encoded content keys so that glados audits them.
+///
+/// Lets processes such as gossip listeners feed in content that glados did not
+/// find itself. Requests must carry `Authorization: Bearer <token>` matching
+/// `--api-token`, and the endpoint is disabled when no token is configured.
+///
+/// A key can be given with the content id the caller has for it, and is rejected if
+/// that is not the id derived from the key, which points to a bug in the caller.
+pub async fn insert_content_keys(
+    headers: HeaderMap,
+    Extension(state): Extension<Arc<State>>,
+    Json(content_keys): Json<Vec<ContentKeyInput>>,
+) -> Result<Json<Vec<ContentKeyInsertion>>, StatusCode> {
+    require_api_token(&headers, &state)?;
+    if content_keys.len() > MAX_INSERTED_CONTENT_KEYS {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    let mut insertions = Vec::with_capacity(content_keys.len());
+    for input in content_keys {
+        let (content_key_hex, content_id_hex) = match input {
+            ContentKeyInput::Key(content_key) => (content_key, None),
+            ContentKeyInput::WithId {
+                content_key,
+                content_id,
+            } => (content_key, Some(content_id)),
+        };
+        let content_key = decode_hex(&content_key_hex)
+            .ok()
+            .and_then(|raw| HistoryContentKey::try_from(raw).ok());
+        let content_id = content_id_hex.as_deref().map(decode_hex).transpose();
+        let status = match (content_key, content_id) {
+            (None, _) | (_, Err(_)) => ContentKeyInsertionStatus::Invalid,
+            (Some(content_key), Ok(content_id)) => {
+                let mismatch = content_id.and_then(|content_id| {
+                    content::verify_content_id(&content_key, &content_id).err()
+                });
+                if let Some(mismatch) = mismatch {
+                    warn!(err = %mismatch, "Content id given with content key does not match it");
+                    ContentKeyInsertionStatus::Mismatch
+                } else if content::insert_if_new(&content_key, &state.database_connection)
+                    .await
+                    .map_err(|e| {
+                        error!(content.key = content_key_hex, err=?e, "Could not insert content key");
+                        StatusCode::INTERNAL_SERVER_ERROR
+                    })?
+                {
+                    ContentKeyInsertionStatus::Inserted
+                } else {
+                    ContentKeyInsertionStatus::Duplicate
+                }
+            }
+        };
+        insertions.push(ContentKeyInsertion {
+            content_key: content_key_hex,
+            status,
+        });
+    }
+    info!(
+        inserted = insertions
+            .iter()
+            .filter(|insertion| insertion.status == ContentKeyInsertionStatus::Inserted)
+            .count(),
+        received = insertions.len(),
+        "Content keys received through the API"
+    );
+
+    Ok(Json(insertions))
+}
+
+/// Time allowed for the selection tasks to finish a cycle requested by [`trigger_audit`].
+const AUDIT_TRIGGER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+#[derive(Serialize, Debug)]
+pub struct AuditTriggerJson {
+    /// Content keys added to the audit queue by the selection cycle.
+    pub enqueued: usize,
+}
+
+/// Runs a selection cycle of every selection strategy now, rather than at their next period.
+///
+/// Useful right after inserting content keys. Needs the same bearer token as
+/// [`insert_content_keys`]. Responds `503` if the audit process is not running in this
+/// process, and `504` if the selection tasks do not finish the cycle in time.
+pub async fn trigger_audit(
+    headers: HeaderMap,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Json<AuditTriggerJson>, StatusCode> {
+    require_api_token(&headers, &state)?;
+    let Some(audit_trigger) = &state.audit_trigger else {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    };
+    match tokio::time::timeout(AUDIT_TRIGGER_TIMEOUT, audit_trigger.trigger()).await {
+        Ok(Some(enqueued)) => {
+            info!(enqueued, "Ran a triggered selection cycle.");
+            Ok(Json(AuditTriggerJson { enqueued }))
+        }
+        Ok(None) => Err(StatusCode::SERVICE_UNAVAILABLE),
+        Err(_) => {
+            warn!("Selection tasks did not finish a triggered cycle in time.");
+            Err(StatusCode::GATEWAY_TIMEOUT)
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ContentSearchParams {
+    pub hash: String,
+}
+
+/// Builds the block header content key for a hex block hash.
+///
+/// Malformed hashes are rejected with `400`.
+fn block_header_key_from_hash(block_hash_hex: &str) -> Result<HistoryContentKey, StatusCode> {
+    let block_hash: [u8; 32] = decode_hex(block_hash_hex)
+        .map_err(|e| {
+            error!(block.hash=block_hash_hex, err=?e, "Could not decode block hash");
+            StatusCode::BAD_REQUEST
+        })?
+        .try_into()
+        .map_err(|bytes: Vec<u8>| {
+            error!(
+                block.hash = block_hash_hex,
+                len = bytes.len(),
+                "Block hash must be 32 bytes"
+            );
+            StatusCode::BAD_REQUEST
+        })?;
+    Ok(HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+        block_hash,
+    }))
+}
+
+/// Looks up the block header content for a block hash.
+///
+/// Returns the derived content id along with the content, if glados knows of it.
+async fn find_block_header_content(
+    block_hash_hex: &str,
+    conn: &DatabaseConnection,
+) -> Result<([u8; 32], Option<content::Model>), StatusCode> {
+    let content_id = block_header_key_from_hash(block_hash_hex)?.content_id();
+    let content = content::Entity::find()
+        .filter(content::Column::ContentId.eq(content_id.to_vec()))
+        .one(conn)
+        .await
+        .map_err(|e| {
+            error!(block.hash=block_hash_hex, err=?e, "Could not look up block header content");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok((content_id, content))
+}
+
+/// Redirects to the content id page for the header of the given block hash.
+pub async fn content_search(
+    params: HttpQuery<ContentSearchParams>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Response, StatusCode> {
+    let (content_id, content) =
+        find_block_header_content(&params.hash, &state.database_connection).await?;
+    let content_id = hex_encode(content_id);
+    if content.is_some() {
+        return Ok(Redirect::to(&format!("/content/id/{content_id}/")).into_response());
+    }
+    let template = ContentSearchTemplate {
+        block_hash: params.0.hash,
+        content_id,
+    };
+    Ok((StatusCode::NOT_FOUND, HtmlTemplate(template)).into_response())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SearchParams {
+    pub q: String,
+}
+
+/// A way of reading the input to [`search_api`].
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchInterpretation {
+    /// The hash of a block, looked up as the content id of its header.
+    BlockHash,
+    /// The content id of stored content.
+    ContentId,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ContentJson {
+    pub content_key: String,
+    pub content_id: String,
+    pub first_available_at: DateTime<Utc>,
+}
+
+impl From<content::Model> for ContentJson {
+    fn from(content: content::Model) -> Self {
+        ContentJson {
+            content_key: content.key_as_hex(),
+            content_id: content.id_as_hex(),
+            first_available_at: content.first_available_at,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SearchResultJson {
+    Found {
+        interpretation: SearchInterpretation,
+        content: ContentJson,
+    },
+    NotFound {
+        query: String,
+        attempted: Vec<SearchInterpretation>,
+    },
+}
+
+/// Finds content from a 32 byte hex value that may be a block hash or a content id.
+///
+/// The input is tried as a block hash first, then as a content id. Misses are
+/// answered with `404` and the interpretations that were tried.
+pub async fn search_api(
+    params: HttpQuery<SearchParams>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Response, StatusCode> {
+    let query = params.0.q;
+    let (_, header_content) = find_block_header_content(&query, &state.database_connection).await?;
+    if let Some(content) = header_content {
+        return Ok(Json(SearchResultJson::Found {
+            interpretation: SearchInterpretation::BlockHash,
+            content: content.into(),
+        })
+        .into_response());
+    }
+
+    // The input was checked to be 32 bytes of hex when it was read as a block hash.
+    let content_id = decode_hex(&query).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let content = content::Entity::find()
+        .filter(content::Column::ContentId.eq(content_id))
+        .one(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(search.query=query, err=?e, "Could not look up content id");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let result = match content {
+        Some(content) => SearchResultJson::Found {
+            interpretation: SearchInterpretation::ContentId,
+            content: content.into(),
+        },
+        None => {
+            let not_found = SearchResultJson::NotFound {
+                query,
+                attempted: vec![
+                    SearchInterpretation::BlockHash,
+                    SearchInterpretation::ContentId,
+                ],
+            };
+            return Ok((StatusCode::NOT_FOUND, Json(not_found)).into_response());
+        }
+    };
+    Ok(Json(result).into_response())
+}
+
+pub async fn contentkey_list(
+    Extension(state): Extension<Arc<State>>,
+) -> Result<HtmlTemplate<ContentKeyListTemplate>, StatusCode> {
+    let key_count = state.page_limits.list_rows;
+    let contentkey_list: Vec<content::Model> = content::Entity::find()
+        .order_by_desc(content::Column::Id)
+        .limit(key_count)
+        .all(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(key.count=key_count, err=?e, "Could not look up keys");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let template = ContentKeyListTemplate { contentkey_list };
+    Ok(HtmlTemplate(template))
+}
+
+pub async fn contentaudit_dashboard() -> Result<HtmlTemplate<AuditDashboardTemplate>, StatusCode> {
+    let template = AuditDashboardTemplate {};
+    Ok(HtmlTemplate(template))
+}
+
+pub async fn census_explorer() -> Result<HtmlTemplate<CensusExplorerTemplate>, StatusCode> {
+    let template = CensusExplorerTemplate {};
+    Ok(HtmlTemplate(template))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LabelParams {
+    /// Only include audits recorded with this `--run-label`.
+    pub label: Option<String>,
 }
 
 /// Returns the success rate for the last hour as a percentage.
 pub async fn hourly_success_rate(
+    params: HttpQuery<LabelParams>,
     Extension(state): Extension<Arc<State>>,
 ) -> Result<Json<f32>, StatusCode> {
-    let open_filter = content_audit::Entity::find();
+    let open_filter = content_audit::Entity::find().filter(has_label(params.label.as_deref()));
     let stats = get_audit_stats(open_filter, Period::Hour, &state.database_connection)
         .await
         .map_err(|e| {
@@ -860,10 +1905,10 @@ pub async fn hourly_success_rate(
 pub async fn contentkey_detail(
     Path(content_key_hex): Path<String>,
     Extension(state): Extension<Arc<State>>,
-) -> Result<HtmlTemplate<ContentKeyDetailTemplate>, StatusCode> {
-    let content_key_raw = hex_decode(&content_key_hex).map_err(|e| {
+) -> Result<HtmlTemplate<ContentKeyDetailTemplate>, AppError> {
+    let content_key_raw = decode_hex(&content_key_hex).map_err(|e| {
         error!(content.key=content_key_hex, err=?e, "Could not decode up key bytes");
-        StatusCode::INTERNAL_SERVER_ERROR
+        AppError::BadRequest(format!("Invalid content key: {content_key_hex}"))
     })?;
     let content_key_model = content::Entity::find()
         .filter(content::Column::ContentKey.eq(content_key_raw.clone()))
@@ -871,11 +1916,11 @@ pub async fn contentkey_detail(
         .await
         .map_err(|e| {
             error!(content.key=content_key_hex, err=?e, "Could not look up key");
-            StatusCode::INTERNAL_SERVER_ERROR
+            AppError::Internal("Could not look up content key".to_string())
         })?
         .ok_or_else(|| {
             error!(content.key = content_key_hex, "No data for key");
-            StatusCode::NOT_FOUND
+            AppError::NotFound(format!("No content found with key {content_key_hex}"))
         })?;
 
     let contentaudit_list = content_key_model
@@ -884,30 +1929,32 @@ pub async fn contentkey_detail(
         .await
         .map_err(|e| {
             error!(content.key=content_key_hex, err=?e, "Could not look up audits for key");
-            StatusCode::NOT_FOUND
+            AppError::Internal("Could not look up audits for content key".to_string())
         })?;
 
     let content_key: HistoryContentKey = HistoryContentKey::try_from(content_key_raw.clone())
         .map_err(|e| {
             error!(content.key=content_key_hex, err=?e, "Could not create key from bytes.");
-            StatusCode::INTERNAL_SERVER_ERROR
+            AppError::BadRequest(format!("Not a history content key: {content_key_hex}"))
         })?;
     let metadata_model = execution_metadata::Entity::find()
         .filter(execution_metadata::Column::Content.eq(content_key_model.id))
         .one(&state.database_connection)
         .await
         .map_err(|e| {
-            error!(content.key=content_key_hex, err=?e, "No content metadata found");
-            StatusCode::NOT_FOUND
+            error!(content.key=content_key_hex, err=?e, "Could not look up content metadata");
+            AppError::Internal("Could not look up content metadata".to_string())
         })?;
     let block_number = metadata_model.map(|m| m.block_number);
 
     let content_id = hex_encode(content_key.content_id());
     let content_kind = content_key.to_string();
+    let availability = Availability::from_audits(&contentaudit_list);
     let template = ContentKeyDetailTemplate {
         content_key: content_key_hex,
         content_key_model,
         contentaudit_list,
+        availability,
         content_id,
         content_kind,
         block_number,
@@ -918,35 +1965,46 @@ pub async fn contentkey_detail(
 pub async fn contentaudit_detail(
     Path(audit_id): Path<String>,
     Extension(state): Extension<Arc<State>>,
-) -> impl IntoResponse {
-    let audit_id = audit_id.parse::<i32>().unwrap();
+) -> Result<HtmlTemplate<ContentAuditDetailTemplate>, AppError> {
+    let audit_id = audit_id.parse::<i32>().map_err(|e| {
+        error!(audit.id=audit_id, err=?e, "Could not parse audit id");
+        AppError::BadRequest(format!("Invalid audit id: {audit_id}"))
+    })?;
     info!("Audit ID: {}", audit_id);
     let audit = content_audit::Entity::find_by_id(audit_id)
         .one(&state.database_connection)
         .await
-        .unwrap()
-        .expect("No audit found");
+        .map_err(|e| {
+            error!(audit.id=audit_id, err=?e, "Could not look up audit");
+            AppError::Internal("Could not look up audit".to_string())
+        })?
+        .ok_or_else(|| AppError::NotFound(format!("No audit found with id {audit_id}")))?;
 
     let content = audit
         .find_related(content::Entity)
         .one(&state.database_connection)
         .await
-        .unwrap()
-        .expect("Failed to get audit content key");
+        .map_err(|e| {
+            error!(audit.id=audit_id, err=?e, "Could not look up audit content key");
+            AppError::Internal("Could not look up audit content key".to_string())
+        })?
+        .ok_or_else(|| AppError::NotFound(format!("No content found for audit {audit_id}")))?;
 
     let execution_metadata = content
         .find_related(execution_metadata::Entity)
         .one(&state.database_connection)
         .await
-        .unwrap()
-        .expect("Failed to get audit metadata");
+        .map_err(|e| {
+            error!(audit.id=audit_id, err=?e, "Could not look up audit metadata");
+            AppError::Internal("Could not look up audit metadata".to_string())
+        })?;
 
     let template = ContentAuditDetailTemplate {
         audit,
         content,
         execution_metadata,
     };
-    HtmlTemplate(template)
+    Ok(HtmlTemplate(template))
 }
 
 /// Takes an AuditFilter object generated from http query params
@@ -983,119 +2041,1089 @@ pub async fn contentaudit_filter(
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    let filtered_audits: Vec<AuditTuple> =
-        get_audit_tuples_from_audit_models(filtered_audits, &state.database_connection).await?;
+    let filtered_audits: Vec<AuditTuple> =
+        get_audit_tuples_from_audit_models(filtered_audits, &state.database_connection).await?;
+
+    let template = AuditTableTemplate {
+        stats: [hour_stats, day_stats, week_stats],
+        audits: filtered_audits,
+    };
+
+    Ok(HtmlTemplate(template))
+}
+
+/// Compares the latest audit results of each Portal node for recently audited content.
+pub async fn audit_comparison(
+    Extension(state): Extension<Arc<State>>,
+) -> Result<HtmlTemplate<AuditComparisonTemplate>, StatusCode> {
+    const RECENT_AUDIT_LIMIT: u64 = 500;
+    const MAX_CONTENT_ROWS: usize = 20;
+
+    let recent_audits: Vec<(content_audit::Model, Option<content::Model>)> =
+        content_audit::Entity::find()
+            .filter(content_audit::Column::Node.is_not_null())
+            .order_by_desc(content_audit::Column::CreatedAt)
+            .limit(RECENT_AUDIT_LIMIT)
+            .find_also_related(content::Entity)
+            .all(&state.database_connection)
+            .await
+            .map_err(|e| {
+                error!(err=?e, "Could not look up recent audits for comparison");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+    let node_ids: Vec<i32> = recent_audits
+        .iter()
+        .filter_map(|(audit, _)| audit.node)
+        .collect::<HashSet<i32>>()
+        .into_iter()
+        .collect();
+    let nodes: Vec<node::Model> = node::Entity::find()
+        .filter(node::Column::Id.is_in(node_ids))
+        .order_by_asc(node::Column::Id)
+        .all(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(err=?e, "Could not look up audit nodes for comparison");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    // Audits are newest first, so the first audit seen for a (content, node) pair is its latest.
+    let mut rows: Vec<(content::Model, Vec<Option<content_audit::Model>>)> = vec![];
+    for (audit, content) in recent_audits {
+        let (Some(content), Some(node_id)) = (content, audit.node) else {
+            continue;
+        };
+        let Some(column) = nodes.iter().position(|node| node.id == node_id) else {
+            continue;
+        };
+        let row = match rows.iter().position(|(c, _)| c.id == content.id) {
+            Some(row) => row,
+            None if rows.len() < MAX_CONTENT_ROWS => {
+                rows.push((content, vec![None; nodes.len()]));
+                rows.len() - 1
+            }
+            None => continue,
+        };
+        rows[row].1[column].get_or_insert(audit);
+    }
+
+    Ok(HtmlTemplate(AuditComparisonTemplate { nodes, rows }))
+}
+
+#[derive(FromQueryResult, Serialize, Debug)]
+pub struct DeadZoneData {
+    pub data_radius: Vec<u8>,
+    pub raw: String,
+    pub node_id: Vec<u8>,
+}
+
+pub async fn is_content_in_deadzone(
+    Path(content_key): Path<String>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Json<Vec<String>>, AppError> {
+    let builder = state.database_connection.get_database_backend();
+    let mut select_dead_zone_data = Query::select();
+    #[rustfmt::skip]
+    select_dead_zone_data
+        .expr(Expr::col((census_node::Entity, census_node::Column::DataRadius)))
+        .expr(Expr::col((node::Entity, node::Column::NodeId)))
+        .expr(Expr::col((record::Entity, record::Column::Raw)))
+        .from(census_node::Entity)
+        .from(node::Entity)
+        .from(record::Entity)
+        .from_subquery(
+            Query::select()
+                .from(census::Entity)
+                .expr_as(Expr::col(census::Column::StartedAt), Alias::new("started_at"))
+                .expr_as(Expr::col(census::Column::Duration), Alias::new("duration"))
+                .order_by(census::Column::StartedAt, Order::Desc)
+                .limit(1)
+                .take(),
+            Alias::new("latest_census"),
+        )
+        .and_where(
+            Expr::col((census_node::Entity, census_node::Column::SurveyedAt))
+                .gte(Expr::col((Alias::new("latest_census"), Alias::new("started_at")))),
+        )
+        .and_where(
+            Expr::col((census_node::Entity, census_node::Column::SurveyedAt))
+                .lt(Expr::cust(Dialect::new(builder).add_seconds(
+                    "latest_census.started_at",
+                    "latest_census.duration",
+                ))),
+        )
+        .and_where(
+            Expr::col((census_node::Entity, census_node::Column::RecordId))
+                .eq(Expr::col((record::Entity, record::Column::Id))),
+        )
+        .and_where(
+            Expr::col((record::Entity, record::Column::NodeId))
+                .eq(Expr::col((node::Entity, node::Column::Id))),
+        );
+
+    let content_key: ethportal_api::HistoryContentKey =
+        serde_json::from_value(serde_json::json!(content_key)).map_err(|e| {
+            error!(content.key=content_key, err=?e, "Could not decode content key");
+            AppError::BadRequest(format!("Invalid content key: {content_key}"))
+        })?;
+
+    let dead_zone_data_vec = DeadZoneData::find_by_statement(builder.build(&select_dead_zone_data))
+        .all(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(err=?e, "Could not look up latest census radius data");
+            AppError::Internal("Could not look up latest census".to_string())
+        })?;
+    let content_id = content_key.content_id();
+
+    let mut enrs: Vec<String> = vec![];
+    for dead_zone_data in dead_zone_data_vec {
+        let radius = Distance::from(crate::U256::from_big_endian(&dead_zone_data.data_radius));
+        let node_id = Distance::from(crate::U256::from_big_endian(&dead_zone_data.node_id));
+        if XorMetric::distance(&content_id, &node_id.big_endian()) <= radius {
+            enrs.push(dead_zone_data.raw);
+        }
+    }
+
+    Ok(Json(enrs))
+}
+
+/// Failing audits considered by [`failure_distances`] when no limit is given.
+const FAILURE_DISTANCE_AUDIT_LIMIT: u64 = 10_000;
+
+/// How far failing content is from the node that audited it.
+#[derive(Serialize, Debug)]
+pub struct NodeFailureDistances {
+    pub node_id: String,
+    /// The radius the node advertised in the latest census it appeared in, if any.
+    pub radius: Option<String>,
+    pub inside_radius: u64,
+    pub outside_radius: u64,
+    pub buckets: Vec<DistanceBucket>,
+}
+
+/// Failures whose XOR distance to the node has the given bit length (0 to 256).
+#[derive(Serialize, Debug)]
+pub struct DistanceBucket {
+    pub log_distance: u32,
+    pub failures: u64,
+}
+
+/// The radius a node advertised in a census.
+#[derive(FromQueryResult, Debug)]
+struct NodeRadius {
+    node_id: i32,
+    data_radius: Vec<u8>,
+}
+
+/// Buckets failed audits by the XOR distance from the content id to the auditing node id.
+///
+/// Failures that cluster outside the radius a node advertises are content it is not
+/// expected to store, while failures inside it suggest content it should have.
+/// Failed requests are left out, since the content was never looked up.
+pub async fn failure_distances(
+    params: HttpQuery<RecentAuditsParams>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Json<Vec<NodeFailureDistances>>, StatusCode> {
+    let cutoff = params.cutoff()?;
+    let limit = match params.limit {
+        Some(0) => return Err(StatusCode::BAD_REQUEST),
+        Some(limit) => limit,
+        None => FAILURE_DISTANCE_AUDIT_LIMIT,
+    };
+    let failures = select_audits_with_content(cutoff, params.label.as_deref())
+        .filter(content_audit::Column::Result.eq(AuditResult::Failure))
+        .filter(content_audit::Column::Node.is_not_null())
+        .filter(excludes_request_failures())
+        .limit(limit)
+        .all(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(err=?e, "Could not look up failed audits");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let mut failing_content_ids: HashMap<i32, Vec<[u8; 32]>> = HashMap::new();
+    for (audit, content) in failures {
+        let (Some(node_id), Some(content)) = (audit.node, content) else {
+            continue;
+        };
+        let Ok(content_id) = <[u8; 32]>::try_from(content.content_id) else {
+            warn!(content.id = content.id, "Stored content id is not 32 bytes");
+            continue;
+        };
+        failing_content_ids
+            .entry(node_id)
+            .or_default()
+            .push(content_id);
+    }
+
+    let nodes = node::Entity::find()
+        .filter(node::Column::Id.is_in(failing_content_ids.keys().copied()))
+        .order_by_asc(node::Column::Id)
+        .all(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(err=?e, "Could not look up nodes of failed audits");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    // The census records of each node are added in survey order, so the highest id is
+    // the latest radius the node advertised.
+    let latest_surveys = Query::select()
+        .expr(Expr::col((census_node::Entity, census_node::Column::Id)).max())
+        .from(census_node::Entity)
+        .inner_join(
+            record::Entity,
+            Expr::col((record::Entity, record::Column::Id))
+                .equals((census_node::Entity, census_node::Column::RecordId)),
+        )
+        .and_where(
+            Expr::col((record::Entity, record::Column::NodeId))
+                .is_in(failing_content_ids.keys().copied()),
+        )
+        .group_by_col((record::Entity, record::Column::NodeId))
+        .to_owned();
+    let radii: HashMap<i32, Distance> = census_node::Entity::find()
+        .select_only()
+        .column(record::Column::NodeId)
+        .column(census_node::Column::DataRadius)
+        .join(JoinType::InnerJoin, census_node::Relation::Record.def())
+        .filter(census_node::Column::Id.in_subquery(latest_surveys))
+        .into_model::<NodeRadius>()
+        .all(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(err=?e, "Could not look up node radii");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .map(|radius| {
+            (
+                radius.node_id,
+                Distance::from(crate::U256::from_big_endian(&radius.data_radius)),
+            )
+        })
+        .collect();
+
+    let mut report = vec![];
+    for node_model in nodes {
+        let radius = radii.get(&node_model.id);
+        let node_id = node_model.get_node_id().0;
+
+        let mut buckets: HashMap<u32, u64> = HashMap::new();
+        let (mut inside_radius, mut outside_radius) = (0, 0);
+        for content_id in failing_content_ids
+            .remove(&node_model.id)
+            .unwrap_or_default()
+        {
+            let distance = XorMetric::distance(&content_id, &node_id);
+            let log_distance = crate::U256::from_big_endian(&distance.big_endian()).bits() as u32;
+            *buckets.entry(log_distance).or_default() += 1;
+            match radius {
+                Some(radius) if distance <= *radius => inside_radius += 1,
+                Some(_) => outside_radius += 1,
+                None => {}
+            }
+        }
+        let mut buckets: Vec<DistanceBucket> = buckets
+            .into_iter()
+            .map(|(log_distance, failures)| DistanceBucket {
+                log_distance,
+                failures,
+            })
+            .collect();
+        buckets.sort_by_key(|bucket| bucket.log_distance);
+
+        report.push(NodeFailureDistances {
+            node_id: node_model.node_id_as_hex(),
+            radius: radius.map(|radius| hex_encode(radius.big_endian())),
+            inside_radius,
+            outside_radius,
+            buckets,
+        });
+    }
+
+    Ok(Json(report))
+}
+
+/// Routing table snapshots returned by [`routing_table_history`] when no limit is given.
+const ROUTING_TABLE_HISTORY_LIMIT: u64 = 1000;
+
+#[derive(Serialize, Debug)]
+pub struct RoutingTableSnapshotJson {
+    pub created_at: DateTime<Utc>,
+    pub local_node_id: String,
+    pub total_peers: i32,
+    pub connected_peers: i32,
+    pub buckets: Vec<RoutingTableBucketJson>,
+}
+
+/// Entries in the routing table bucket at the given log distance from the local node.
+#[derive(Serialize, Debug)]
+pub struct RoutingTableBucketJson {
+    pub log_distance: u16,
+    pub peers: u32,
+}
+
+/// Routing table snapshots taken by the cartographer, oldest first.
+pub async fn routing_table_history(
+    params: HttpQuery<RecentAuditsParams>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Json<Vec<RoutingTableSnapshotJson>>, StatusCode> {
+    let cutoff = params.cutoff()?;
+    let limit = match params.limit {
+        Some(0) => return Err(StatusCode::BAD_REQUEST),
+        Some(limit) => limit,
+        None => ROUTING_TABLE_HISTORY_LIMIT,
+    };
+    let snapshots = routing_table_snapshot::Entity::find();
+    let snapshots = match cutoff {
+        Some(cutoff) => snapshots.filter(routing_table_snapshot::Column::CreatedAt.gt(cutoff)),
+        None => snapshots,
+    };
+    let snapshots = snapshots
+        .order_by_desc(routing_table_snapshot::Column::CreatedAt)
+        .limit(limit)
+        .all(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(err=?e, "Could not look up routing table snapshots");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let mut history = vec![];
+    for snapshot in snapshots.into_iter().rev() {
+        let buckets = snapshot.bucket_counts().map_err(|e| {
+            error!(snapshot.id = snapshot.id, err=?e, "Stored bucket counts are not valid JSON");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        history.push(RoutingTableSnapshotJson {
+            created_at: snapshot.created_at,
+            local_node_id: hex_encode(&snapshot.local_node_id),
+            total_peers: snapshot.total_peers,
+            connected_peers: snapshot.connected_peers,
+            buckets: buckets
+                .into_iter()
+                .map(|(log_distance, peers)| RoutingTableBucketJson {
+                    log_distance,
+                    peers,
+                })
+                .collect(),
+        });
+    }
+
+    Ok(Json(history))
+}
+
+/// Returns the stats of the last week recorded by the audit process with `?label=`, or
+/// by one without a label.
+pub async fn get_audit_stats_handler(
+    params: HttpQuery<LabelParams>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Json<Vec<audit_stats::Model>>, StatusCode> {
+    let stats = audit_stats::get_recent_stats(params.label.as_deref(), &state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(err=?e, "Could not look up audit stat history");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(stats))
+}
+
+#[derive(Serialize, Debug)]
+pub struct ClientCount {
+    pub client: String,
+    pub count: i64,
+}
+
+/// Returns the number of known nodes running each client implementation, most common first.
+pub async fn client_counts(
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Json<Vec<ClientCount>>, StatusCode> {
+    let version_counts = node::count_by_client_version(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(err=?e, "Could not look up client version counts");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let mut counts: HashMap<&'static str, i64> = HashMap::new();
+    for version_count in version_counts {
+        let client = node::client_name_from_version(version_count.client_version.as_deref());
+        *counts.entry(client).or_default() += version_count.count;
+    }
+    let mut counts: Vec<ClientCount> = counts
+        .into_iter()
+        .map(|(client, count)| ClientCount {
+            client: client.to_string(),
+            count,
+        })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.client.cmp(&b.client)));
+    Ok(Json(counts))
+}
+
+/// Lookback window of the success rate history when none is given.
+const DEFAULT_SUCCESS_RATE_HOURS: i64 = 24;
+/// Longest lookback window of the success rate history.
+const MAX_SUCCESS_RATE_HOURS: i64 = 24 * 30;
+
+#[derive(Deserialize, Debug)]
+pub struct SuccessRateParams {
+    #[serde(default)]
+    pub interval: BucketInterval,
+    /// Number of hours to look back.
+    pub since: Option<i64>,
+    /// Only include audits recorded with this `--run-label`.
+    pub label: Option<String>,
+}
+
+/// Returns audit totals and passes bucketed by time, oldest first.
+///
+/// Lookback windows beyond the maximum are capped.
+pub async fn success_rate_history(
+    params: HttpQuery<SuccessRateParams>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Json<Vec<SuccessRateBucket>>, StatusCode> {
+    let hours = match params.since {
+        Some(hours) if hours < 1 => return Err(StatusCode::BAD_REQUEST),
+        Some(hours) => hours.min(MAX_SUCCESS_RATE_HOURS),
+        None => DEFAULT_SUCCESS_RATE_HOURS,
+    };
+    let since = Utc::now() - chrono::Duration::hours(hours);
+    let history = get_success_rate_history(
+        params.interval,
+        since,
+        params.label.as_deref(),
+        &state.database_connection,
+    )
+    .await
+    .map_err(|e| {
+        error!(err=?e, "Could not look up success rate history");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(history))
+}
+
+/// Lookback window of the node counts when none is given.
+const DEFAULT_NODE_COUNT_DAYS: i64 = 7;
+/// Longest lookback window of the node counts.
+const MAX_NODE_COUNT_DAYS: i64 = 365;
+
+#[derive(Deserialize, Debug)]
+pub struct NodeStatsParams {
+    /// Window to look back over, eg `7d` or `24h`.
+    pub since: Option<String>,
+    /// Count the nodes that served audits recorded with this `--run-label` instead.
+    pub label: Option<String>,
+}
+
+/// Returns the number of distinct nodes seen by the census over a window, with a daily series.
+///
+/// With a label, the nodes that served content to its audits are counted instead, as
+/// censuses are not labelled. Lookback windows beyond the maximum are capped.
+pub async fn node_stats(
+    params: HttpQuery<NodeStatsParams>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Json<NodeCounts>, StatusCode> {
+    let window = match params.since.as_deref() {
+        Some(since) => parse_window(since, chrono::Duration::days(MAX_NODE_COUNT_DAYS))
+            .ok_or(StatusCode::BAD_REQUEST)?,
+        None => chrono::Duration::days(DEFAULT_NODE_COUNT_DAYS),
+    };
+    let counts = get_distinct_node_counts(
+        Utc::now() - window,
+        params.label.as_deref(),
+        &state.database_connection,
+    )
+    .await
+    .map_err(|e| {
+        error!(err=?e, "Could not count distinct nodes");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(counts))
+}
+
+/// Lookback window of the latency summary when none is given.
+const DEFAULT_LATENCY_HOURS: i64 = 24;
+
+/// Ways the latency summary can be split up.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LatencyGrouping {
+    ContentType,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LatencyParams {
+    /// Number of hours to look back.
+    pub since: Option<i64>,
+    pub group_by: Option<LatencyGrouping>,
+    /// Only include audits recorded with this `--run-label`.
+    pub label: Option<String>,
+}
+
+/// Returns the average, median and 95th percentile audit latency.
+///
+/// Lookback windows beyond the maximum are capped, as for the success rate history.
+pub async fn latency_stats(
+    params: HttpQuery<LatencyParams>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Json<AuditLatency>, StatusCode> {
+    let hours = match params.since {
+        Some(hours) if hours < 1 => return Err(StatusCode::BAD_REQUEST),
+        Some(hours) => hours.min(MAX_SUCCESS_RATE_HOURS),
+        None => DEFAULT_LATENCY_HOURS,
+    };
+    let since = Utc::now() - chrono::Duration::hours(hours);
+    let by_content_type = params.group_by == Some(LatencyGrouping::ContentType);
+    let latency = get_audit_latency(
+        since,
+        by_content_type,
+        params.label.as_deref(),
+        &state.database_connection,
+    )
+    .await
+    .map_err(|e| {
+        error!(err=?e, "Could not look up audit latency");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(latency))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ContentLengthParams {
+    /// Number of hours to look back.
+    pub since: Option<i64>,
+}
+
+/// Returns the number of audits by the length of the content returned, for each type
+/// of content, in buckets doubling in size.
+///
+/// Lookback windows beyond the maximum are capped, as for the success rate history.
+pub async fn content_length_histogram(
+    params: HttpQuery<ContentLengthParams>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Json<Vec<ContentLengthBucket>>, StatusCode> {
+    let hours = match params.since {
+        Some(hours) if hours < 1 => return Err(StatusCode::BAD_REQUEST),
+        Some(hours) => hours.min(MAX_SUCCESS_RATE_HOURS),
+        None => DEFAULT_LATENCY_HOURS,
+    };
+    let since = Utc::now() - chrono::Duration::hours(hours);
+    let histogram = get_content_length_histogram(since, &state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(err=?e, "Could not look up content length histogram");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(Json(histogram))
+}
+
+/// Number of audits returned by `/api/audits` when no limit is given.
+const DEFAULT_API_AUDIT_LIMIT: u64 = 50;
+/// Largest number of audits returned by a single `/api/audits` request.
+const MAX_API_AUDIT_LIMIT: u64 = 1000;
+/// Number of audits read from the database for each chunk of a CSV export.
+const CSV_EXPORT_BATCH_SIZE: u64 = 500;
+
+#[derive(Deserialize, Debug)]
+pub struct RecentAuditsParams {
+    pub limit: Option<u64>,
+    /// Only include audits from this many hours back.
+    pub since: Option<i64>,
+    /// Only include audits recorded with this `--run-label`.
+    pub label: Option<String>,
+}
+
+impl RecentAuditsParams {
+    /// Returns the earliest audit time requested, rejecting windows shorter than an hour.
+    fn cutoff(&self) -> Result<Option<DateTime<Utc>>, StatusCode> {
+        match self.since {
+            Some(hours) if hours < 1 => Err(StatusCode::BAD_REQUEST),
+            Some(hours) => Ok(Some(Utc::now() - chrono::Duration::hours(hours))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Audits newest first, joined to their content, optionally limited to those after `cutoff`
+/// and to those recorded with `label`.
+fn select_audits_with_content(
+    cutoff: Option<DateTime<Utc>>,
+    label: Option<&str>,
+) -> sea_orm::SelectTwo<content_audit::Entity, content::Entity> {
+    let audits = content_audit::Entity::find().filter(has_label(label));
+    let audits = match cutoff {
+        Some(cutoff) => audits.filter(content_audit::Column::CreatedAt.gt(cutoff)),
+        None => audits,
+    };
+    audits
+        .order_by_desc(content_audit::Column::Id)
+        .find_also_related(content::Entity)
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AuditJson {
+    pub content_key: String,
+    pub passed: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Streams each new audit as a JSON text message over a WebSocket.
+pub async fn audit_feed(ws: WebSocketUpgrade, Extension(state): Extension<Arc<State>>) -> Response {
+    let rx = state.audit_feed.subscribe();
+    ws.on_upgrade(|socket| forward_audits(socket, rx))
+}
+
+/// Forwards audits to a WebSocket client until it disconnects.
+///
+/// A client that falls too far behind skips the audits it missed and carries on.
+async fn forward_audits(mut socket: WebSocket, mut rx: broadcast::Receiver<AuditJson>) {
+    loop {
+        tokio::select! {
+            audit = rx.recv() => {
+                let audit = match audit {
+                    Ok(audit) => audit,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!(audits.skipped = skipped, "Live audit feed client lagged behind");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return,
+                };
+                let text = match serde_json::to_string(&audit) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        error!(err=?e, "Could not serialize audit for live feed");
+                        continue;
+                    }
+                };
+                if socket.send(Message::Text(text)).await.is_err() {
+                    debug!("Live audit feed client disconnected");
+                    return;
+                }
+            }
+            message = socket.recv() => match message {
+                // Messages from the client are ignored.
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                    debug!("Live audit feed client disconnected");
+                    return;
+                }
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// Returns the most recent audits as JSON, newest first.
+///
+/// Limits above the maximum are capped. Non-numeric limits are rejected by the
+/// query extractor with `400`.
+pub async fn recent_audits_api(
+    params: HttpQuery<RecentAuditsParams>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Json<Vec<AuditJson>>, StatusCode> {
+    let limit = match params.limit {
+        Some(0) => return Err(StatusCode::BAD_REQUEST),
+        Some(limit) => limit.min(MAX_API_AUDIT_LIMIT),
+        None => DEFAULT_API_AUDIT_LIMIT,
+    };
+    let audits = select_audits_with_content(params.cutoff()?, params.label.as_deref())
+        .limit(limit)
+        .all(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(key.count=limit, err=?e, "Could not look up recent audits");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .filter_map(|(audit, content)| {
+            Some(AuditJson {
+                content_key: hex_encode(content?.content_key),
+                passed: audit.is_success(),
+                created_at: audit.created_at,
+            })
+        })
+        .collect();
+
+    Ok(Json(audits))
+}
+
+/// Number of content keys returned by `/api/worst-content` when no limit is given.
+const DEFAULT_WORST_CONTENT_LIMIT: u64 = 20;
+
+#[derive(Serialize, Debug)]
+pub struct WorstContentJson {
+    pub content_key: String,
+    pub total_audits: i64,
+    pub failures: i64,
+    pub failure_rate: f64,
+}
+
+impl From<WorstContent> for WorstContentJson {
+    fn from(content: WorstContent) -> Self {
+        let failure_rate = match content.audits {
+            0 => 0.0,
+            audits => content.failures as f64 / audits as f64,
+        };
+        WorstContentJson {
+            content_key: hex_encode(content.content_key),
+            total_audits: content.audits,
+            failures: content.failures,
+            failure_rate,
+        }
+    }
+}
+
+/// Returns the content keys with the highest failure ratio, worst first.
+///
+/// Takes the same `limit`, `since` and `label` parameters as `/api/audits`.
+/// Without `since` or `label` it ranks every audit recorded, using the audit
+/// counts kept on each content key.
+pub async fn worst_content(
+    params: HttpQuery<RecentAuditsParams>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Json<Vec<WorstContentJson>>, StatusCode> {
+    let limit = match params.limit {
+        Some(0) => return Err(StatusCode::BAD_REQUEST),
+        Some(limit) => limit.min(MAX_API_AUDIT_LIMIT),
+        None => DEFAULT_WORST_CONTENT_LIMIT,
+    };
+    let cutoff = params.cutoff()?;
+    let worst = get_worst_content(
+        cutoff,
+        limit,
+        params.label.as_deref(),
+        &state.database_connection,
+    )
+    .await
+    .map_err(|e| {
+        error!(key.count=limit, err=?e, "Could not look up worst performing content");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(
+        worst.into_iter().map(WorstContentJson::from).collect(),
+    ))
+}
+
+/// Progress through a CSV export, read from the database one batch at a time.
+struct AuditCsvExport {
+    state: Arc<State>,
+    cutoff: Option<DateTime<Utc>>,
+    label: Option<String>,
+    /// Id of the last audit written, audits are exported in descending id order.
+    last_id: Option<i32>,
+    remaining: u64,
+    /// Keeps the query limit slot taken until the export is done.
+    _permit: Option<QueryPermit>,
+}
+
+impl AuditCsvExport {
+    const HEADER: &'static str = "id,content_key,result,outcome,strategy,created_at,duration_ms\n";
+
+    /// Reads the next batch of audits and formats them as CSV rows.
+    ///
+    /// Returns `None` once the export is complete.
+    async fn next_chunk(mut self) -> Option<(Result<String, sea_orm::DbErr>, Self)> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let audits = select_audits_with_content(self.cutoff, self.label.as_deref());
+        let audits = match self.last_id {
+            Some(last_id) => audits.filter(content_audit::Column::Id.lt(last_id)),
+            None => audits,
+        };
+        let batch = match audits
+            .limit(self.remaining.min(CSV_EXPORT_BATCH_SIZE))
+            .all(&self.state.database_connection)
+            .await
+        {
+            Ok(batch) => batch,
+            Err(e) => {
+                error!(err=?e, "Could not look up audits for CSV export");
+                self.remaining = 0;
+                return Some((Err(e), self));
+            }
+        };
+        let (last_audit, _) = batch.last()?;
+        self.last_id = Some(last_audit.id);
+        self.remaining = self.remaining.saturating_sub(batch.len() as u64);
+
+        let mut chunk = String::new();
+        for (audit, content) in batch {
+            let content_key = content
+                .map(|content| hex_encode(content.content_key))
+                .unwrap_or_default();
+            let duration_ms = audit
+                .duration_ms
+                .map(|duration_ms| duration_ms.to_string())
+                .unwrap_or_default();
+            // Writing to a String cannot fail.
+            let _ = writeln!(
+                chunk,
+                "{},{},{},{},{},{},{}",
+                audit.id,
+                content_key,
+                audit.result.as_text(),
+                audit.outcome_as_text(),
+                audit.strategy_as_text(),
+                audit.created_at.to_rfc3339(),
+                duration_ms,
+            );
+        }
+        Some((Ok(chunk), self))
+    }
+}
 
-    let template = AuditTableTemplate {
-        stats: [hour_stats, day_stats, week_stats],
-        audits: filtered_audits,
+/// Streams audits as CSV, newest first.
+///
+/// Accepts the same filters as `/api/audits`, except that the number of rows
+/// is unlimited unless a limit is given.
+pub async fn audits_csv(
+    params: HttpQuery<RecentAuditsParams>,
+    Extension(state): Extension<Arc<State>>,
+    permit: Option<Extension<QueryPermit>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let remaining = match params.limit {
+        Some(0) => return Err(StatusCode::BAD_REQUEST),
+        Some(limit) => limit,
+        None => u64::MAX,
+    };
+    let export = AuditCsvExport {
+        state,
+        cutoff: params.cutoff()?,
+        label: params.label.clone(),
+        last_id: None,
+        remaining,
+        _permit: permit.map(|Extension(permit)| permit),
     };
+    let rows = stream::unfold(export, AuditCsvExport::next_chunk);
+    let body = stream::once(async { Ok(AuditCsvExport::HEADER.to_string()) }).chain(rows);
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"audits.csv\"",
+            ),
+        ],
+        StreamBody::new(body),
+    ))
+}
 
-    Ok(HtmlTemplate(template))
+/// Content type of the Prometheus text exposition format.
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Liveness probe, answering without touching the database or the Portal node.
+pub async fn healthz() -> StatusCode {
+    StatusCode::OK
 }
 
-#[derive(FromQueryResult, Serialize, Debug)]
-pub struct DeadZoneData {
-    pub data_radius: Vec<u8>,
-    pub raw: String,
-    pub node_id: Vec<u8>,
+#[derive(Serialize, Debug)]
+pub struct VersionJson {
+    pub glados_version: &'static str,
+    /// `null` when no Portal node is configured or it could not be reached.
+    pub node_client_version: Option<String>,
 }
 
-pub async fn is_content_in_deadzone(
-    Path(content_key): Path<String>,
-    Extension(state): Extension<Arc<State>>,
-) -> Result<Json<Vec<String>>, StatusCode> {
-    let builder = state.database_connection.get_database_backend();
-    let mut select_dead_zone_data = Query::select();
-    select_dead_zone_data
-        .expr(Expr::col((census_node::Entity, census_node::Column::DataRadius)))
-        .expr(Expr::col((node::Entity, node::Column::NodeId)))
-        .expr(Expr::col((record::Entity, record::Column::Raw)))
-        .from(census_node::Entity)
-        .from(node::Entity)
-        .from(record::Entity)
-        .from_subquery(
-            Query::select()
-                .from(census::Entity)
-                .expr_as(Expr::col(census::Column::StartedAt), Alias::new("started_at"))
-                .expr_as(Expr::col(census::Column::Duration), Alias::new("duration"))
-                .order_by(census::Column::StartedAt, Order::Desc)
-                .limit(1)
-                .take(),
-            Alias::new("latest_census"),
-        )
-        .and_where(
-            Expr::col((census_node::Entity, census_node::Column::SurveyedAt))
-                .gte(Expr::col((Alias::new("latest_census"), Alias::new("started_at")))),
-        )
-        .and_where(
-            Expr::col((census_node::Entity, census_node::Column::SurveyedAt))
-                .lt(Expr::cust(if builder == DbBackend::Sqlite {
-                    "STRFTIME('%Y-%m-%dT%H:%M:%S.%f', DATETIME(latest_census.started_at, '+' || latest_census.duration || ' seconds'))"
-                } else {
-                    "latest_census.started_at + latest_census.duration * interval '1 second'"
-                })),
-        )
-        .and_where(
-            Expr::col((census_node::Entity, census_node::Column::RecordId))
-                .eq(Expr::col((record::Entity, record::Column::Id))),
-        )
-        .and_where(
-            Expr::col((record::Entity, record::Column::NodeId))
-                .eq(Expr::col((node::Entity, node::Column::Id))),
-        );
+/// Reports the version of glados and of the Portal node it is connected to.
+///
+/// The node's version is cached for a short time, and a failure to reach the node
+/// for a shorter time, so that polling this endpoint does not call the node on every
+/// request.
+pub async fn version(Extension(state): Extension<Arc<State>>) -> Json<VersionJson> {
+    Json(VersionJson {
+        glados_version: env!("CARGO_PKG_VERSION"),
+        node_client_version: node_client_version(&state).await,
+    })
+}
 
-    let dead_zone_data_vec = DeadZoneData::find_by_statement(builder.build(&select_dead_zone_data))
-        .all(&state.database_connection)
-        .await
-        .unwrap();
+async fn node_client_version(state: &State) -> Option<String> {
+    if let Some(version) = state.node_version.get() {
+        return version;
+    }
+    let portal_api = state.portal_api.as_ref()?;
+    match portal_api.get_client_version().await {
+        Ok(version) => {
+            let version = strip_quotes(version);
+            state.node_version.set(Some(version.clone()));
+            Some(version)
+        }
+        Err(e) => {
+            warn!(client.url = portal_api.client_url, err=?e, "Could not look up the Portal node's client version");
+            state
+                .node_version
+                .set_for(None, NODE_VERSION_FAILURE_CACHE_TTL);
+            None
+        }
+    }
+}
 
-    let content_key: ethportal_api::HistoryContentKey =
-        serde_json::from_value(serde_json::json!(content_key)).unwrap();
-    let content_id = content_key.content_id();
+#[derive(Serialize, Debug)]
+pub struct ReadinessJson {
+    pub ready: bool,
+    /// Dependencies that could not be reached.
+    pub failing: Vec<&'static str>,
+}
 
-    let mut enrs: Vec<String> = vec![];
-    for dead_zone_data in dead_zone_data_vec {
-        let radius = Distance::from(crate::U256::from_big_endian(&dead_zone_data.data_radius));
-        let node_id = Distance::from(crate::U256::from_big_endian(&dead_zone_data.node_id));
-        if XorMetric::distance(&content_id, &node_id.big_endian()) <= radius {
-            enrs.push(dead_zone_data.raw);
+/// Readiness probe, checking the database and, if configured, the Portal node.
+///
+/// Responds `503` with the failing dependencies listed when any check fails.
+pub async fn readyz(Extension(state): Extension<Arc<State>>) -> (StatusCode, Json<ReadinessJson>) {
+    let mut failing = vec![];
+
+    let backend = state.database_connection.get_database_backend();
+    if let Err(e) = state
+        .database_connection
+        .execute(Statement::from_string(backend, "SELECT 1".to_owned()))
+        .await
+    {
+        warn!(err=?e, "Readiness check could not reach the database");
+        failing.push("database");
+    }
+    if let Some(portal_api) = &state.portal_api {
+        if let Err(e) = portal_api.get_client_version().await {
+            warn!(client.url = portal_api.client_url, err=?e, "Readiness check could not reach the Portal node");
+            failing.push("portal_node");
         }
     }
 
-    Ok(Json(enrs))
+    let status = match failing.is_empty() {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    let ready = failing.is_empty();
+    (status, Json(ReadinessJson { ready, failing }))
 }
 
-pub async fn get_audit_stats_handler(
+/// Serves operational metrics in the Prometheus text format.
+///
+/// Audit counts are read from the database. Live audit process state, such as
+/// the queue depth, is only available when the audit process shares this state.
+pub async fn metrics(
     Extension(state): Extension<Arc<State>>,
-) -> Result<Json<Vec<audit_stats::Model>>, StatusCode> {
-    let stats = audit_stats::get_recent_stats(&state.database_connection)
-        .await
-        .map_err(|e| {
-            error!(err=?e, "Could not look up audit stat history");
-            StatusCode::INTERNAL_SERVER_ERROR
-        })
-        .unwrap();
+) -> Result<impl IntoResponse, StatusCode> {
+    let (total_audits, total_passes, total_failures) = tokio::join!(
+        content_audit::Entity::find().count(&state.database_connection),
+        content_audit::Entity::find()
+            .filter(content_audit::Column::Result.eq(AuditResult::Success))
+            .count(&state.database_connection),
+        // Failed requests to the node say nothing about the content, so they are left
+        // out of the failures, as they are on the dashboard.
+        content_audit::Entity::find()
+            .filter(content_audit::Column::Result.eq(AuditResult::Failure))
+            .filter(excludes_request_failures())
+            .count(&state.database_connection),
+    );
+    let total_audits = total_audits.map_err(|e| {
+        error!(err=?e, "Could not count audits");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let total_passes = total_passes.map_err(|e| {
+        error!(err=?e, "Could not count audit passes");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let total_failures = total_failures.map_err(|e| {
+        error!(err=?e, "Could not count audit failures");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
-    Ok(Json(stats))
+    let mut body = String::new();
+    write_metric(
+        &mut body,
+        "glados_audits_total",
+        "Total number of content audits performed.",
+        "counter",
+        total_audits,
+    );
+    write_metric(
+        &mut body,
+        "glados_audit_passes_total",
+        "Total number of content audits that passed.",
+        "counter",
+        total_passes,
+    );
+    write_metric(
+        &mut body,
+        "glados_audit_failures_total",
+        "Total number of content audits that failed.",
+        "counter",
+        total_failures,
+    );
+    // The live audit state is only known when the audit process runs in this
+    // process, so a standalone glados-web leaves these series out.
+    if let Some(audit_metrics) = &state.audit_metrics {
+        write_metric(
+            &mut body,
+            "glados_audit_queue_depth",
+            "Number of audit tasks waiting for a worker.",
+            "gauge",
+            audit_metrics.queue_depth.load(Ordering::Relaxed) as u64,
+        );
+        write_metric(
+            &mut body,
+            "glados_audit_queue_lag_seconds",
+            "Approximate age of the oldest audit task waiting for a worker.",
+            "gauge",
+            audit_metrics.queue_lag(Utc::now()).as_secs(),
+        );
+        write_metric(
+            &mut body,
+            "glados_audit_workers",
+            "Number of running audit workers.",
+            "gauge",
+            audit_metrics.workers.load(Ordering::Relaxed) as u64,
+        );
+        write_metric(
+            &mut body,
+            "glados_audit_active_workers",
+            "Number of audit workers busy with an audit.",
+            "gauge",
+            audit_metrics.active_workers.load(Ordering::Relaxed) as u64,
+        );
+        write_metric(
+            &mut body,
+            "glados_audit_malformed_content_keys_total",
+            "Number of stored content keys skipped because they could not be decoded.",
+            "counter",
+            audit_metrics.malformed_content_keys.load(Ordering::Relaxed) as u64,
+        );
+        write_metric(
+            &mut body,
+            "glados_audit_backpressure_skips_total",
+            "Number of content selection periods skipped because the audit workers were behind.",
+            "counter",
+            audit_metrics.backpressure_skips.load(Ordering::Relaxed) as u64,
+        );
+    }
+    Ok(([(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)], body))
+}
+
+/// Appends a single metric in the Prometheus text format.
+fn write_metric(body: &mut String, name: &str, help: &str, metric_type: &str, value: u64) {
+    // Writing to a String cannot fail.
+    let _ = writeln!(body, "# HELP {name} {help}");
+    let _ = writeln!(body, "# TYPE {name} {metric_type}");
+    let _ = writeln!(body, "{name} {value}");
 }
 
 pub async fn census_explorer_list(
     page: HttpQuery<HashMap<String, String>>,
     Extension(state): Extension<Arc<State>>,
-) -> Result<HtmlTemplate<PaginatedCensusListTemplate>, StatusCode> {
-    let max_census_id = match get_max_census_id(&state).await {
-        None => return Err(StatusCode::from_u16(404).unwrap()),
-        Some(max_census_id) => max_census_id,
-    };
+) -> Result<HtmlTemplate<PaginatedCensusListTemplate>, AppError> {
+    let max_census_id = get_max_census_id(&state)
+        .await
+        .ok_or_else(|| AppError::NotFound("No censuses found".to_string()))?;
 
-    let mut list_census_page_id: i32 = match page.get("page") {
-        None => return Err(StatusCode::from_u16(404).unwrap()),
-        Some(list_census_page_id) => match list_census_page_id.parse::<i32>() {
-            Ok(list_census_page_id) => list_census_page_id,
-            Err(_) => return Err(StatusCode::from_u16(404).unwrap()),
-        },
-    };
+    let page_id = page
+        .get("page")
+        .ok_or_else(|| AppError::BadRequest("Missing page number".to_string()))?;
+    let mut list_census_page_id: i32 = page_id
+        .parse()
+        .map_err(|_| AppError::BadRequest(format!("Invalid page number: {page_id}")))?;
 
     if list_census_page_id > max_census_id.id / 50 + 1 {
         list_census_page_id = max_census_id.id / 50 + 1;
@@ -1142,7 +3170,10 @@ pub async fn census_explorer_list(
         PaginatedCensusListResult::find_by_statement(builder.build(&paginated_census_list))
             .all(&state.database_connection)
             .await
-            .unwrap();
+            .map_err(|e| {
+                error!(err=?e, "Could not look up censuses");
+                AppError::Internal("Could not look up censuses".to_string())
+            })?;
 
     let template = PaginatedCensusListTemplate {
         census_data: paginated_census_list,
@@ -1192,10 +3223,15 @@ pub async fn census_timeseries(
     };
 
     // Load all censuses in the given 24 hour window with each node's presence status & ENR
+    let backend = state.database_connection.get_database_backend();
+    let dialect = Dialect::new(backend);
+    let window_end = Utc::now() - chrono::Duration::days(days_ago.into());
+    let window_start = window_end - chrono::Duration::days(1);
     let node_statuses: Vec<NodeStatus> =
         NodeStatus::find_by_statement(Statement::from_sql_and_values(
-            DbBackend::Postgres,
-            "SELECT 
+            backend,
+            &format!(
+                "SELECT 
                 c.started_at AS census_time, 
                 c.id AS census_id,
                 n.node_id,
@@ -1207,8 +3243,8 @@ pub async fn census_timeseries(
             FROM 
                 (
                     SELECT * FROM census
-                    WHERE started_at >= NOW() - INTERVAL '1 day' * ($1 + 1)
-                    AND started_at < NOW() - INTERVAL '1 day' * $1
+                    WHERE started_at >= {}
+                    AND started_at < {}
                 ) AS c
             LEFT JOIN 
                 census_node AS cn ON c.id = cn.census_id
@@ -1218,7 +3254,10 @@ pub async fn census_timeseries(
                 node AS n ON n.id = r.node_id
             ORDER BY 
                 c.started_at, n.node_id;",
-            vec![days_ago.into()],
+                dialect.placeholder(1),
+                dialect.placeholder(2),
+            ),
+            vec![window_start.into(), window_end.into()],
         ))
         .all(&state.database_connection)
         .await
@@ -1234,27 +3273,18 @@ pub async fn census_timeseries(
         .collect::<HashSet<i32>>() // Collect into a HashSet to remove duplicates
         .into_iter()
         .collect::<Vec<i32>>();
-    let record_ids_str = format!(
-        "{{{}}}",
-        record_ids
-            .iter()
-            .map(|id| id.to_string())
-            .collect::<Vec<_>>()
-            .join(",")
-    );
-    let records: Vec<RecordInfo> = RecordInfo::find_by_statement(Statement::from_sql_and_values(
-        DbBackend::Postgres,
-        "SELECT id, raw
-            FROM record
-            WHERE id = ANY($1::int[]);",
-        vec![record_ids_str.into()],
-    ))
-    .all(&state.database_connection)
-    .await
-    .map_err(|e| {
-        error!(err=?e, "Failed to lookup census node timeseries data");
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let records: Vec<RecordInfo> = record::Entity::find()
+        .select_only()
+        .column(record::Column::Id)
+        .column(record::Column::Raw)
+        .filter(record::Column::Id.is_in(record_ids))
+        .into_model::<RecordInfo>()
+        .all(&state.database_connection)
+        .await
+        .map_err(|e| {
+            error!(err=?e, "Failed to lookup census node timeseries data");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
     let enr_id_map: HashMap<i32, String> = records.into_iter().map(|r| (r.id, r.raw)).collect();
 
     let (node_ids, censuses) = decouple_nodes_and_censuses(node_statuses);
@@ -1314,42 +3344,471 @@ fn decouple_nodes_and_censuses(
 pub async fn single_census_view(
     census_id: HttpQuery<HashMap<String, String>>,
     Extension(state): Extension<Arc<State>>,
-) -> Result<HtmlTemplate<SingleCensusViewTemplate>, StatusCode> {
-    let max_census_id = match get_max_census_id(&state).await {
-        None => return Err(StatusCode::from_u16(404).unwrap()),
-        Some(max_census_id) => max_census_id,
-    };
+) -> Result<HtmlTemplate<SingleCensusViewTemplate>, AppError> {
+    let max_census_id = get_max_census_id(&state)
+        .await
+        .ok_or_else(|| AppError::NotFound("No censuses found".to_string()))?;
 
-    let census_id: Option<i32> = match census_id.get("census-id") {
-        None => return Err(StatusCode::from_u16(404).unwrap()),
-        Some(census_id) => match census_id.parse::<i32>() {
-            Ok(census_id) => Some(census_id),
-            Err(_) => Some(max_census_id.id),
-        },
-    };
+    let census_id: i32 = census_id
+        .get("census-id")
+        .ok_or_else(|| AppError::BadRequest("Missing census id".to_string()))?
+        .parse()
+        .unwrap_or(max_census_id.id);
 
     let client_diversity_data =
-        match generate_client_diversity_data(&state, census_id, max_census_id).await {
-            None => return Err(StatusCode::from_u16(404).unwrap()),
-            Some(client_diversity_data) => client_diversity_data,
-        };
+        generate_client_diversity_data(&state, Some(census_id), max_census_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("No census found with id {census_id}")))?;
 
-    let enr_list = match generate_enr_list_from_census_id(&state, census_id, max_census_id).await {
-        None => return Err(StatusCode::from_u16(404).unwrap()),
-        Some(enr_list) => enr_list,
-    };
+    let enr_list = generate_enr_list_from_census_id(&state, Some(census_id), max_census_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("No census found with id {census_id}")))?;
 
     let template = SingleCensusViewTemplate {
         client_diversity_data,
         node_count: enr_list.len() as i32,
         enr_list,
-        census_id: census_id.unwrap(),
+        census_id,
         max_census_id: max_census_id.id,
-        created_at: get_created_data_from_census_id(&state, census_id.unwrap())
-            .await
+        created_at: get_created_data_from_census_id(&state, census_id)
+            .await?
             .format("%Y-%m-%d %H:%M:%S UTC")
             .to_string(),
     };
 
     Ok(HtmlTemplate(template))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ethportal_api::{
+        types::{enr::Enr, node_id::NodeId},
+        utils::bytes::hex_decode,
+        BlockBodyKey,
+    };
+    use migration::{Migrator, MigratorTrait};
+
+    use super::*;
+    use crate::{
+        cli::PageLimits,
+        utils::{Cached, ProbeLimiter, NODE_VERSION_CACHE_TTL},
+    };
+
+    async fn test_state(portal_api: Option<PortalApi>) -> Arc<State> {
+        let (audit_feed, _) = broadcast::channel(1);
+        Arc::new(State {
+            database_connection: sea_orm::Database::connect("sqlite::memory:").await.unwrap(),
+            audit_metrics: None,
+            audit_trigger: None,
+            portal_api,
+            api_token: None,
+            raw_content_limiter: ProbeLimiter::per_minute(1),
+            node_version: Cached::new(NODE_VERSION_CACHE_TTL),
+            audit_feed,
+            page_limits: PageLimits::default(),
+            max_request_body_bytes: 1024,
+            query_limiter: None,
+        })
+    }
+
+    /// Serves `app` on a free local port, returning a client for it.
+    fn serve_mock_node(app: axum::Router) -> PortalApi {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let client_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+        PortalApi {
+            client_url,
+            request_timeout: Duration::from_secs(5),
+            max_retries: 0,
+        }
+    }
+
+    /// Starts a Portal node that answers every JSON-RPC request with `result`.
+    fn mock_node(result: serde_json::Value) -> PortalApi {
+        let app = axum::Router::new().route(
+            "/",
+            axum::routing::post(move |Json(request): Json<serde_json::Value>| {
+                let result = result.clone();
+                async move {
+                    Json(serde_json::json!({"jsonrpc": "2.0", "id": request["id"], "result": result}))
+                }
+            }),
+        );
+        serve_mock_node(app)
+    }
+
+    /// Starts a Portal node that answers every request with `503 Service Unavailable`.
+    fn failing_node() -> PortalApi {
+        let app = axum::Router::new().route(
+            "/",
+            axum::routing::post(|| async { StatusCode::SERVICE_UNAVAILABLE }),
+        );
+        serve_mock_node(app)
+    }
+
+    /// Mainnet block 1 as header content, without a proof.
+    fn block_1_header() -> Vec<u8> {
+        hex_decode(include_str!("../../glados-audit/testdata/block_1_header.hex").trim()).unwrap()
+    }
+
+    fn block_1_key() -> HistoryContentKey {
+        HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+            block_hash: decode_hex(
+                "0x88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6",
+            )
+            .unwrap()
+            .try_into()
+            .unwrap(),
+        })
+    }
+
+    /// Tests that the content returned by the node is checked against the content id
+    /// it was asked for.
+    #[tokio::test]
+    async fn test_raw_content_verifies_content_id() {
+        let other_key = HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+            block_hash: [1; 32],
+        });
+        for (content_key, verified) in [(block_1_key(), true), (other_key, false)] {
+            // The node answers with the block 1 header whichever key is asked for.
+            let node = mock_node(serde_json::json!({
+                "content": hex_encode(block_1_header()),
+                "utpTransfer": false,
+            }));
+            let mut state = Arc::into_inner(test_state(Some(node)).await).unwrap();
+            state.api_token = Some("secret".to_owned());
+            Migrator::up(&state.database_connection, None)
+                .await
+                .unwrap();
+            let content = content::get_or_create(&content_key, &state.database_connection)
+                .await
+                .unwrap();
+
+            let mut headers = HeaderMap::new();
+            headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+            let Json(raw) = raw_content(
+                Path(content.id_as_hex()),
+                headers,
+                Extension(Arc::new(state)),
+            )
+            .await
+            .unwrap();
+            assert_eq!(raw.raw, hex_encode(block_1_header()));
+            assert_eq!(raw.content_id_verified, Some(verified));
+        }
+    }
+
+    /// Tests that a header fetched from the node is decoded for the content id page.
+    #[tokio::test]
+    async fn test_fetch_decoded_header() {
+        let node = mock_node(serde_json::json!({
+            "content": hex_encode(block_1_header()),
+            "utpTransfer": false,
+        }));
+        let state = test_state(Some(node)).await;
+        let header = fetch_decoded_header(&block_1_key(), &state).await.unwrap();
+        assert_eq!(header.number, 1);
+        assert_eq!(
+            header.parent_hash,
+            "0xd4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"
+        );
+
+        // Other content types are not fetched.
+        let body_key = HistoryContentKey::BlockBody(BlockBodyKey {
+            block_hash: [1; 32],
+        });
+        assert_eq!(fetch_decoded_header(&body_key, &state).await, None);
+    }
+
+    /// Tests that a key given with a content id that is not derived from it is not stored.
+    #[tokio::test]
+    async fn test_insert_content_keys_checks_content_id() {
+        let mut state = Arc::into_inner(test_state(None).await).unwrap();
+        state.api_token = Some("secret".to_owned());
+        let state = Arc::new(state);
+        Migrator::up(&state.database_connection, None)
+            .await
+            .unwrap();
+        let content_key = block_1_key();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+
+        let inputs = vec![
+            ContentKeyInput::WithId {
+                content_key: content_key.to_hex(),
+                content_id: hex_encode([0; 32]),
+            },
+            ContentKeyInput::WithId {
+                content_key: content_key.to_hex(),
+                content_id: "0xzz".to_owned(),
+            },
+        ];
+        let Json(insertions) =
+            insert_content_keys(headers.clone(), Extension(state.clone()), Json(inputs))
+                .await
+                .unwrap();
+        let statuses: Vec<_> = insertions
+            .iter()
+            .map(|insertion| insertion.status)
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ContentKeyInsertionStatus::Mismatch,
+                ContentKeyInsertionStatus::Invalid
+            ]
+        );
+        let stored = content::get(&content_key, &state.database_connection)
+            .await
+            .unwrap();
+        assert_eq!(stored, None);
+
+        let inputs = vec![
+            ContentKeyInput::WithId {
+                content_key: content_key.to_hex(),
+                content_id: hex_encode(content_key.content_id()),
+            },
+            ContentKeyInput::Key(content_key.to_hex()),
+        ];
+        let Json(insertions) = insert_content_keys(headers, Extension(state), Json(inputs))
+            .await
+            .unwrap();
+        let statuses: Vec<_> = insertions
+            .iter()
+            .map(|insertion| insertion.status)
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ContentKeyInsertionStatus::Inserted,
+                ContentKeyInsertionStatus::Duplicate
+            ]
+        );
+    }
+
+    /// Tests that the authenticated endpoints are not found when no token is configured,
+    /// and turn away requests without the configured token.
+    #[tokio::test]
+    async fn test_require_api_token() {
+        let mut headers = HeaderMap::new();
+        let mut state = Arc::into_inner(test_state(None).await).unwrap();
+        assert_eq!(
+            require_api_token(&headers, &state),
+            Err(StatusCode::NOT_FOUND)
+        );
+
+        state.api_token = Some("secret".to_owned());
+        assert_eq!(
+            require_api_token(&headers, &state),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        assert_eq!(require_api_token(&headers, &state), Ok(()));
+    }
+
+    /// Tests that failures are compared against the latest radius each node advertised.
+    #[tokio::test]
+    async fn test_failure_distances() {
+        let state = test_state(None).await;
+        let conn = &state.database_connection;
+        Migrator::up(conn, None).await.unwrap();
+        let enr: Enr = "enr:-IS4QHCYrYZbAKWCBRlAy5zzaDZXJBGkcnh4MHcBFZntXNFrdvJjX04jRzjzCBOonrkTfj499SZuOh8R33Ls8RRcy5wBgmlkgnY0gmlwhH8AAAGJc2VjcDI1NmsxoQPKY0yuDUmstAHYpMa2_oxVtw0RW_QAdpzBQA8yWM0xOIN1ZHCCdl8"
+            .parse()
+            .unwrap();
+        let record = record::get_or_create(&enr, conn).await.unwrap();
+        let census = census::create(Utc::now(), 60, conn).await.unwrap();
+        // The node advertised the largest radius, and then none.
+        for radius in [crate::U256::MAX, crate::U256::zero()] {
+            census_node::create(census.id, record.id, radius, Utc::now(), conn)
+                .await
+                .unwrap();
+        }
+        let client_info = client_info::get_or_create("trin v0.1.0".to_owned(), conn)
+            .await
+            .unwrap();
+        let content_key = HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+            block_hash: [1; 32],
+        });
+        let content = content::get_or_create(&content_key, conn).await.unwrap();
+        content_audit::create(
+            content.id,
+            client_info.id,
+            record.node_id,
+            AuditOutcome::NotFound,
+            content_audit::SelectionStrategy::Latest,
+            "".to_owned(),
+            None,
+            None,
+            None,
+            None,
+            conn,
+        )
+        .await
+        .unwrap();
+
+        let params = RecentAuditsParams {
+            limit: None,
+            since: None,
+            label: None,
+        };
+        let Json(report) = failure_distances(HttpQuery(params), Extension(state.clone()))
+            .await
+            .unwrap();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].radius, Some(hex_encode([0; 32])));
+        assert_eq!((report[0].inside_radius, report[0].outside_radius), (0, 1));
+    }
+
+    /// Tests that an audit of content without execution metadata still has a detail page.
+    #[tokio::test]
+    async fn test_contentaudit_detail_without_metadata() {
+        use askama::Template;
+
+        let state = test_state(None).await;
+        let conn = &state.database_connection;
+        Migrator::up(conn, None).await.unwrap();
+        let client_info = client_info::get_or_create("trin v0.1.0".to_owned(), conn)
+            .await
+            .unwrap();
+        let content_key = HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+            block_hash: [1; 32],
+        });
+        let node = node::get_or_create(NodeId::random(), conn).await.unwrap();
+        let content = content::get_or_create(&content_key, conn).await.unwrap();
+        let audit = content_audit::create(
+            content.id,
+            client_info.id,
+            node.id,
+            AuditOutcome::NotFound,
+            content_audit::SelectionStrategy::Latest,
+            "".to_owned(),
+            None,
+            None,
+            None,
+            None,
+            conn,
+        )
+        .await
+        .unwrap();
+
+        let HtmlTemplate(template) =
+            contentaudit_detail(Path(audit.id.to_string()), Extension(state.clone()))
+                .await
+                .unwrap();
+        assert!(template.execution_metadata.is_none());
+        template.render().unwrap();
+    }
+
+    /// Tests that a CSV export keeps its query limit slot until the body has been sent.
+    #[tokio::test]
+    async fn test_audits_csv_holds_query_permit() {
+        use tower::ServiceExt;
+
+        let mut state = Arc::into_inner(test_state(None).await).unwrap();
+        Migrator::up(&state.database_connection, None)
+            .await
+            .unwrap();
+        let query_limiter = Arc::new(tokio::sync::Semaphore::new(1));
+        state.query_limiter = Some(query_limiter.clone());
+        let app = axum::Router::new()
+            .route("/api/audits.csv", axum::routing::get(audits_csv))
+            .route_layer(axum::middleware::from_fn(crate::limit_concurrent_queries))
+            .layer(Extension(Arc::new(state)));
+
+        let request = axum::http::Request::builder()
+            .uri("/api/audits.csv")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(query_limiter.available_permits(), 0);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, AuditCsvExport::HEADER);
+        assert_eq!(query_limiter.available_permits(), 1);
+    }
+
+    /// Tests that a Portal node that fails to answer is not asked again on the next
+    /// request, and is reported as `null`.
+    #[tokio::test]
+    async fn test_version_caches_failures() {
+        let state = test_state(None).await;
+        let Json(version) = version(Extension(state.clone())).await;
+        assert_eq!(version.glados_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(version.node_client_version, None);
+        assert_eq!(state.node_version.get(), None);
+
+        let state = test_state(Some(failing_node())).await;
+        let Json(version) = version(Extension(state.clone())).await;
+        assert_eq!(version.node_client_version, None);
+        assert_eq!(state.node_version.get(), Some(None));
+
+        state.node_version.set(Some("trin v0.1.0".to_owned()));
+        let Json(version) = version(Extension(state)).await;
+        assert_eq!(version.node_client_version.as_deref(), Some("trin v0.1.0"));
+    }
+
+    /// Tests that sort keys are read, and unknown ones fall back to newest first.
+    #[test]
+    fn test_dashboard_sort_from_param() {
+        let sort =
+            |value: Option<&str>| DashboardSort::from_param(value.map(str::to_owned).as_ref());
+        assert_eq!(sort(None), DashboardSort::Newest);
+        assert_eq!(sort(Some("newest")), DashboardSort::Newest);
+        assert_eq!(sort(Some("oldest")), DashboardSort::Oldest);
+        assert_eq!(sort(Some("by-id")), DashboardSort::ById);
+        assert_eq!(sort(Some("sideways")), DashboardSort::Newest);
+    }
+
+    /// Tests that the dashboard links change one parameter and keep the others.
+    #[test]
+    fn test_dashboard_query_links() {
+        let params: HashMap<String, String> = [
+            ("result", "fail"),
+            ("type", "body"),
+            ("content_sort", "oldest"),
+            ("audit_sort", "by-id"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_owned(), value.to_owned()))
+        .collect();
+        let query = DashboardQuery::from_params(&params);
+        assert_eq!(
+            query.with("content_sort", "newest"),
+            "?content_sort=newest&audit_sort=by-id&result=fail&type=body"
+        );
+        assert_eq!(
+            query.with("result", "pass"),
+            "?content_sort=oldest&audit_sort=by-id&result=pass&type=body"
+        );
+        assert_eq!(
+            query.without_filters(),
+            "?content_sort=oldest&audit_sort=by-id"
+        );
+
+        // The label is kept by every link, and encoded.
+        let mut params = params;
+        params.insert("label".to_owned(), "eu west".to_owned());
+        let query = DashboardQuery::from_params(&params);
+        assert_eq!(
+            query.with("type", "header"),
+            "?content_sort=oldest&audit_sort=by-id&result=fail&type=header&label=eu+west"
+        );
+        assert_eq!(
+            query.without_filters(),
+            "?content_sort=oldest&audit_sort=by-id&label=eu+west"
+        );
+
+        let query = DashboardQuery::from_params(&HashMap::new());
+        assert_eq!(
+            query.with("type", "header"),
+            "?content_sort=newest&audit_sort=newest&type=header"
+        );
+    }
+}
@@ -1,5 +1,53 @@
+use std::sync::Arc;
+
+use glados_core::{jsonrpc::PortalApi, metrics::AuditMetrics, trigger::AuditTrigger};
 use sea_orm::DatabaseConnection;
+use tokio::sync::{broadcast, OwnedSemaphorePermit, Semaphore};
+
+use crate::{
+    cli::PageLimits,
+    routes::AuditJson,
+    utils::{Cached, ProbeLimiter},
+};
 
 pub struct State {
     pub database_connection: DatabaseConnection,
+    /// Present when the audit process runs alongside the web server.
+    pub audit_metrics: Option<Arc<AuditMetrics>>,
+    /// Runs a selection cycle on demand, present when the audit process runs alongside.
+    pub audit_trigger: Option<AuditTrigger>,
+    /// Portal node checked by the readiness probe and asked for raw content, if configured.
+    pub portal_api: Option<PortalApi>,
+    /// Bearer token required by the authenticated API endpoints, which are disabled if unset.
+    pub api_token: Option<String>,
+    /// Limits how often raw content is fetched from the Portal node.
+    pub raw_content_limiter: ProbeLimiter,
+    /// Client version last reported by the Portal node, or `None` if it could not be
+    /// reached when last asked.
+    pub node_version: Cached<Option<String>>,
+    /// New audits, published for live feed subscribers.
+    pub audit_feed: broadcast::Sender<AuditJson>,
+    pub page_limits: PageLimits,
+    /// Largest request body accepted, in bytes.
+    pub max_request_body_bytes: usize,
+    /// Bounds the requests that may query the database at once, if set, so that the
+    /// dashboard cannot starve the audit process of connections.
+    pub query_limiter: Option<Arc<Semaphore>>,
+}
+
+/// A slot of the query limit, taken for as long as any clone of it is alive.
+///
+/// Handlers that stream their response keep a clone in the stream, so that the slot
+/// is only released once the body has been sent.
+#[derive(Clone)]
+pub struct QueryPermit {
+    _permit: Arc<OwnedSemaphorePermit>,
+}
+
+impl QueryPermit {
+    pub fn new(permit: OwnedSemaphorePermit) -> Self {
+        QueryPermit {
+            _permit: Arc::new(permit),
+        }
+    }
 }
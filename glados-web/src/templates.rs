@@ -3,12 +3,18 @@ use axum::{
     http::StatusCode,
     response::{Html, IntoResponse, Response},
 };
-use entity::{client_info, content, content_audit, execution_metadata, key_value, node, record};
+use entity::{
+    census_result, client_info, content,
+    content_audit::{self, Availability},
+    execution_metadata, key_value, node, record,
+};
 
 use crate::routes::{
-    CalculatedRadiusChartData, ClientDiversityResult, PaginatedCensusListResult, RawEnr,
+    CalculatedRadiusChartData, ClientDiversityResult, DashboardQuery, PaginatedCensusListResult,
+    PipelineHealth, RawEnr,
 };
-use glados_core::stats::AuditStats;
+use crate::utils::DecodedHeader;
+use glados_core::stats::{AuditLatency, AuditStats, ContentTypeBreakdown, FailureCauses, Totals};
 
 #[derive(Template)]
 #[template(path = "index.html")]
@@ -16,6 +22,7 @@ pub struct IndexTemplate {
     pub client_diversity_data: Vec<ClientDiversityResult>,
     pub average_radius_chart: Vec<CalculatedRadiusChartData>,
     pub stats: [AuditStats; 3],
+    pub totals: Totals,
 }
 
 #[derive(Template)]
@@ -50,6 +57,21 @@ pub struct NetworkDashboardTemplate {
     pub recent_enr_list: Vec<(record::Model, node::Model)>,
 }
 
+#[derive(Template)]
+#[template(path = "node_list.html")]
+pub struct NodeListTemplate {
+    pub nodes: Vec<node::Model>,
+    pub page: u64,
+    pub page_size: u64,
+    /// The `?sort=` value of the list.
+    pub sort: &'static str,
+    /// The `&alive=` query parameter of the list, or empty when not filtering.
+    pub alive_query: String,
+    pub alive_filter: Option<bool>,
+    pub total_node_count: u64,
+    pub has_next: bool,
+}
+
 #[derive(Template)]
 #[template(path = "node_detail.html")]
 pub struct NodeDetailTemplate {
@@ -58,6 +80,8 @@ pub struct NodeDetailTemplate {
     pub latest_enr_key_value_list: Option<Vec<key_value::Model>>,
     pub enr_list: Vec<record::Model>,
     pub closest_node_list: Vec<node::ModelWithDistance>,
+    pub census_results: Vec<census_result::Model>,
+    pub recent_audits: Vec<AuditTuple>,
 }
 
 #[derive(Template)]
@@ -74,11 +98,19 @@ pub type AuditTuple = (content_audit::Model, content::Model, client_info::Model)
 #[template(path = "content_dashboard.html")]
 pub struct ContentDashboardTemplate {
     pub stats: [AuditStats; 3],
+    pub failure_causes: FailureCauses,
+    pub content_types: ContentTypeBreakdown,
+    /// Latency over the last day, by type of content.
+    pub latency: AuditLatency,
     pub contentid_list: Vec<content::Model>,
     pub audits_of_recent_content: Vec<AuditTuple>,
     pub recent_audits: Vec<AuditTuple>,
+    /// Filters of the recent audits and sorts of both lists.
+    pub query: DashboardQuery,
     pub recent_audit_successes: Vec<AuditTuple>,
     pub recent_audit_failures: Vec<AuditTuple>,
+    /// Absent when the audit process runs separately.
+    pub pipeline: Option<PipelineHealth>,
 }
 
 #[derive(Template)]
@@ -91,7 +123,18 @@ pub struct ContentIdListTemplate {
 #[template(path = "contentid_detail.html")]
 pub struct ContentIdDetailTemplate {
     pub content_id: content::Model,
-    pub contentkey_list: Vec<content::Model>,
+    /// The block header, decoded from content fetched from the Portal node, if the
+    /// content is a header that could be fetched.
+    pub header: Option<DecodedHeader>,
+    /// Each content key with its audits, newest first, and whether it is available.
+    pub contentkey_list: Vec<(content::Model, Vec<content_audit::Model>, Availability)>,
+}
+
+#[derive(Template)]
+#[template(path = "content_search.html")]
+pub struct ContentSearchTemplate {
+    pub block_hash: String,
+    pub content_id: String,
 }
 
 #[derive(Template)]
@@ -99,7 +142,8 @@ pub struct ContentIdDetailTemplate {
 pub struct ContentAuditDetailTemplate {
     pub audit: content_audit::Model,
     pub content: content::Model,
-    pub execution_metadata: execution_metadata::Model,
+    /// The block of the content, unknown for keys that were inserted without one.
+    pub execution_metadata: Option<execution_metadata::Model>,
 }
 
 #[derive(Template)]
@@ -119,6 +163,15 @@ pub struct AuditTableTemplate {
     pub audits: Vec<AuditTuple>,
 }
 
+#[derive(Template)]
+#[template(path = "audit_comparison.html")]
+pub struct AuditComparisonTemplate {
+    /// The Portal nodes that performed audits, one column each.
+    pub nodes: Vec<node::Model>,
+    /// Each content item with its latest audit from every node, in column order.
+    pub rows: Vec<(content::Model, Vec<Option<content_audit::Model>>)>,
+}
+
 #[derive(Template)]
 #[template(path = "contentkey_detail.html")]
 pub struct ContentKeyDetailTemplate {
@@ -128,6 +181,14 @@ pub struct ContentKeyDetailTemplate {
     pub content_kind: String,
     pub block_number: Option<i32>,
     pub contentaudit_list: Vec<content_audit::Model>,
+    pub availability: Availability,
+}
+
+#[derive(Template)]
+#[template(path = "error.html")]
+pub struct ErrorTemplate {
+    pub status: u16,
+    pub message: String,
 }
 
 pub struct HtmlTemplate<T: Template>(pub T);
@@ -0,0 +1,206 @@
+//! Serving glados-web over HTTPS, for deployments without a reverse proxy.
+use std::{fs::File, io::BufReader, net::SocketAddr, path::Path, sync::Arc};
+
+use anyhow::{anyhow, bail, Context, Result};
+use axum::Router;
+use hyper::server::conn::Http;
+use rustls_pemfile::Item;
+use tokio::{
+    net::TcpListener,
+    time::{sleep, timeout, Duration},
+};
+use tokio_rustls::{
+    rustls::{sign::any_supported_type, Certificate, PrivateKey, ServerConfig, SignatureScheme},
+    TlsAcceptor,
+};
+use tracing::{debug, warn};
+
+/// Loads a PEM encoded certificate chain and private key for serving HTTPS.
+///
+/// Fails with the path of the file at fault if either cannot be read, or if the key
+/// does not belong to the certificate.
+pub fn load_tls_config(cert_path: &Path, key_path: &Path) -> Result<Arc<ServerConfig>> {
+    let certs = rustls_pemfile::certs(&mut open(cert_path)?)
+        .with_context(|| format!("Could not read TLS certificate {}", cert_path.display()))?;
+    if certs.is_empty() {
+        bail!("No PEM certificate found in {}", cert_path.display());
+    }
+    let key = rustls_pemfile::read_all(&mut open(key_path)?)
+        .with_context(|| format!("Could not read TLS private key {}", key_path.display()))?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::RSAKey(key) | Item::PKCS8Key(key) | Item::ECKey(key) => Some(key),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("No PEM private key found in {}", key_path.display()))?;
+    let certs: Vec<Certificate> = certs.into_iter().map(Certificate).collect();
+    let key = PrivateKey(key);
+    check_key_matches(&certs[0], &key).with_context(|| {
+        format!(
+            "TLS private key {} does not match certificate {}",
+            key_path.display(),
+            cert_path.display()
+        )
+    })?;
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .with_context(|| {
+            format!(
+                "Could not use TLS certificate {} with private key {}",
+                cert_path.display(),
+                key_path.display()
+            )
+        })?;
+    Ok(Arc::new(config))
+}
+
+/// Message signed with the private key to check it against the certificate.
+const KEY_CHECK_MESSAGE: &[u8] = b"glados-web TLS key check";
+
+/// Signature schemes offered to the private key when checking it, one for each type
+/// of key that rustls loads.
+const KEY_CHECK_SCHEMES: [SignatureScheme; 4] = [
+    SignatureScheme::ECDSA_NISTP256_SHA256,
+    SignatureScheme::ECDSA_NISTP384_SHA384,
+    SignatureScheme::ED25519,
+    SignatureScheme::RSA_PKCS1_SHA256,
+];
+
+/// The algorithm verifying a signature made with one of [KEY_CHECK_SCHEMES].
+fn verification_algorithm(scheme: SignatureScheme) -> Option<&'static webpki::SignatureAlgorithm> {
+    match scheme {
+        SignatureScheme::ECDSA_NISTP256_SHA256 => Some(&webpki::ECDSA_P256_SHA256),
+        SignatureScheme::ECDSA_NISTP384_SHA384 => Some(&webpki::ECDSA_P384_SHA384),
+        SignatureScheme::ED25519 => Some(&webpki::ED25519),
+        SignatureScheme::RSA_PKCS1_SHA256 => Some(&webpki::RSA_PKCS1_2048_8192_SHA256),
+        _ => None,
+    }
+}
+
+/// Checks that the private key belongs to the leaf certificate, by signing a message
+/// with the key and verifying the signature with the certificate's public key.
+///
+/// rustls only checks that the key can be parsed, so a mismatched pair would load and
+/// then fail every handshake.
+fn check_key_matches(leaf: &Certificate, key: &PrivateKey) -> Result<()> {
+    let signing_key =
+        any_supported_type(key).map_err(|_| anyhow!("unsupported type of private key"))?;
+    let signer = signing_key
+        .choose_scheme(&KEY_CHECK_SCHEMES)
+        .ok_or_else(|| anyhow!("private key does not support a known signature scheme"))?;
+    let algorithm = verification_algorithm(signer.scheme())
+        .ok_or_else(|| anyhow!("private key chose an unknown signature scheme"))?;
+    let signature = signer
+        .sign(KEY_CHECK_MESSAGE)
+        .map_err(|e| anyhow!("could not sign with private key: {e}"))?;
+    let cert = webpki::EndEntityCert::try_from(leaf.0.as_slice())
+        .map_err(|e| anyhow!("could not parse certificate: {e:?}"))?;
+    cert.verify_signature(algorithm, KEY_CHECK_MESSAGE, &signature)
+        .map_err(|_| anyhow!("signature by private key does not verify with certificate"))
+}
+
+fn open(path: &Path) -> Result<BufReader<File>> {
+    let file = File::open(path).with_context(|| format!("Could not open {}", path.display()))?;
+    Ok(BufReader::new(file))
+}
+
+/// Wait after failing to accept a connection, such as when out of file descriptors.
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Time a client is given to complete the TLS handshake, so that clients stalling it
+/// do not hold connections open.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serves the app over HTTPS.
+///
+/// Connections that fail the TLS handshake, or do not finish it within
+/// [HANDSHAKE_TIMEOUT], are logged and dropped.
+pub(crate) async fn serve_tls(
+    app: Router,
+    socket: SocketAddr,
+    tls_config: Arc<ServerConfig>,
+) -> Result<()> {
+    let acceptor = TlsAcceptor::from(tls_config);
+    let listener = TcpListener::bind(socket).await?;
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!(err=%e, "Could not accept connection.");
+                sleep(ACCEPT_RETRY_DELAY).await;
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+        tokio::spawn(async move {
+            let stream = match timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => stream,
+                Ok(Err(e)) => {
+                    debug!(peer=%peer, err=%e, "TLS handshake failed.");
+                    return;
+                }
+                Err(_) => {
+                    debug!(peer=%peer, "TLS handshake timed out.");
+                    return;
+                }
+            };
+            // Upgrades are needed for the live audit feed's websockets.
+            if let Err(e) = Http::new()
+                .serve_connection(stream, app)
+                .with_upgrades()
+                .await
+            {
+                debug!(peer=%peer, err=%e, "Connection closed with an error.");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn testdata(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/tls")
+            .join(name)
+    }
+
+    fn load_error(cert: &str, key: &str) -> String {
+        let err = load_tls_config(&testdata(cert), &testdata(key)).unwrap_err();
+        format!("{err:#}")
+    }
+
+    #[test]
+    fn test_load_tls_config() {
+        assert!(load_tls_config(&testdata("cert.pem"), &testdata("key.pem")).is_ok());
+    }
+
+    #[test]
+    fn test_load_tls_config_missing_file() {
+        assert!(load_error("missing.pem", "key.pem").contains("Could not open"));
+        assert!(load_error("cert.pem", "missing.pem").contains("Could not open"));
+    }
+
+    #[test]
+    fn test_load_tls_config_without_cert() {
+        assert!(load_error("key.pem", "key.pem").contains("No PEM certificate"));
+    }
+
+    #[test]
+    fn test_load_tls_config_without_key() {
+        assert!(load_error("cert.pem", "cert.pem").contains("No PEM private key"));
+    }
+
+    /// Tests that a key of another certificate is rejected when loading, rather than
+    /// failing each handshake.
+    #[test]
+    fn test_load_tls_config_mismatched_key() {
+        assert!(load_error("cert.pem", "other_key.pem").contains("does not match"));
+    }
+}
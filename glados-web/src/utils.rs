@@ -0,0 +1,305 @@
+use std::{sync::Mutex, time::Duration};
+
+use chrono::{DateTime, TimeZone, Utc};
+use ethportal_api::utils::bytes::hex_encode;
+use ethportal_api::{ContentValue, HistoryContentKey, HistoryContentValue};
+use thiserror::Error;
+use tokio::time::Instant;
+
+/// Why a hex string from a request could not be decoded.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum HexError {
+    #[error("hex string has an odd number of digits ({0})")]
+    OddLength(usize),
+    #[error("invalid hex character {character:?} at position {index}")]
+    InvalidCharacter { character: char, index: usize },
+}
+
+/// Decodes a hex string, with or without a `0x`/`0X` prefix.
+pub fn decode_hex(value: &str) -> Result<Vec<u8>, HexError> {
+    let digits = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+    if digits.len() % 2 != 0 {
+        return Err(HexError::OddLength(digits.len()));
+    }
+    let nibbles = digits
+        .chars()
+        .enumerate()
+        .map(|(index, character)| {
+            character
+                .to_digit(16)
+                .map(|nibble| nibble as u8)
+                .ok_or(HexError::InvalidCharacter { character, index })
+        })
+        .collect::<Result<Vec<u8>, HexError>>()?;
+    Ok(nibbles
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair[1])
+        .collect())
+}
+
+/// Compares a token from a request with the configured one.
+///
+/// Every byte is compared, so the time taken does not reveal how much of the token matched.
+pub fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// The fields of a block header shown on the content id page.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedHeader {
+    pub number: u64,
+    pub parent_hash: String,
+    pub state_root: String,
+    /// Seconds since the unix epoch, as given in the header.
+    pub timestamp: u64,
+}
+
+impl DecodedHeader {
+    pub fn time(&self) -> Option<DateTime<Utc>> {
+        Utc.timestamp_opt(i64::try_from(self.timestamp).ok()?, 0)
+            .single()
+    }
+}
+
+/// Decodes the header content of a header key.
+///
+/// Returns `None` for other content keys, and for content that is not a header
+/// hashing to the block hash in the key.
+pub fn decode_header(content_key: &HistoryContentKey, raw: &[u8]) -> Option<DecodedHeader> {
+    let HistoryContentKey::BlockHeaderWithProof(key) = content_key else {
+        return None;
+    };
+    let Ok(HistoryContentValue::BlockHeaderWithProof(h)) = HistoryContentValue::decode(raw) else {
+        return None;
+    };
+    if h.header.hash().0 != key.block_hash {
+        return None;
+    }
+    Some(DecodedHeader {
+        number: h.header.number,
+        parent_hash: hex_encode(h.header.parent_hash),
+        state_root: hex_encode(h.header.state_root),
+        timestamp: h.header.timestamp,
+    })
+}
+
+/// Limits how often requests may make live calls to the Portal node.
+///
+/// Requests over the limit are turned away rather than queued.
+#[derive(Debug)]
+pub struct ProbeLimiter {
+    min_interval: Duration,
+    last_probe: Mutex<Option<Instant>>,
+}
+
+impl ProbeLimiter {
+    pub fn per_minute(probes: u32) -> Self {
+        ProbeLimiter {
+            min_interval: Duration::from_secs(60) / probes.max(1),
+            last_probe: Mutex::new(None),
+        }
+    }
+
+    /// Takes the next probe, or returns false if the previous one was too recent.
+    pub fn try_acquire(&self) -> bool {
+        let now = Instant::now();
+        let mut last_probe = self.last_probe.lock().unwrap();
+        match *last_probe {
+            Some(last) if now.duration_since(last) < self.min_interval => false,
+            _ => {
+                *last_probe = Some(now);
+                true
+            }
+        }
+    }
+}
+
+/// How long the Portal node's client version is served from the cache.
+pub const NODE_VERSION_CACHE_TTL: Duration = Duration::from_secs(60);
+/// How long a failure to reach the Portal node for its client version is remembered,
+/// so that a node that is down is not called on every request.
+pub const NODE_VERSION_FAILURE_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// Holds a value for a short time, so that it is not fetched from the Portal node
+/// on every request.
+#[derive(Debug)]
+pub struct Cached<T> {
+    ttl: Duration,
+    /// The value and when it expires.
+    entry: Mutex<Option<(Instant, T)>>,
+}
+
+impl<T: Clone> Cached<T> {
+    pub fn new(ttl: Duration) -> Self {
+        Cached {
+            ttl,
+            entry: Mutex::new(None),
+        }
+    }
+
+    /// Returns the value, or `None` if it was never set or has expired.
+    pub fn get(&self) -> Option<T> {
+        match &*self.entry.lock().unwrap() {
+            Some((expires_at, value)) if Instant::now() < *expires_at => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn set(&self, value: T) {
+        self.set_for(value, self.ttl);
+    }
+
+    /// Stores a value that expires after `ttl` instead of the usual time.
+    pub fn set_for(&self, value: T, ttl: Duration) {
+        *self.entry.lock().unwrap() = Some((Instant::now() + ttl, value));
+    }
+}
+
+/// Parses a lookback window such as `7d` or `24h`, a bare number being hours.
+///
+/// Windows longer than `max` are shortened to it. Returns `None` for windows that are
+/// empty, zero or not a whole number of hours or days.
+pub fn parse_window(value: &str, max: chrono::Duration) -> Option<chrono::Duration> {
+    let (amount, hours_per_unit) = match value.strip_suffix('d') {
+        Some(days) => (days, 24),
+        None => (value.strip_suffix('h').unwrap_or(value), 1),
+    };
+    match amount.parse::<i64>().ok()? {
+        amount if amount < 1 => None,
+        // Capped before building the duration, which panics if it overflows.
+        amount => Some(chrono::Duration::hours(
+            amount.saturating_mul(hours_per_unit).min(max.num_hours()),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethportal_api::{BlockBodyKey, BlockHeaderKey};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_probe_limiter() {
+        tokio::time::pause();
+        let limiter = ProbeLimiter::per_minute(1);
+        assert!(limiter.try_acquire());
+        assert!(!limiter.try_acquire());
+
+        let limiter = ProbeLimiter::per_minute(60_000);
+        assert!(limiter.try_acquire());
+        assert!(!limiter.try_acquire());
+        tokio::time::advance(Duration::from_millis(1)).await;
+        assert!(limiter.try_acquire());
+    }
+
+    #[test]
+    fn test_cached() {
+        let cached = Cached::new(Duration::from_secs(60));
+        assert_eq!(cached.get(), None);
+        cached.set("trin v0.1.0".to_string());
+        assert_eq!(cached.get().as_deref(), Some("trin v0.1.0"));
+
+        let expired = Cached::new(Duration::ZERO);
+        expired.set("trin v0.1.0".to_string());
+        assert_eq!(expired.get(), None);
+
+        cached.set_for("trin v0.1.1".to_string(), Duration::ZERO);
+        assert_eq!(cached.get(), None);
+    }
+
+    #[test]
+    fn test_parse_window() {
+        let max = chrono::Duration::days(365);
+        assert_eq!(parse_window("7d", max), Some(chrono::Duration::days(7)));
+        assert_eq!(parse_window("24h", max), Some(chrono::Duration::hours(24)));
+        assert_eq!(parse_window("6", max), Some(chrono::Duration::hours(6)));
+        assert_eq!(parse_window("400d", max), Some(max));
+        assert_eq!(parse_window("3000000000000000h", max), Some(max));
+        assert_eq!(parse_window(&format!("{}d", i64::MAX), max), Some(max));
+        assert_eq!(parse_window("0d", max), None);
+        assert_eq!(parse_window("-1h", max), None);
+        assert_eq!(parse_window("d", max), None);
+        assert_eq!(parse_window("1w", max), None);
+        assert_eq!(parse_window("", max), None);
+    }
+
+    #[test]
+    fn test_decode_hex_prefixed() {
+        assert_eq!(decode_hex("0x00ff1a"), Ok(vec![0x00, 0xff, 0x1a]));
+        assert_eq!(decode_hex("0XABcd"), Ok(vec![0xab, 0xcd]));
+        assert_eq!(decode_hex("0x"), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_decode_hex_unprefixed() {
+        assert_eq!(decode_hex("00ff1a"), Ok(vec![0x00, 0xff, 0x1a]));
+        assert_eq!(decode_hex(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_decode_hex_odd_length() {
+        assert_eq!(decode_hex("0xabc"), Err(HexError::OddLength(3)));
+        assert_eq!(decode_hex("a"), Err(HexError::OddLength(1)));
+    }
+
+    #[test]
+    fn test_decode_hex_invalid_character() {
+        assert_eq!(
+            decode_hex("0xzz"),
+            Err(HexError::InvalidCharacter {
+                character: 'z',
+                index: 0
+            })
+        );
+        assert_eq!(
+            decode_hex("12g4"),
+            Err(HexError::InvalidCharacter {
+                character: 'g',
+                index: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_header_rejects_other_content() {
+        let header_key = HistoryContentKey::BlockHeaderWithProof(BlockHeaderKey {
+            block_hash: [1; 32],
+        });
+        assert_eq!(decode_header(&header_key, &[0xff; 8]), None);
+        let body_key = HistoryContentKey::BlockBody(BlockBodyKey {
+            block_hash: [1; 32],
+        });
+        assert_eq!(decode_header(&body_key, &[]), None);
+    }
+
+    #[test]
+    fn test_decoded_header_time() {
+        let header = DecodedHeader {
+            number: 1,
+            parent_hash: "0x00".to_string(),
+            state_root: "0x00".to_string(),
+            timestamp: 1438269988,
+        };
+        assert_eq!(
+            header.time().unwrap().to_rfc3339(),
+            "2015-07-30T15:26:28+00:00"
+        );
+    }
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("s3cret", "s3cret"));
+        assert!(!tokens_match("s3creT", "s3cret"));
+        assert!(!tokens_match("s3cre", "s3cret"));
+        assert!(!tokens_match("", "s3cret"));
+    }
+}
@@ -10,6 +10,25 @@ mod m20230511_104838_create_execution_metadata;
 mod m20230511_104937_create_key_value;
 mod m20231107_004843_create_audit_stats;
 mod m20240213_190221_add_fourfours_stats;
+mod m20240304_153212_add_audit_duration;
+mod m20240311_101530_add_audit_outcome;
+mod m20240318_120000_create_census_result;
+mod m20240325_090000_add_node_client_version;
+mod m20240401_100000_add_audit_served_by;
+mod m20240408_100000_create_routing_table_snapshot;
+mod m20240415_100000_add_node_last_seen;
+mod m20240422_100000_add_audit_error;
+mod m20240429_100000_add_node_address;
+mod m20240506_100000_add_content_key_unique_index;
+mod m20240513_100000_add_content_audit_counts;
+mod m20240520_100000_add_audit_label;
+mod m20240527_100000_add_node_enr;
+mod m20240603_100000_add_audit_closer_peer_responses;
+mod m20240610_100000_add_audit_content_length;
+mod m20240617_100000_add_audit_content_id_verified;
+mod m20240624_100000_recount_content_audits;
+mod m20240701_100000_backfill_node_enr;
+mod m20240708_100000_add_audit_stats_label;
 
 pub struct Migrator;
 
@@ -27,6 +46,25 @@ impl MigratorTrait for Migrator {
             Box::new(m20230508_111707_create_census_tables::Migration),
             Box::new(m20231107_004843_create_audit_stats::Migration),
             Box::new(m20240213_190221_add_fourfours_stats::Migration),
+            Box::new(m20240304_153212_add_audit_duration::Migration),
+            Box::new(m20240311_101530_add_audit_outcome::Migration),
+            Box::new(m20240318_120000_create_census_result::Migration),
+            Box::new(m20240325_090000_add_node_client_version::Migration),
+            Box::new(m20240401_100000_add_audit_served_by::Migration),
+            Box::new(m20240408_100000_create_routing_table_snapshot::Migration),
+            Box::new(m20240415_100000_add_node_last_seen::Migration),
+            Box::new(m20240422_100000_add_audit_error::Migration),
+            Box::new(m20240429_100000_add_node_address::Migration),
+            Box::new(m20240506_100000_add_content_key_unique_index::Migration),
+            Box::new(m20240513_100000_add_content_audit_counts::Migration),
+            Box::new(m20240520_100000_add_audit_label::Migration),
+            Box::new(m20240527_100000_add_node_enr::Migration),
+            Box::new(m20240603_100000_add_audit_closer_peer_responses::Migration),
+            Box::new(m20240610_100000_add_audit_content_length::Migration),
+            Box::new(m20240617_100000_add_audit_content_id_verified::Migration),
+            Box::new(m20240624_100000_recount_content_audits::Migration),
+            Box::new(m20240701_100000_backfill_node_enr::Migration),
+            Box::new(m20240708_100000_add_audit_stats_label::Migration),
         ]
     }
 }
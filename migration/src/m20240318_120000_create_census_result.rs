@@ -0,0 +1,78 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CensusResult::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CensusResult::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(CensusResult::NodeId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_census_result_node")
+                            .from(CensusResult::Table, CensusResult::NodeId)
+                            .to(Node::Table, Node::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .col(
+                        ColumnDef::new(CensusResult::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CensusResult::PingSuccessful)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(CensusResult::RttMs).integer())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_census_result-node-time")
+                    .table(CensusResult::Table)
+                    .col(CensusResult::NodeId)
+                    .col(CensusResult::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CensusResult::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum CensusResult {
+    Table,
+    Id,
+    NodeId,         // Foreign key
+    CreatedAt,      // datetime
+    PingSuccessful, // Whether the node responded to the ping
+    RttMs,          // Round trip time of the ping (milliseconds)
+}
+
+#[derive(Iden)]
+enum Node {
+    Table,
+    Id,
+}
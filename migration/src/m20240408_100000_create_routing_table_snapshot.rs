@@ -0,0 +1,77 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RoutingTableSnapshot::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RoutingTableSnapshot::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(RoutingTableSnapshot::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RoutingTableSnapshot::LocalNodeId)
+                            .binary_len(32)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RoutingTableSnapshot::TotalPeers)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RoutingTableSnapshot::ConnectedPeers)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RoutingTableSnapshot::BucketCounts)
+                            .text()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_routing_table_snapshot-created_at")
+                    .table(RoutingTableSnapshot::Table)
+                    .col(RoutingTableSnapshot::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RoutingTableSnapshot::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum RoutingTableSnapshot {
+    Table,
+    Id,
+    CreatedAt,      // datetime
+    LocalNodeId,    // Node id of the Portal node whose routing table was read
+    TotalPeers,     // Number of entries across all buckets
+    ConnectedPeers, // Number of entries with a connected status
+    BucketCounts,   // JSON object of log distance to number of entries
+}
@@ -0,0 +1,78 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Node::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Node::LastSeen).timestamp_with_time_zone(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        // SQLite only allows one column to be added per statement.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Node::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Node::Alive)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_node-last_seen")
+                    .table(Node::Table)
+                    .col(Node::LastSeen)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_node-last_seen")
+                    .table(Node::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Node::Table)
+                    .drop_column(Node::Alive)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Node::Table)
+                    .drop_column(Node::LastSeen)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Node {
+    Table,
+    LastSeen, // When the node last answered a census ping
+    Alive,    // Whether the node answered its latest census ping
+}
@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only allows one column to be added per statement.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Node::Table)
+                    .add_column_if_not_exists(ColumnDef::new(Node::Ip).string())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Node::Table)
+                    .add_column_if_not_exists(ColumnDef::new(Node::Port).integer())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Node::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Node::InfoUpdatedAt).timestamp_with_time_zone(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [Node::InfoUpdatedAt, Node::Port, Node::Ip] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Node::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+enum Node {
+    Table,
+    Ip,            // Address advertised in the node's ENR
+    Port,          // UDP port advertised in the node's ENR
+    InfoUpdatedAt, // When the node's info was last read from the node itself
+}
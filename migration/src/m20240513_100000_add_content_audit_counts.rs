@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only allows one column to be added per statement.
+        for column in [Content::AuditCount, Content::AuditSuccessCount] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Content::Table)
+                        .add_column_if_not_exists(
+                            ColumnDef::new(column).integer().not_null().default(0),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+        // Count the audits recorded before the columns existed.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "UPDATE content SET
+                    audit_count = (
+                        SELECT COUNT(*) FROM content_audit
+                        WHERE content_audit.content_key = content.id
+                    ),
+                    audit_success_count = (
+                        SELECT COUNT(*) FROM content_audit
+                        WHERE content_audit.content_key = content.id AND content_audit.result = 1
+                    )",
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [Content::AuditSuccessCount, Content::AuditCount] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Content::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+enum Content {
+    Table,
+    AuditCount,        // Audits recorded of the content, however long ago
+    AuditSuccessCount, // Those audits that passed
+}
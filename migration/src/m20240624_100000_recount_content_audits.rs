@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Recount the audits without those where the request to the node failed, with
+        // a transport error (3), timeout (4) or JSON-RPC error (5).
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "UPDATE content SET
+                    audit_count = (
+                        SELECT COUNT(*) FROM content_audit
+                        WHERE content_audit.content_key = content.id
+                            AND (content_audit.outcome IS NULL OR content_audit.outcome NOT IN (3, 4, 5))
+                    ),
+                    audit_success_count = (
+                        SELECT COUNT(*) FROM content_audit
+                        WHERE content_audit.content_key = content.id AND content_audit.result = 1
+                    )",
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Count every audit again, as the counts did before.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "UPDATE content SET
+                    audit_count = (
+                        SELECT COUNT(*) FROM content_audit
+                        WHERE content_audit.content_key = content.id
+                    )",
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,30 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Give nodes stored before the column existed the record with their highest
+        // sequence number.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "UPDATE node SET enr = (
+                    SELECT record.raw FROM record
+                    WHERE record.node_id = node.id
+                    ORDER BY record.sequence_number DESC
+                    LIMIT 1
+                )
+                WHERE enr IS NULL",
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // The copied records cannot be told apart from those stored since.
+        Ok(())
+    }
+}
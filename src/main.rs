@@ -1,2 +1,142 @@
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+use tokio::{
+    sync::{broadcast, Semaphore},
+    time::Duration,
+};
+use tracing::{debug, info};
+
+use glados_audit::{run_glados_audit, spawn_periodic_tasks, AuditConfig};
+use glados_core::{
+    jsonrpc::{PortalApi, DEFAULT_MAX_RETRIES},
+    logging::init_logging,
+    pool::{connect, redact_database_url},
+};
+use glados_web::{
+    cli::WebArgs,
+    feed::AUDIT_FEED_CAPACITY,
+    run_glados_web,
+    state::State,
+    utils::{Cached, ProbeLimiter, NODE_VERSION_CACHE_TTL},
+};
+use migration::{Migrator, MigratorTrait};
+
+/// Time allowed for the Portal node to answer a readiness check.
+const READINESS_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Runs the audit process and the web dashboard in one process, sharing one database pool.
+    ///
+    /// Takes the same flags as glados-audit, along with the flags below for the web server.
+    AllInOne(AllInOneArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct AllInOneArgs {
+    #[command(flatten)]
+    audit: glados_audit::cli::Args,
+    #[command(flatten)]
+    web: WebArgs,
+}
+
 #[tokio::main]
-async fn main() {}
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    match args.command {
+        Command::AllInOne(args) => run_all_in_one(args).await,
+    }
+}
+
+async fn run_all_in_one(args: AllInOneArgs) -> Result<()> {
+    init_logging(&args.audit.log)?;
+    info!("Starting glados (audit and web)");
+    if args.audit.subcommand.is_some() {
+        bail!(
+            "glados-audit subcommands are not supported by all-in-one, run them with glados-audit."
+        );
+    }
+    if args.audit.start_block.is_some() {
+        bail!("Block range audits are not supported by all-in-one, run them with glados-audit.");
+    }
+
+    let tls_config = args.web.tls.load()?;
+
+    let pool = args.audit.pool.clone();
+    let config = AuditConfig::from_args(args.audit).await?;
+    let redacted_url = redact_database_url(&config.database_url);
+    debug!(database_url = %redacted_url, "Connecting to database");
+    let conn = connect(&config.database_url, &pool)
+        .await
+        .with_context(|| format!("Could not connect to the database at {redacted_url}"))?;
+    info!(
+        database_url = %redacted_url,
+        "database connection established"
+    );
+    Migrator::up(&conn, None).await?;
+
+    let (audit_feed, _) = broadcast::channel(AUDIT_FEED_CAPACITY);
+    let state = Arc::new(State {
+        database_connection: conn.clone(),
+        audit_metrics: Some(config.metrics.clone()),
+        audit_trigger: Some(config.trigger.clone()),
+        portal_api: config.portal_clients.first().map(|client| PortalApi {
+            client_url: client.api.client_url.clone(),
+            request_timeout: READINESS_REQUEST_TIMEOUT,
+            max_retries: DEFAULT_MAX_RETRIES,
+        }),
+        api_token: args.web.api_token,
+        raw_content_limiter: ProbeLimiter::per_minute(args.web.raw_content_per_minute),
+        node_version: Cached::new(NODE_VERSION_CACHE_TTL),
+        audit_feed,
+        page_limits: args.web.page_limits,
+        max_request_body_bytes: args.web.max_request_body_bytes,
+        query_limiter: args
+            .web
+            .web_max_concurrent_queries
+            .map(|limit| Arc::new(Semaphore::new(limit as usize))),
+    });
+
+    spawn_periodic_tasks(&conn, &config);
+    // The audit process returns once it has been stopped with CTRL+C and drained its queue.
+    tokio::select! {
+        _ = run_glados_audit(conn, config) => Ok(()),
+        result = run_glados_web(state, args.web.socket_addr(), tls_config) => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    /// Tests that the audit and web server flags combine without clashing.
+    #[test]
+    fn test_all_in_one_args() {
+        Args::command().debug_assert();
+        let args = Args::parse_from([
+            "glados",
+            "all-in-one",
+            "--portal-client",
+            "ipc:////path/to/ipc",
+            "--port",
+            "4000",
+            "--api-token",
+            "secret",
+        ]);
+        let Command::AllInOne(args) = args.command;
+        assert_eq!(args.audit.portal_client, vec!["ipc:////path/to/ipc"]);
+        assert_eq!(args.web.port, 4000);
+        assert_eq!(args.web.api_token.as_deref(), Some("secret"));
+    }
+}